@@ -7,6 +7,10 @@ extern crate test;
 
 use crate::coroutine::*;
 use may::coroutine;
+use may::timer_bench::{now, TimeOutList, TimerThread, TimerWheel};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
 use test::Bencher;
 
 #[bench]
@@ -170,3 +174,186 @@ fn smoke_bench_3(b: &mut Bencher) {
         }
     });
 }
+
+const TIMERS: usize = 1_000_000;
+
+// jittered timeouts like the retry backoff, between 1s and 2s
+fn random_timeouts() -> Vec<Duration> {
+    // xorshift, good enough for the jitter
+    let mut seed = 0x2545_f491_4f6c_dd1du64;
+    (0..TIMERS)
+        .map(|_| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            Duration::from_secs(1) + Duration::from_nanos(seed % 1_000_000_000)
+        })
+        .collect()
+}
+
+#[bench]
+fn timer_list_add_bench(b: &mut Bencher) {
+    let timeouts = random_timeouts();
+    b.iter(|| {
+        let list = TimeOutList::new();
+        for (i, dur) in timeouts.iter().enumerate() {
+            list.add_timer(*dur, i);
+        }
+    });
+}
+
+#[bench]
+fn timer_wheel_add_bench(b: &mut Bencher) {
+    let timeouts = random_timeouts();
+    b.iter(|| {
+        let wheel = TimerWheel::new();
+        for (i, dur) in timeouts.iter().enumerate() {
+            wheel.add_timer(*dur, i);
+        }
+    });
+}
+
+#[bench]
+fn timer_list_cancel_bench(b: &mut Bencher) {
+    let timeouts = random_timeouts();
+    b.iter(|| {
+        let list = TimeOutList::new();
+        let handles = timeouts
+            .iter()
+            .enumerate()
+            .map(|(i, dur)| list.add_timer(*dur, i).0)
+            .collect::<Vec<_>>();
+        for h in handles {
+            h.remove();
+        }
+    });
+}
+
+#[bench]
+fn timer_wheel_cancel_bench(b: &mut Bencher) {
+    let timeouts = random_timeouts();
+    b.iter(|| {
+        let wheel = TimerWheel::new();
+        let handles = timeouts
+            .iter()
+            .enumerate()
+            .map(|(i, dur)| wheel.add_timer(*dur, i).0)
+            .collect::<Vec<_>>();
+        for h in handles {
+            h.remove();
+        }
+    });
+}
+
+#[bench]
+fn timer_list_expire_bench(b: &mut Bencher) {
+    let timeouts = random_timeouts();
+    b.iter(|| {
+        let list = TimeOutList::new();
+        for (i, dur) in timeouts.iter().enumerate() {
+            list.add_timer(*dur, i);
+        }
+        // expire all the timers step by step
+        let start = now();
+        for ms in 1000..=2000 {
+            list.schedule_timer(start + ms * 1_000_000, &|i| {
+                test::black_box(i);
+            });
+        }
+    });
+}
+
+#[bench]
+fn timer_wheel_expire_bench(b: &mut Bencher) {
+    let timeouts = random_timeouts();
+    b.iter(|| {
+        let wheel = TimerWheel::new();
+        for (i, dur) in timeouts.iter().enumerate() {
+            wheel.add_timer(*dur, i);
+        }
+        // expire all the timers step by step
+        let start = now();
+        for ms in 1000..=2000 {
+            wheel.schedule_timer(start + ms * 1_000_000, &|i| {
+                test::black_box(i);
+            });
+        }
+    });
+}
+
+const THREADS: usize = 4;
+
+// each thread adds its part of the jittered timeouts and then cancels
+// them, like the io timeouts that rarely expire
+fn run_timer_threads<F>(timeouts: &Arc<Vec<Duration>>, f: F)
+where
+    F: Fn(&[Duration]) + Send + Sync + 'static,
+{
+    let f = Arc::new(f);
+    let chunk = TIMERS / THREADS;
+    let handles = (0..THREADS)
+        .map(|i| {
+            let (f, timeouts) = (f.clone(), timeouts.clone());
+            thread::spawn(move || f(&timeouts[i * chunk..(i + 1) * chunk]))
+        })
+        .collect::<Vec<_>>();
+    for h in handles {
+        h.join().unwrap();
+    }
+}
+
+#[bench]
+fn timer_list_mt_bench(b: &mut Bencher) {
+    let timeouts = Arc::new(random_timeouts());
+    b.iter(|| {
+        let list = Arc::new(TimeOutList::new());
+        run_timer_threads(&timeouts, move |timeouts| {
+            let handles = timeouts
+                .iter()
+                .enumerate()
+                .map(|(i, dur)| list.add_timer(*dur, i).0)
+                .collect::<Vec<_>>();
+            for h in handles {
+                h.remove();
+            }
+        });
+    });
+}
+
+#[bench]
+fn timer_wheel_mt_bench(b: &mut Bencher) {
+    let timeouts = Arc::new(random_timeouts());
+    b.iter(|| {
+        // all the threads share one wheel
+        let wheel = Arc::new(TimerWheel::new());
+        run_timer_threads(&timeouts, move |timeouts| {
+            let handles = timeouts
+                .iter()
+                .enumerate()
+                .map(|(i, dur)| wheel.add_timer(*dur, i).0)
+                .collect::<Vec<_>>();
+            for h in handles {
+                h.remove();
+            }
+        });
+    });
+}
+
+#[bench]
+fn timer_shards_mt_bench(b: &mut Bencher) {
+    let timeouts = Arc::new(random_timeouts());
+    b.iter(|| {
+        // each thread uses its own shard
+        let timer = Arc::new(TimerThread::new(THREADS));
+        run_timer_threads(&timeouts, move |timeouts| {
+            let handles = timeouts
+                .iter()
+                .enumerate()
+                .map(|(i, dur)| timer.add_timer(*dur, i))
+                .collect::<Vec<_>>();
+            for h in handles {
+                timer.del_timer(h);
+            }
+        });
+    });
+}
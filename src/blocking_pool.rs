@@ -0,0 +1,139 @@
+//! a dedicated thread pool for the blocking operations that can't be
+//! registered to the selector, e.g. regular file io
+//!
+//! the calling coroutine would be parked until the job is done, so that
+//! the worker thread is free to run other coroutines in the meantime
+
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Once};
+use std::thread;
+use std::time::Duration;
+
+use crate::coroutine_impl::is_coroutine;
+use crate::park::ParkError;
+use crate::sync::Blocker;
+use crossbeam::atomic::AtomicCell;
+use crossbeam::channel::{self, Receiver, RecvTimeoutError, Sender};
+
+// max number of threads that the pool would create
+const MAX_THREADS: usize = 512;
+// idle thread would exit after this duration
+const KEEP_ALIVE: Duration = Duration::from_secs(10);
+
+type Job = Box<dyn FnOnce() + Send>;
+
+struct BlockingPool {
+    tx: Sender<Job>,
+    rx: Receiver<Job>,
+    // total threads in the pool
+    threads: AtomicUsize,
+    // idle threads that waiting for jobs
+    idle: AtomicUsize,
+}
+
+static mut POOL: *const BlockingPool = std::ptr::null();
+
+fn get_pool() -> &'static BlockingPool {
+    static ONCE: Once = Once::new();
+    ONCE.call_once(|| {
+        let (tx, rx) = channel::unbounded();
+        let pool = Box::new(BlockingPool {
+            tx,
+            rx,
+            threads: AtomicUsize::new(0),
+            idle: AtomicUsize::new(0),
+        });
+        unsafe { POOL = Box::into_raw(pool) };
+    });
+    unsafe { &*POOL }
+}
+
+impl BlockingPool {
+    fn submit(&'static self, job: Job) {
+        self.tx.send(job).expect("blocking pool is closed");
+
+        // no idle thread, try to create a new one
+        if self.idle.load(Ordering::Acquire) == 0 {
+            if self.threads.fetch_add(1, Ordering::AcqRel) >= MAX_THREADS {
+                // the queued job would be picked up by existing threads
+                self.threads.fetch_sub(1, Ordering::AcqRel);
+                return;
+            }
+
+            let spawned = thread::Builder::new()
+                .name("may-blocking".to_owned())
+                .spawn(move || self.run());
+            if spawned.is_err() {
+                self.threads.fetch_sub(1, Ordering::AcqRel);
+            }
+        }
+    }
+
+    // the thread function of the pool
+    fn run(&self) {
+        loop {
+            self.idle.fetch_add(1, Ordering::AcqRel);
+            let job = self.rx.recv_timeout(KEEP_ALIVE);
+            self.idle.fetch_sub(1, Ordering::AcqRel);
+
+            match job {
+                Ok(job) => job(),
+                Err(RecvTimeoutError::Timeout) => {
+                    self.threads.fetch_sub(1, Ordering::AcqRel);
+                    // re-check the queue in case a job is pushed just before we leave
+                    match self.rx.try_recv() {
+                        Ok(job) => {
+                            self.threads.fetch_add(1, Ordering::AcqRel);
+                            job();
+                        }
+                        Err(_) => return,
+                    }
+                }
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+        }
+    }
+}
+
+/// run the blocking function in the pool and wait for its result
+///
+/// in thread context the function is called directly. in coroutine context
+/// the coroutine is parked until the function returns or the timeout expires.
+/// when timeout happens an `io::ErrorKind::TimedOut` error is returned, but the
+/// function itself can't be interrupted and would still run to completion.
+pub fn run<F, T>(f: F, timeout: Option<Duration>) -> io::Result<T>
+where
+    F: FnOnce() -> io::Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    if !is_coroutine() {
+        return f();
+    }
+
+    let blocker = Blocker::current();
+    let ret = Arc::new(AtomicCell::new(None));
+    let their_blocker = blocker.clone();
+    let their_ret = ret.clone();
+
+    get_pool().submit(Box::new(move || {
+        their_ret.swap(Some(f()));
+        their_blocker.unpark();
+    }));
+
+    match blocker.park(timeout) {
+        Ok(_) => ret
+            .take()
+            .unwrap_or_else(|| Err(io::Error::new(io::ErrorKind::Other, "no result"))),
+        Err(ParkError::Timeout) => Err(io::Error::new(io::ErrorKind::TimedOut, "timeout")),
+        Err(ParkError::Canceled) => Err(io::Error::new(io::ErrorKind::Other, "Canceled")),
+    }
+}
+
+/// run the blocking function in the pool without waiting for it
+pub fn spawn<F>(f: F)
+where
+    F: FnOnce() + Send + 'static,
+{
+    get_pool().submit(Box::new(f));
+}
@@ -1,9 +1,10 @@
 use std::io;
+use std::marker::PhantomData;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::thread;
 
-use crate::coroutine_impl::CoroutineImpl;
+use crate::coroutine_impl::{current_cancel_data, is_coroutine, CoroutineImpl};
 use crate::io::cancel::CancelIoImpl;
 use crate::scheduler::get_scheduler;
 use crate::sync::AtomicOption;
@@ -98,8 +99,12 @@ impl<T: CancelIo> CancelImpl<T> {
     }
 
     // async cancel for a coroutine
+    // if the cancel is disabled, only the cancel bit is set, and the
+    // coroutine would panic at the next yield point after it's enabled
     pub unsafe fn cancel(&self) {
-        self.state.fetch_or(1, Ordering::Release);
+        if self.state.fetch_or(1, Ordering::AcqRel) >= 2 {
+            return;
+        }
         match self.co.take(Ordering::Acquire) {
             Some(co) => {
                 co.take(Ordering::Acquire)
@@ -115,7 +120,6 @@ impl<T: CancelIo> CancelImpl<T> {
     }
 
     // clear the cancel bit so that we can reuse the cancel
-    #[cfg(unix)]
     pub fn clear_cancel_bit(&self) {
         self.state.fetch_and(!1, Ordering::Release);
     }
@@ -142,3 +146,81 @@ impl<T: CancelIo> CancelImpl<T> {
 }
 
 pub type Cancel = CancelImpl<CancelIoImpl>;
+
+/// A guard that defers the cancellation of the current coroutine
+///
+/// while the guard is alive, `Coroutine::cancel` would not interrupt the
+/// current coroutine. the blocking APIs wait as usual, and an in-flight io
+/// request is not aborted. the cancellation is recorded and delivered at the
+/// next yield point after the guard is dropped.
+///
+/// the guards can be nested, the cancellation is deferred until the outermost
+/// guard is dropped. the guard must be dropped in the coroutine that creates
+/// it, and it does nothing in a thread context.
+///
+/// # Examples
+///
+/// ```rust
+/// use may::coroutine::CancelGuard;
+///
+/// may::go!(|| {
+///     let _guard = CancelGuard::new();
+///     // the two writes would not be torn apart by a cancel
+///     // stream.write_all(&header).unwrap();
+///     // stream.write_all(&body).unwrap();
+/// })
+/// .join()
+/// .unwrap();
+/// ```
+pub struct CancelGuard {
+    cancel: Option<&'static Cancel>,
+    // the guard is bound to the current coroutine
+    _marker: PhantomData<*const ()>,
+}
+
+impl CancelGuard {
+    /// disable the cancellation of the current coroutine until the guard
+    /// is dropped
+    pub fn new() -> Self {
+        let cancel = if is_coroutine() {
+            let cancel = current_cancel_data();
+            cancel.disable_cancel();
+            Some(cancel)
+        } else {
+            None
+        };
+
+        CancelGuard {
+            cancel,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl Default for CancelGuard {
+    fn default() -> Self {
+        CancelGuard::new()
+    }
+}
+
+impl Drop for CancelGuard {
+    fn drop(&mut self) {
+        if let Some(cancel) = self.cancel {
+            cancel.enable_cancel();
+        }
+    }
+}
+
+/// run the closure with the cancellation of the current coroutine deferred
+///
+/// a cancel request that arrives in the closure is delivered at the next
+/// yield point after the closure returns. see [`CancelGuard`] for details.
+///
+/// [`CancelGuard`]: struct.CancelGuard.html
+pub fn cancel_shield<F, T>(f: F) -> T
+where
+    F: FnOnce() -> T,
+{
+    let _guard = CancelGuard::new();
+    f()
+}
@@ -2,15 +2,19 @@
 //!
 
 use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
 
 // default stack size, in usize
 // windows has a minimal size as 0x4a8!!!!
 const DEFAULT_STACK_SIZE: usize = 0x1000;
 const DEFAULT_POOL_CAPACITY: usize = 100;
+// default timer tick resolution, in ns
+const DEFAULT_TIMER_RESOLUTION: usize = 1_000_000;
 
 static WORKERS: AtomicUsize = AtomicUsize::new(0);
 static STACK_SIZE: AtomicUsize = AtomicUsize::new(DEFAULT_STACK_SIZE);
 static POOL_CAPACITY: AtomicUsize = AtomicUsize::new(DEFAULT_POOL_CAPACITY);
+static TIMER_RESOLUTION: AtomicUsize = AtomicUsize::new(DEFAULT_TIMER_RESOLUTION);
 
 /// `May` Configuration type
 pub struct Config;
@@ -85,4 +89,24 @@ impl Config {
     pub fn get_stack_size(&self) -> usize {
         STACK_SIZE.load(Ordering::Acquire)
     }
+
+    /// set the tick resolution of the timers
+    ///
+    /// all the timers are rounded up to the tick, a coarse resolution would
+    /// reduce the timer overhead. if you pass 0 to it, will use internal
+    /// default which is 1ms
+    pub fn set_timer_resolution(&self, resolution: Duration) -> &Self {
+        info!("set timer resolution={:?}", resolution);
+        let ns = resolution.as_nanos().min(usize::MAX as u128) as usize;
+        TIMER_RESOLUTION.store(ns, Ordering::Release);
+        self
+    }
+
+    /// get the tick resolution of the timers
+    pub fn get_timer_resolution(&self) -> Duration {
+        match TIMER_RESOLUTION.load(Ordering::Acquire) {
+            0 => Duration::from_nanos(DEFAULT_TIMER_RESOLUTION as u64),
+            ns => Duration::from_nanos(ns as u64),
+        }
+    }
 }
@@ -1,10 +1,12 @@
 // re-export coroutine interface
-pub use crate::cancel::trigger_cancel_panic;
+pub use crate::cancel::{cancel_shield, trigger_cancel_panic, CancelGuard};
 pub use crate::coroutine_impl::{
-    current, is_coroutine, park, park_timeout, spawn, Builder, Coroutine,
+    current, is_coroutine, park, park_or_cancel, park_timeout, spawn, Builder, Coroutine,
 };
-pub use crate::join::JoinHandle;
+pub use crate::join::{set_detached_panic_hook, JoinHandle};
 pub use crate::park::ParkError;
 pub use crate::scoped::scope;
-pub use crate::sleep::sleep;
+pub use crate::sleep::{sleep, sleep_or_cancel};
+pub use crate::stream::{stream, Stream, StreamSender};
+pub use crate::task_group::{GroupError, TaskGroup};
 pub use crate::yield_now::yield_now;
@@ -1,7 +1,7 @@
 use std::cell::UnsafeCell;
 use std::fmt;
 use std::io;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use crate::cancel::Cancel;
@@ -10,7 +10,8 @@ use crate::join::{make_join_handle, Join, JoinHandle};
 use crate::local::get_co_local_data;
 use crate::local::CoroutineLocal;
 use crate::park::Park;
-use crate::scheduler::get_scheduler;
+use crate::scheduler::{get_scheduler, worker_id};
+use crate::sync::{CancellationToken, Cancelled};
 use crossbeam::atomic::AtomicCell;
 use generator::{Generator, Gn};
 
@@ -95,6 +96,13 @@ fn get_co_local(co: &CoroutineImpl) -> *mut CoroutineLocal {
     co.get_local_data() as *mut CoroutineLocal
 }
 
+// get the worker that the coroutine is pinned to
+#[inline]
+pub(crate) fn pinned_worker(co: &CoroutineImpl) -> Option<usize> {
+    let local = unsafe { &*get_co_local(co) };
+    local.get_co().inner.worker
+}
+
 /// /////////////////////////////////////////////////////////////////////////////
 /// Coroutine
 /// /////////////////////////////////////////////////////////////////////////////
@@ -103,6 +111,7 @@ fn get_co_local(co: &CoroutineImpl) -> *mut CoroutineLocal {
 struct Inner {
     name: Option<String>,
     stack_size: usize,
+    worker: Option<usize>,
     park: Park,
     cancel: Cancel,
 }
@@ -117,11 +126,12 @@ unsafe impl Send for Coroutine {}
 
 impl Coroutine {
     // Used only internally to construct a coroutine object without spawning
-    fn new(name: Option<String>, stack_size: usize) -> Coroutine {
+    fn new(name: Option<String>, stack_size: usize, worker: Option<usize>) -> Coroutine {
         Coroutine {
             inner: Arc::new(Inner {
                 name,
                 stack_size,
+                worker,
                 park: Park::new(),
                 cancel: Cancel::new(),
             }),
@@ -144,6 +154,10 @@ impl Coroutine {
     /// This function would force a coroutine exist when next scheduling
     /// And would drop all the resource tha the coroutine currently holding
     /// This may have unexpected side effects if you are not fully aware it
+    ///
+    /// The cancel is deferred if the coroutine is inside a [`CancelGuard`]
+    ///
+    /// [`CancelGuard`]: struct.CancelGuard.html
     pub unsafe fn cancel(&self) {
         self.inner.cancel.cancel();
     }
@@ -179,6 +193,7 @@ impl fmt::Debug for Coroutine {
 ///
 /// - [`name`]: specifies an [associated name for the coroutine][naming-coroutines]
 /// - [`stack_size`]: specifies the [desired stack size for the coroutine][stack-size]
+/// - [`worker`]: pins the coroutine to a worker thread
 ///
 /// The [`spawn`] method will take ownership of the builder and create an
 /// `io::Result` to the coroutine handle with the given configuration.
@@ -208,6 +223,7 @@ impl fmt::Debug for Coroutine {
 /// [`coroutine::spawn`]: ./fn.spawn.html
 /// [`stack_size`]: ./struct.Builder.html#method.stack_size
 /// [`name`]: ./struct.Builder.html#method.name
+/// [`worker`]: ./struct.Builder.html#method.worker
 /// [`spawn`]: ./struct.Builder.html#method.spawn
 /// [naming-coroutines]: ./index.html#naming-coroutine
 /// [stack-size]: ./index.html#stack-siz
@@ -217,6 +233,8 @@ pub struct Builder {
     name: Option<String>,
     // The size of the stack for the spawned coroutine
     stack_size: Option<usize>,
+    // The worker thread that the coroutine is pinned to
+    worker: Option<usize>,
 }
 
 impl Builder {
@@ -226,6 +244,7 @@ impl Builder {
         Builder {
             name: None,
             stack_size: None,
+            worker: None,
         }
     }
 
@@ -242,6 +261,22 @@ impl Builder {
         self
     }
 
+    /// Pins the new coroutine to the worker thread `id`.
+    ///
+    /// the coroutine is never stolen by other workers, and it's always resumed
+    /// on the worker `id` no matter which thread wakes it up. this is useful to
+    /// keep the work of an io object on the worker whose selector it's
+    /// registered to, e.g. the listeners returned by [`bind_sharded`].
+    ///
+    /// spawning fails with `InvalidInput` if `id` is not less than the
+    /// number of workers.
+    ///
+    /// [`bind_sharded`]: ../net/struct.TcpListenerBuilder.html#method.bind_sharded
+    pub fn worker(mut self, id: usize) -> Builder {
+        self.worker = Some(id);
+        self
+    }
+
     /// Spawns a new coroutine, and returns a join handle for it.
     /// The join handle can be used to block on
     /// termination of the child coroutine, including recovering its panics.
@@ -253,7 +288,19 @@ impl Builder {
         static DONE: Done = Done {};
 
         let sched = get_scheduler();
-        let Builder { name, stack_size } = self;
+        let Builder {
+            name,
+            stack_size,
+            worker,
+        } = self;
+        if let Some(id) = worker {
+            if id >= sched.get_selector().size() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "worker id out of range",
+                ));
+            }
+        }
         let stack_size = stack_size.unwrap_or_else(|| config().get_stack_size());
         let _co = if stack_size == config().get_stack_size() {
             let co = sched.pool.get();
@@ -294,7 +341,7 @@ impl Builder {
             Gn::new_opt(stack_size, closure)
         };
 
-        let handle = Coroutine::new(name, stack_size);
+        let handle = Coroutine::new(name, stack_size, worker);
         // create the local storage
         let local = CoroutineLocal::new(handle.clone(), join.clone());
         // attache the local storage to the coroutine
@@ -500,9 +547,34 @@ pub fn park_timeout(dur: Duration) {
     park_timeout_impl(Some(dur));
 }
 
+/// block the current coroutine until it's get unparked or the token is cancelled
+///
+/// return `Err(Cancelled)` if the token is cancelled when waking up
+pub fn park_or_cancel(token: &CancellationToken) -> Result<(), Cancelled> {
+    if is_coroutine() {
+        // the coroutine handle is not `Sync`, guard it for the waker
+        let co = Mutex::new(current());
+        let _waker = token.register(Box::new(move || co.lock().unwrap().unpark()));
+        park_timeout_impl(None);
+    }
+
+    if token.is_cancelled() {
+        Err(Cancelled(()))
+    } else {
+        Ok(())
+    }
+}
+
 /// run the coroutine
 #[inline]
 pub(crate) fn run_coroutine(mut co: CoroutineImpl) {
+    // a pinned coroutine can only run on its own worker
+    if let Some(id) = pinned_worker(&co) {
+        if worker_id() != id {
+            return get_scheduler().schedule_pinned(id, co);
+        }
+    }
+
     match co.resume() {
         Some(ev) => ev.subscribe(co),
         None => {
@@ -512,6 +584,7 @@ pub(crate) fn run_coroutine(mut co: CoroutineImpl) {
             // set the panic data
             if let Some(panic) = co.get_panic_data() {
                 join.set_panic_data(panic);
+                join.set_panicked(local.get_co().name());
             }
             // trigger the join here
             join.trigger();
@@ -1,7 +1,7 @@
 use std::panic;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use crate::cancel::Cancel;
 use crate::coroutine_impl::{
@@ -11,6 +11,7 @@ use crate::join::JoinHandle;
 use crate::scoped::spawn_unsafe;
 use crate::sync::Mutex;
 use crate::sync::{AtomicOption, Blocker};
+use crate::time;
 use crate::yield_now::yield_with;
 
 use crossbeam::queue::SegQueue as Queue;
@@ -145,6 +146,9 @@ impl<'a> Drop for EventSender<'a> {
     }
 }
 
+// the token and the join handle of a select coroutine
+type SelectorHandle = (usize, JoinHandle<()>);
+
 /// cqueue interface for general select model
 pub struct Cqueue {
     // the mpsc queue that transfer event
@@ -153,8 +157,8 @@ pub struct Cqueue {
     to_wake: AtomicOption<Arc<Blocker>>,
     // track how many coroutines left
     cnt: AtomicUsize,
-    // store the select coroutine handles
-    selectors: Mutex<Vec<Option<JoinHandle<()>>>>,
+    // store the select coroutine handles with the tokens
+    selectors: Mutex<Vec<Option<SelectorHandle>>>,
     // total created select coroutines
     total: AtomicUsize,
     // panic status
@@ -180,7 +184,7 @@ impl Cqueue {
         self.cnt.fetch_add(1, Ordering::Relaxed);
 
         self.total.fetch_add(1, Ordering::Relaxed);
-        self.selectors.lock().unwrap().push(Some(h));
+        self.selectors.lock().unwrap().push(Some((token, h)));
         Selector { co }
     }
 
@@ -194,6 +198,32 @@ impl Cqueue {
         self.add_impl(token, f)
     }
 
+    /// terminate all the select coroutines associated with the token
+    /// this is the same as calling `remove` on their `Selector` handles
+    ///
+    /// return the number of the select coroutines that are removed
+    pub fn remove(&self, token: usize) -> usize {
+        let selectors = self.selectors.lock().unwrap();
+        let mut removed = 0;
+        for (_, j) in selectors.iter().flatten().filter(|s| s.0 == token) {
+            if !j.is_done() {
+                unsafe { j.coroutine().cancel() };
+                removed += 1;
+            }
+        }
+        removed
+    }
+
+    /// return the number of the active select coroutines
+    pub fn len(&self) -> usize {
+        self.cnt.load(Ordering::Relaxed)
+    }
+
+    /// return true if there is no active select coroutine
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
     // when the select coroutine is done, check the panic status
     // if it's panicked, re throw the panic data
     fn check_panic(&self, id: usize) {
@@ -205,6 +235,7 @@ impl Cqueue {
         match self.selectors.lock().unwrap()[id]
             .take()
             .expect("join handler not set")
+            .1
             .join()
         {
             Ok(_) => {}
@@ -226,6 +257,9 @@ impl Cqueue {
     /// the API is "completion" mode
     /// if any panic in select coroutine detected during the poll
     /// it will propagate the panic to the caller
+    ///
+    /// the timeout is the deadline of the whole poll, a zero timeout
+    /// would not block at all
     pub fn poll(&self, timeout: Option<Duration>) -> Result<Event, PollError> {
         macro_rules! run_ev {
             ($ev:ident) => {{
@@ -238,7 +272,7 @@ impl Cqueue {
             }};
         }
 
-        let deadline = timeout.map(|dur| Instant::now() + dur);
+        let deadline = timeout.map(|dur| time::blocking_now() + dur);
         loop {
             match self.ev_queue.pop() {
                 Some(mut ev) => run_ev!(ev),
@@ -249,29 +283,63 @@ impl Cqueue {
                 }
             }
 
+            // park with the time left before the deadline
+            let dur = match deadline {
+                Some(d) => {
+                    let now = time::blocking_now();
+                    if now >= d {
+                        return Err(PollError::Timeout);
+                    }
+                    Some(d - now)
+                }
+                None => None,
+            };
+
             let cur = Blocker::current();
             // register the waiter
             self.to_wake.swap(cur.clone(), Ordering::Release);
             // re-check the queue
             match self.ev_queue.pop() {
                 None => {
-                    cur.park(timeout).ok();
+                    cur.park(dur).ok();
                 }
                 Some(mut ev) => {
                     if let Some(w) = self.to_wake.take(Ordering::Relaxed) {
                         w.unpark();
                     }
-                    cur.park(timeout).ok();
+                    cur.park(dur).ok();
                     run_ev!(ev);
                 }
             }
+        }
+    }
 
-            // check the timeout
-            match deadline {
-                Some(d) if Instant::now() >= d => return Err(PollError::Timeout),
-                _ => {}
+    /// poll at most `max` events that are ready to process
+    /// it blocks until the first event comes like `poll`, then collects
+    /// the rest ready events without blocking
+    ///
+    /// the events are appended to `events`, return the number of them
+    pub fn poll_many(
+        &self,
+        events: &mut Vec<Event>,
+        max: usize,
+        timeout: Option<Duration>,
+    ) -> Result<usize, PollError> {
+        if max == 0 {
+            return Ok(0);
+        }
+
+        events.push(self.poll(timeout)?);
+        let mut n = 1;
+        while n < max {
+            match self.poll(Some(Duration::from_secs(0))) {
+                Ok(ev) => events.push(ev),
+                // report the error in the next poll
+                Err(_) => break,
             }
+            n += 1;
         }
+        Ok(n)
     }
 }
 
@@ -284,7 +352,7 @@ impl Drop for Cqueue {
             .lock()
             .unwrap()
             .iter()
-            .map(|j| j.as_ref())
+            .map(|j| j.as_ref().map(|s| &s.1))
             .fold((), |_, join| match join {
                 Some(j) if !j.is_done() => unsafe { j.coroutine().cancel() },
                 _ => {}
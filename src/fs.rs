@@ -0,0 +1,386 @@
+//! Filesystem manipulation operations
+//!
+//! Regular files can't be registered to the selector, so all the operations
+//! in this module are dispatched to a dedicated blocking thread pool. The
+//! calling coroutine is parked until the operation is done, so it won't block
+//! the worker thread. In thread context the operations are called directly.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use may::fs::File;
+//! use std::io::prelude::*;
+//!
+//! let mut file = File::create("foo.txt").unwrap();
+//! file.write_all(b"Hello, world!").unwrap();
+//!
+//! let contents = may::fs::read_to_string("foo.txt").unwrap();
+//! assert_eq!(contents, "Hello, world!");
+//! ```
+
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::blocking_pool;
+use crate::sync::atomic_dur::AtomicDuration;
+
+/// A reference to an open file on the filesystem.
+///
+/// This is a wrapper of `std::fs::File`, every blocking operation is executed
+/// in the blocking thread pool. If a read or write timeout is set and expires
+/// before the operation is done, an `io::ErrorKind::TimedOut` error is returned.
+/// Notice that the underlying operation can't be interrupted and would still
+/// complete in the background, so the file cursor may be changed after a timeout.
+///
+/// Cancellation has the same semantics as `may::net` types, the coroutine is
+/// unwound at the blocking point.
+#[derive(Debug)]
+pub struct File {
+    sys: Arc<fs::File>,
+    read_timeout: AtomicDuration,
+    write_timeout: AtomicDuration,
+}
+
+impl File {
+    fn from_std(f: fs::File) -> File {
+        File {
+            sys: Arc::new(f),
+            read_timeout: AtomicDuration::new(None),
+            write_timeout: AtomicDuration::new(None),
+        }
+    }
+
+    /// Attempts to open a file in read-only mode.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<File> {
+        OpenOptions::new().read(true).open(path)
+    }
+
+    /// Opens a file in write-only mode.
+    ///
+    /// This function will create a file if it does not exist,
+    /// and will truncate it if it does.
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<File> {
+        OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)
+    }
+
+    /// get the inner std file ref
+    pub fn inner(&self) -> &fs::File {
+        &self.sys
+    }
+
+    /// Attempts to sync all OS-internal metadata to disk.
+    pub fn sync_all(&self) -> io::Result<()> {
+        let f = self.sys.clone();
+        blocking_pool::run(move || f.sync_all(), self.write_timeout.get())
+    }
+
+    /// This function is similar to `sync_all`, except that it may not
+    /// synchronize file metadata to the filesystem.
+    pub fn sync_data(&self) -> io::Result<()> {
+        let f = self.sys.clone();
+        blocking_pool::run(move || f.sync_data(), self.write_timeout.get())
+    }
+
+    /// Truncates or extends the underlying file, updating the size of
+    /// this file to become `size`.
+    pub fn set_len(&self, size: u64) -> io::Result<()> {
+        let f = self.sys.clone();
+        blocking_pool::run(move || f.set_len(size), self.write_timeout.get())
+    }
+
+    /// Queries metadata about the underlying file.
+    pub fn metadata(&self) -> io::Result<fs::Metadata> {
+        let f = self.sys.clone();
+        blocking_pool::run(move || f.metadata(), self.read_timeout.get())
+    }
+
+    /// Changes the permissions on the underlying file.
+    pub fn set_permissions(&self, perm: fs::Permissions) -> io::Result<()> {
+        let f = self.sys.clone();
+        blocking_pool::run(move || f.set_permissions(perm), self.write_timeout.get())
+    }
+
+    /// Creates a new `File` instance that shares the same underlying file handle
+    /// as the existing `File` instance.
+    pub fn try_clone(&self) -> io::Result<File> {
+        let f = File::from_std(self.sys.try_clone()?);
+        f.set_read_timeout(self.read_timeout.get())?;
+        f.set_write_timeout(self.write_timeout.get())?;
+        Ok(f)
+    }
+
+    /// Sets the read timeout for the file.
+    pub fn set_read_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        self.read_timeout.swap(dur);
+        Ok(())
+    }
+
+    /// Sets the write timeout for the file.
+    pub fn set_write_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        self.write_timeout.swap(dur);
+        Ok(())
+    }
+
+    /// Returns the read timeout of the file.
+    pub fn read_timeout(&self) -> io::Result<Option<Duration>> {
+        Ok(self.read_timeout.get())
+    }
+
+    /// Returns the write timeout of the file.
+    pub fn write_timeout(&self) -> io::Result<Option<Duration>> {
+        Ok(self.write_timeout.get())
+    }
+}
+
+impl Read for File {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let f = self.sys.clone();
+        let len = buf.len();
+        let data = blocking_pool::run(
+            move || {
+                let mut data = vec![0; len];
+                let n = (&*f).read(&mut data)?;
+                data.truncate(n);
+                Ok(data)
+            },
+            self.read_timeout.get(),
+        )?;
+        buf[..data.len()].copy_from_slice(&data);
+        Ok(data.len())
+    }
+}
+
+impl Write for File {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let f = self.sys.clone();
+        let data = buf.to_vec();
+        blocking_pool::run(move || (&*f).write(&data), self.write_timeout.get())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let f = self.sys.clone();
+        blocking_pool::run(move || (&*f).flush(), self.write_timeout.get())
+    }
+}
+
+impl Seek for File {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let f = self.sys.clone();
+        blocking_pool::run(move || (&*f).seek(pos), self.read_timeout.get())
+    }
+}
+
+/// Options and flags which can be used to configure how a file is opened.
+///
+/// This is a wrapper of `std::fs::OpenOptions`, the `open` operation is
+/// executed in the blocking thread pool.
+#[derive(Clone, Debug)]
+pub struct OpenOptions(fs::OpenOptions);
+
+impl OpenOptions {
+    /// Creates a blank new set of options ready for configuration.
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> OpenOptions {
+        OpenOptions(fs::OpenOptions::new())
+    }
+
+    /// Sets the option for read access.
+    pub fn read(&mut self, read: bool) -> &mut OpenOptions {
+        self.0.read(read);
+        self
+    }
+
+    /// Sets the option for write access.
+    pub fn write(&mut self, write: bool) -> &mut OpenOptions {
+        self.0.write(write);
+        self
+    }
+
+    /// Sets the option for the append mode.
+    pub fn append(&mut self, append: bool) -> &mut OpenOptions {
+        self.0.append(append);
+        self
+    }
+
+    /// Sets the option for truncating a previous file.
+    pub fn truncate(&mut self, truncate: bool) -> &mut OpenOptions {
+        self.0.truncate(truncate);
+        self
+    }
+
+    /// Sets the option to create a new file, or open it if it already exists.
+    pub fn create(&mut self, create: bool) -> &mut OpenOptions {
+        self.0.create(create);
+        self
+    }
+
+    /// Sets the option to create a new file, failing if it already exists.
+    pub fn create_new(&mut self, create_new: bool) -> &mut OpenOptions {
+        self.0.create_new(create_new);
+        self
+    }
+
+    /// Opens a file at `path` with the options specified by `self`.
+    pub fn open<P: AsRef<Path>>(&self, path: P) -> io::Result<File> {
+        let opts = self.0.clone();
+        let path = path.as_ref().to_owned();
+        blocking_pool::run(move || opts.open(path), None).map(File::from_std)
+    }
+}
+
+/// Iterator over the entries in a directory.
+///
+/// This is returned from the [`read_dir`] function. All the entries are
+/// collected in the blocking thread pool, so iterating over it never blocks.
+///
+/// [`read_dir`]: fn.read_dir.html
+#[derive(Debug)]
+pub struct ReadDir(::std::vec::IntoIter<io::Result<fs::DirEntry>>);
+
+impl Iterator for ReadDir {
+    type Item = io::Result<fs::DirEntry>;
+
+    fn next(&mut self) -> Option<io::Result<fs::DirEntry>> {
+        self.0.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+fn run_path<F, T>(path: &Path, f: F) -> io::Result<T>
+where
+    F: FnOnce(PathBuf) -> io::Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    let path = path.to_owned();
+    blocking_pool::run(move || f(path), None)
+}
+
+/// Read the entire contents of a file into a bytes vector.
+pub fn read<P: AsRef<Path>>(path: P) -> io::Result<Vec<u8>> {
+    run_path(path.as_ref(), fs::read)
+}
+
+/// Read the entire contents of a file into a string.
+pub fn read_to_string<P: AsRef<Path>>(path: P) -> io::Result<String> {
+    run_path(path.as_ref(), fs::read_to_string)
+}
+
+/// Write a slice as the entire contents of a file.
+pub fn write<P: AsRef<Path>, C: AsRef<[u8]>>(path: P, contents: C) -> io::Result<()> {
+    let contents = contents.as_ref().to_vec();
+    run_path(path.as_ref(), move |p| fs::write(p, contents))
+}
+
+/// Given a path, query the file system to get information about a file,
+/// directory, etc.
+pub fn metadata<P: AsRef<Path>>(path: P) -> io::Result<fs::Metadata> {
+    run_path(path.as_ref(), fs::metadata)
+}
+
+/// Returns an iterator over the entries within a directory.
+pub fn read_dir<P: AsRef<Path>>(path: P) -> io::Result<ReadDir> {
+    run_path(path.as_ref(), |p| {
+        fs::read_dir(p).map(|dir| ReadDir(dir.collect::<Vec<_>>().into_iter()))
+    })
+}
+
+/// Removes a file from the filesystem.
+pub fn remove_file<P: AsRef<Path>>(path: P) -> io::Result<()> {
+    run_path(path.as_ref(), fs::remove_file)
+}
+
+/// Recursively create a directory and all of its parent components if they
+/// are missing.
+pub fn create_dir_all<P: AsRef<Path>>(path: P) -> io::Result<()> {
+    run_path(path.as_ref(), fs::create_dir_all)
+}
+
+// ===== UNIX ext =====
+//
+//
+
+#[cfg(unix)]
+use std::os::unix::io::{AsRawFd, RawFd};
+
+#[cfg(unix)]
+impl AsRawFd for File {
+    fn as_raw_fd(&self) -> RawFd {
+        self.sys.as_raw_fd()
+    }
+}
+
+// ===== Windows ext =====
+//
+//
+
+#[cfg(windows)]
+use std::os::windows::io::{AsRawHandle, RawHandle};
+
+#[cfg(windows)]
+impl AsRawHandle for File {
+    fn as_raw_handle(&self) -> RawHandle {
+        self.sys.as_raw_handle()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn file_read_write() {
+        let dir = TempDir::new("fs").unwrap();
+        let path = dir.path().join("a.txt");
+
+        let h = go!(move || {
+            let mut f = File::create(&path).unwrap();
+            f.write_all(b"hello may").unwrap();
+            f.sync_all().unwrap();
+            drop(f);
+
+            let mut f = File::open(&path).unwrap();
+            let mut s = String::new();
+            f.read_to_string(&mut s).unwrap();
+            assert_eq!(s, "hello may");
+
+            f.seek(SeekFrom::Start(6)).unwrap();
+            let mut buf = [0; 3];
+            f.read_exact(&mut buf).unwrap();
+            assert_eq!(&buf, b"may");
+            assert_eq!(f.metadata().unwrap().len(), 9);
+        });
+        h.join().unwrap();
+    }
+
+    #[test]
+    fn fs_functions() {
+        let dir = TempDir::new("fs").unwrap();
+        let root = dir.path().to_owned();
+
+        let h = go!(move || {
+            let sub = root.join("sub");
+            create_dir_all(&sub).unwrap();
+            write(sub.join("a"), b"a").unwrap();
+            write(sub.join("b"), b"bb").unwrap();
+
+            assert_eq!(read(sub.join("a")).unwrap(), b"a");
+            assert_eq!(metadata(sub.join("b")).unwrap().len(), 2);
+            assert_eq!(read_dir(&sub).unwrap().count(), 2);
+
+            remove_file(sub.join("a")).unwrap();
+            assert!(metadata(sub.join("a")).is_err());
+        });
+        h.join().unwrap();
+    }
+}
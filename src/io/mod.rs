@@ -12,6 +12,10 @@ pub(crate) mod sys;
 pub mod co_io_err;
 
 mod event_loop;
+#[cfg(unix)]
+mod readiness;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod zero_copy;
 
 use std::io;
 use std::ops::Deref;
@@ -20,10 +24,16 @@ use std::sync::atomic::{AtomicBool, Ordering};
 use crate::coroutine_impl::is_coroutine;
 
 pub(crate) use self::event_loop::EventLoop;
+#[cfg(unix)]
+pub use self::readiness::{Interest, Readiness, RegisterOptions};
+#[cfg(unix)]
+pub(crate) use self::sys::add_socket_to;
 pub use self::sys::co_io::CoIo;
 #[cfg(unix)]
 pub use self::sys::wait_io::WaitIo;
 pub(crate) use self::sys::{add_socket, cancel, net, IoData, Selector};
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub use self::zero_copy::{sendfile, splice, SplicePipe};
 
 pub trait AsIoData {
     fn as_io_data(&self) -> &IoData;
@@ -0,0 +1,473 @@
+//! readiness based io for the fds that are driven by other libraries
+//!
+//! some C libraries (e.g. libpq, c-ares) own their sockets and only expose
+//! the fds, `Readiness` registers such a fd to the selector so that the
+//! coroutine could wait for the readiness and then call the library.
+
+use std::io;
+use std::ops::BitOr;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+use super::sys::wait_io::wait_io_timeout;
+use super::sys::{add_socket_with, reregister_socket, IoData, READABLE, WRITABLE};
+use crate::coroutine_impl::is_coroutine;
+use crate::time;
+
+/// The readiness that a [`Readiness`] waits for
+///
+/// [`Readiness`]: struct.Readiness.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Interest(usize);
+
+impl Interest {
+    /// interest in the readable readiness
+    pub const READABLE: Interest = Interest(READABLE);
+    /// interest in the writable readiness
+    pub const WRITABLE: Interest = Interest(WRITABLE);
+
+    /// return true if the interest contains readable
+    pub fn is_readable(self) -> bool {
+        self.0 & READABLE != 0
+    }
+
+    /// return true if the interest contains writable
+    pub fn is_writable(self) -> bool {
+        self.0 & WRITABLE != 0
+    }
+
+    pub(crate) fn bits(self) -> usize {
+        self.0
+    }
+}
+
+impl BitOr for Interest {
+    type Output = Interest;
+
+    fn bitor(self, other: Interest) -> Interest {
+        Interest(self.0 | other.0)
+    }
+}
+
+/// The options to register a fd to the selector
+///
+/// the default is edge triggered on both readable and writable, which is
+/// what all the coroutine io types use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegisterOptions {
+    pub(crate) interest: Interest,
+    pub(crate) level_triggered: bool,
+    pub(crate) oneshot: bool,
+    pub(crate) exclusive: bool,
+}
+
+impl Default for RegisterOptions {
+    fn default() -> Self {
+        RegisterOptions::new()
+    }
+}
+
+impl RegisterOptions {
+    /// create the default options
+    pub fn new() -> Self {
+        RegisterOptions {
+            interest: Interest::READABLE | Interest::WRITABLE,
+            level_triggered: false,
+            oneshot: false,
+            exclusive: false,
+        }
+    }
+
+    /// set the readiness that the selector would report
+    pub fn interest(&mut self, interest: Interest) -> &mut Self {
+        self.interest = interest;
+        self
+    }
+
+    /// use level triggered mode instead of edge triggered
+    ///
+    /// in level triggered mode the selector keeps reporting the readiness
+    /// until the condition is consumed, which would busy loop the selector
+    /// thread if nobody handles it in time.
+    pub fn level_triggered(&mut self, level: bool) -> &mut Self {
+        self.level_triggered = level;
+        self
+    }
+
+    /// disable the fd after one event is reported
+    ///
+    /// the fd must be re-armed by [`Readiness::reregister`] to get the next
+    /// event. it can't be used together with [`exclusive`].
+    ///
+    /// [`exclusive`]: #method.exclusive
+    ///
+    /// [`Readiness::reregister`]: struct.Readiness.html#method.reregister
+    pub fn oneshot(&mut self, oneshot: bool) -> &mut Self {
+        self.oneshot = oneshot;
+        self
+    }
+
+    /// set `EPOLLEXCLUSIVE` to avoid the thundering herd when the same fd
+    /// is registered in multiple epoll instances, e.g. a listener shared by
+    /// multiple processes
+    ///
+    /// this is ignored on the kqueue platforms. epoll doesn't allow to
+    /// modify an exclusive registration, so [`Readiness::reregister`] with
+    /// `exclusive` set removes the fd and adds it back, and `exclusive` must
+    /// be kept set when reregistering an exclusive fd.
+    ///
+    /// [`Readiness::reregister`]: struct.Readiness.html#method.reregister
+    pub fn exclusive(&mut self, exclusive: bool) -> &mut Self {
+        self.exclusive = exclusive;
+        self
+    }
+
+    // check the options before registering
+    fn validate(&self) -> io::Result<()> {
+        if self.exclusive && self.oneshot {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "exclusive can't be used with oneshot",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Wrapper of a fd that waits for the readiness in coroutine context
+///
+/// the fd must be in nonblocking mode, `Readiness` never changes it. the
+/// readiness is recorded once the selector reports it and is kept until
+/// [`clear_ready`] is called, which is done by [`try_io`] automatically when
+/// the io returns `WouldBlock`.
+///
+/// # Examples
+///
+/// ```no_run
+/// use may::io::{Interest, Readiness};
+/// use std::io::{ErrorKind, Read};
+/// use std::net::TcpStream;
+///
+/// let stream = TcpStream::connect("127.0.0.1:8080").unwrap();
+/// stream.set_nonblocking(true).unwrap();
+/// let stream = Readiness::new(stream).unwrap();
+///
+/// let mut buf = [0; 1024];
+/// let n = loop {
+///     stream.readable(None).unwrap();
+///     match stream.try_io(Interest::READABLE, |s| (&*s).read(&mut buf)) {
+///         Err(ref e) if e.kind() == ErrorKind::WouldBlock => continue,
+///         ret => break ret.unwrap(),
+///     }
+/// };
+/// ```
+///
+/// [`clear_ready`]: #method.clear_ready
+/// [`try_io`]: #method.try_io
+#[derive(Debug)]
+pub struct Readiness<T: AsRawFd> {
+    // io must be dropped before inner
+    io: IoData,
+    inner: T,
+}
+
+impl<T: AsRawFd> Readiness<T> {
+    /// register the fd to the selector
+    pub fn new(inner: T) -> io::Result<Self> {
+        Readiness::with_options(inner, &RegisterOptions::new())
+    }
+
+    /// register the fd to the selector with the given options
+    pub fn with_options(inner: T, opts: &RegisterOptions) -> io::Result<Self> {
+        opts.validate()?;
+        let io = add_socket_with(&inner, opts)?;
+        Ok(Readiness { io, inner })
+    }
+
+    /// modify the registration options without deregistering the fd
+    ///
+    /// this also re-arms the fd that is registered in oneshot mode. the
+    /// recorded readiness is not changed.
+    pub fn reregister(&self, opts: &RegisterOptions) -> io::Result<()> {
+        opts.validate()?;
+        reregister_socket(&self.io, opts)
+    }
+
+    /// get inner ref
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// get inner mut ref
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// deregister the fd and return the inner object
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// wait until the fd is readable
+    ///
+    /// return `io::ErrorKind::TimedOut` error if the fd is not readable
+    /// before the timeout
+    pub fn readable(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.ready(Interest::READABLE, timeout).map(|_| ())
+    }
+
+    /// wait until the fd is writable
+    ///
+    /// return `io::ErrorKind::TimedOut` error if the fd is not writable
+    /// before the timeout
+    pub fn writable(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.ready(Interest::WRITABLE, timeout).map(|_| ())
+    }
+
+    /// wait until the fd is ready for any of the interest
+    ///
+    /// return the readiness that is ready now
+    pub fn ready(&self, interest: Interest, timeout: Option<Duration>) -> io::Result<Interest> {
+        if !is_coroutine() {
+            return poll(self.inner.as_raw_fd(), interest, timeout);
+        }
+
+        let deadline = timeout.map(|dur| time::now() + dur);
+        loop {
+            // clear the io flag before checking the readiness, so that any
+            // event comes after the check would wake us up
+            self.io.reset();
+            let ready = self.io.ready.load(Ordering::Acquire) & interest.bits();
+            if ready != 0 {
+                return Ok(Interest(ready));
+            }
+
+            let timeout = match deadline {
+                None => None,
+                Some(deadline) => {
+                    let now = time::now();
+                    if now >= deadline {
+                        return Err(io::Error::new(io::ErrorKind::TimedOut, "timeout"));
+                    }
+                    Some(deadline - now)
+                }
+            };
+            wait_io_timeout(&self.io, timeout)?;
+        }
+    }
+
+    /// clear the recorded readiness
+    ///
+    /// the next [`readable`] or [`writable`] call would wait for a new event
+    /// from the selector.
+    ///
+    /// [`readable`]: #method.readable
+    /// [`writable`]: #method.writable
+    pub fn clear_ready(&self, interest: Interest) {
+        self.io.ready.fetch_and(!interest.bits(), Ordering::AcqRel);
+    }
+
+    /// run the io function and clear the readiness if it returns `WouldBlock`
+    ///
+    /// the `WouldBlock` error is returned to the caller, who should wait for
+    /// the readiness again before retry. the readiness is kept if the
+    /// selector reports a new event while the io function is running, so
+    /// that the event is not lost in edge triggered mode.
+    pub fn try_io<R, F>(&self, interest: Interest, f: F) -> io::Result<R>
+    where
+        F: FnOnce(&T) -> io::Result<R>,
+    {
+        let ready = self.io.ready.load(Ordering::Acquire);
+        let ret = f(&self.inner);
+        if let Err(ref e) = ret {
+            if e.kind() == io::ErrorKind::WouldBlock {
+                // fails if the ready word is changed by a new event
+                let clear = ready & !interest.bits();
+                let _ = self.io.ready.compare_exchange(
+                    ready,
+                    clear,
+                    Ordering::AcqRel,
+                    Ordering::Relaxed,
+                );
+            }
+        }
+        ret
+    }
+}
+
+impl<T: AsRawFd> AsRawFd for Readiness<T> {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+}
+
+// in thread context just poll the fd
+fn poll(fd: RawFd, interest: Interest, timeout: Option<Duration>) -> io::Result<Interest> {
+    let mut events = 0;
+    if interest.is_readable() {
+        events |= libc::POLLIN;
+    }
+    if interest.is_writable() {
+        events |= libc::POLLOUT;
+    }
+    let mut pfd = libc::pollfd {
+        fd,
+        events,
+        revents: 0,
+    };
+    let timeout_ms = timeout
+        .map(|dur| dur.as_millis().min(libc::c_int::MAX as u128) as libc::c_int)
+        .unwrap_or(-1);
+
+    loop {
+        let ret = unsafe { libc::poll(&mut pfd, 1, timeout_ms) };
+        if ret < 0 {
+            let err = io::Error::last_os_error();
+            if err.kind() == io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(err);
+        }
+        if ret == 0 {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "timeout"));
+        }
+        break;
+    }
+
+    let mut ready = 0;
+    if pfd.revents & (libc::POLLIN | libc::POLLHUP | libc::POLLERR) != 0 {
+        ready |= READABLE;
+    }
+    if pfd.revents & (libc::POLLOUT | libc::POLLHUP | libc::POLLERR) != 0 {
+        ready |= WRITABLE;
+    }
+    Ok(Interest(ready & interest.bits()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::os::unix::net::UnixStream;
+
+    #[test]
+    fn readiness() {
+        let (a, mut b) = UnixStream::pair().unwrap();
+        a.set_nonblocking(true).unwrap();
+        let h = go!(move || {
+            let a = Readiness::new(a).unwrap();
+            a.writable(None).unwrap();
+            let err = a.readable(Some(Duration::from_millis(50))).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+
+            let mut buf = [0; 4];
+            let n = loop {
+                a.readable(None).unwrap();
+                match a.try_io(Interest::READABLE, |s| (&*s).read(&mut buf)) {
+                    Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => continue,
+                    ret => break ret.unwrap(),
+                }
+            };
+            assert_eq!(&buf[..n], b"ping");
+
+            // the readiness is cleared after WouldBlock
+            let ret = a.try_io(Interest::READABLE, |s| (&*s).read(&mut buf));
+            assert_eq!(ret.unwrap_err().kind(), io::ErrorKind::WouldBlock);
+            let err = a.readable(Some(Duration::from_millis(20))).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        });
+
+        ::std::thread::sleep(Duration::from_millis(100));
+        b.write_all(b"ping").unwrap();
+        h.join().unwrap();
+    }
+
+    #[test]
+    fn event_during_try_io() {
+        let (a, mut b) = UnixStream::pair().unwrap();
+        a.set_nonblocking(true).unwrap();
+        let h = go!(move || {
+            let a = Readiness::new(a).unwrap();
+            let mut buf = [0; 4];
+            let ret = a.try_io(Interest::READABLE, |s| {
+                let ret = (&*s).read(&mut buf);
+                // the data comes after the failed read but before the clear
+                b.write_all(b"ping").unwrap();
+                crate::coroutine::sleep(Duration::from_millis(50));
+                ret
+            });
+            assert_eq!(ret.unwrap_err().kind(), io::ErrorKind::WouldBlock);
+
+            // the new event is not lost
+            a.readable(Some(Duration::from_secs(1))).unwrap();
+            a.try_io(Interest::READABLE, |s| (&*s).read_exact(&mut buf))
+                .unwrap();
+            assert_eq!(&buf, b"ping");
+        });
+        h.join().unwrap();
+    }
+
+    #[test]
+    fn register_options() {
+        let (a, mut b) = UnixStream::pair().unwrap();
+        a.set_nonblocking(true).unwrap();
+        b.write_all(b"ping").unwrap();
+        let h = go!(move || {
+            // level triggered reports the pending data again
+            let mut opts = RegisterOptions::new();
+            opts.interest(Interest::READABLE).level_triggered(true);
+            let a = Readiness::with_options(a, &opts).unwrap();
+            a.readable(None).unwrap();
+            a.clear_ready(Interest::READABLE);
+            a.readable(Some(Duration::from_secs(1))).unwrap();
+            let mut buf = [0; 4];
+            a.try_io(Interest::READABLE, |s| (&*s).read_exact(&mut buf))
+                .unwrap();
+            a.clear_ready(Interest::READABLE);
+
+            // oneshot is disabled after the first event until re-armed
+            opts.level_triggered(false).oneshot(true);
+            a.reregister(&opts).unwrap();
+            a.readable(None).unwrap();
+            a.try_io(Interest::READABLE, |s| (&*s).read_exact(&mut buf))
+                .unwrap();
+            a.clear_ready(Interest::READABLE);
+            crate::coroutine::sleep(Duration::from_millis(50));
+            let err = a.readable(Some(Duration::from_millis(50))).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+            a.reregister(&opts).unwrap();
+            a.readable(Some(Duration::from_secs(1))).unwrap();
+        });
+
+        ::std::thread::sleep(Duration::from_millis(50));
+        b.write_all(b"pong").unwrap();
+        ::std::thread::sleep(Duration::from_millis(50));
+        b.write_all(b"more").unwrap();
+        h.join().unwrap();
+    }
+
+    #[test]
+    fn exclusive_reregister() {
+        let (a, mut b) = UnixStream::pair().unwrap();
+        a.set_nonblocking(true).unwrap();
+        let h = go!(move || {
+            let mut opts = RegisterOptions::new();
+            opts.interest(Interest::READABLE).exclusive(true);
+            let a = Readiness::with_options(a, &opts).unwrap();
+
+            // exclusive can't be combined with oneshot
+            opts.oneshot(true);
+            let err = a.reregister(&opts).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+            opts.oneshot(false);
+            a.reregister(&opts).unwrap();
+            a.readable(Some(Duration::from_secs(1))).unwrap();
+        });
+
+        ::std::thread::sleep(Duration::from_millis(50));
+        b.write_all(b"ping").unwrap();
+        h.join().unwrap();
+    }
+}
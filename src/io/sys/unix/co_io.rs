@@ -9,8 +9,10 @@ use std::time::Duration;
 
 use self::io_impl::co_io_err::Error;
 use self::io_impl::net as net_impl;
+use super::wait_io::io_or_cancel;
 use crate::io as io_impl;
 use crate::sync::atomic_dur::AtomicDuration;
+use crate::sync::CancellationToken;
 use crate::yield_now::yield_with;
 
 fn set_nonblocking<T: AsRawFd>(fd: &T, nb: bool) -> io::Result<()> {
@@ -38,8 +40,10 @@ fn set_nonblocking<T: AsRawFd>(fd: &T, nb: bool) -> io::Result<()> {
 /// this type can be used in coroutine context without blocking the thread
 #[derive(Debug)]
 pub struct CoIo<T: AsRawFd> {
-    inner: T,
+    // io must be dropped before inner, so that the fd is removed from
+    // the selector before it's closed
     io: io_impl::IoData,
+    inner: T,
     ctx: io_impl::IoContext,
     read_timeout: AtomicDuration,
     write_timeout: AtomicDuration,
@@ -149,6 +153,66 @@ impl<T: AsRawFd> CoIo<T> {
         self.ctx.set_nonblocking(nb);
         Ok(())
     }
+
+    /// recvmsg with a prepared msghdr
+    pub(crate) fn recv_msg_hdr(
+        &self,
+        hdr: &mut libc::msghdr,
+        flags: libc::c_int,
+    ) -> io::Result<usize> {
+        if !self.ctx_check()? {
+            // this can't be nonblocking!!
+            return net_impl::recvmsg(self.as_raw_fd(), hdr, flags);
+        }
+
+        self.io.reset();
+        // this is an earlier return try for nonblocking read
+        match net_impl::recvmsg(self.as_raw_fd(), hdr, flags) {
+            Ok(n) => return Ok(n),
+            Err(e) => {
+                // raw_os_error is faster than kind
+                let raw_err = e.raw_os_error();
+                if raw_err == Some(libc::EAGAIN) || raw_err == Some(libc::EWOULDBLOCK) {
+                    // do nothing here
+                } else {
+                    return Err(e);
+                }
+            }
+        }
+
+        let timeout = self.read_timeout.get();
+        let mut reader = net_impl::SocketRecvMsg::new(self, hdr, flags, timeout);
+        yield_with(&reader);
+        reader.done()
+    }
+
+    /// sendmsg with a prepared msghdr
+    pub(crate) fn send_msg_hdr(&self, hdr: &libc::msghdr, flags: libc::c_int) -> io::Result<usize> {
+        if !self.ctx_check()? {
+            // this can't be nonblocking!!
+            return net_impl::sendmsg(self.as_raw_fd(), hdr, flags);
+        }
+
+        self.io.reset();
+        // this is an earlier return try for nonblocking write
+        match net_impl::sendmsg(self.as_raw_fd(), hdr, flags) {
+            Ok(n) => return Ok(n),
+            Err(e) => {
+                // raw_os_error is faster than kind
+                let raw_err = e.raw_os_error();
+                if raw_err == Some(libc::EAGAIN) || raw_err == Some(libc::EWOULDBLOCK) {
+                    // do nothing here
+                } else {
+                    return Err(e);
+                }
+            }
+        }
+
+        let timeout = self.write_timeout.get();
+        let mut writer = net_impl::SocketSendMsg::new(self, hdr, flags, timeout);
+        yield_with(&writer);
+        writer.done()
+    }
 }
 
 impl<T: AsRawFd + Read> Read for CoIo<T> {
@@ -178,6 +242,33 @@ impl<T: AsRawFd + Read> Read for CoIo<T> {
         yield_with(&reader);
         reader.done()
     }
+
+    fn read_vectored(&mut self, bufs: &mut [io::IoSliceMut<'_>]) -> io::Result<usize> {
+        if !self.ctx_check()? {
+            // this can't be nonblocking!!
+            return self.inner.read_vectored(bufs);
+        }
+
+        self.io.reset();
+        // this is an earlier return try for nonblocking read
+        match self.inner.read_vectored(bufs) {
+            Ok(n) => return Ok(n),
+            Err(e) => {
+                // raw_os_error is faster than kind
+                let raw_err = e.raw_os_error();
+                if raw_err == Some(libc::EAGAIN) || raw_err == Some(libc::EWOULDBLOCK) {
+                    // do nothing here
+                } else {
+                    return Err(e);
+                }
+            }
+        }
+
+        let timeout = self.read_timeout.get();
+        let mut reader = net_impl::SocketReadVectored::new(self, bufs, timeout);
+        yield_with(&reader);
+        reader.done()
+    }
 }
 
 impl<T: AsRawFd + Write> Write for CoIo<T> {
@@ -207,11 +298,69 @@ impl<T: AsRawFd + Write> Write for CoIo<T> {
         writer.done()
     }
 
+    fn write_vectored(&mut self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize> {
+        if !self.ctx_check()? {
+            // this can't be nonblocking!!
+            return self.inner.write_vectored(bufs);
+        }
+
+        self.io.reset();
+        // this is an earlier return try for nonblocking write
+        match self.inner.write_vectored(bufs) {
+            Ok(n) => return Ok(n),
+            Err(e) => {
+                // raw_os_error is faster than kind
+                let raw_err = e.raw_os_error();
+                if raw_err == Some(libc::EAGAIN) || raw_err == Some(libc::EWOULDBLOCK) {
+                    // do nothing here
+                } else {
+                    return Err(e);
+                }
+            }
+        }
+
+        let mut writer = net_impl::SocketWriteVectored::new(self, bufs, self.write_timeout.get());
+        yield_with(&writer);
+        writer.done()
+    }
+
     fn flush(&mut self) -> io::Result<()> {
         self.inner.flush()
     }
 }
 
+impl<T: AsRawFd + Read> CoIo<T> {
+    /// read from the inner io, or return an error built from `Cancelled`
+    /// once the token is cancelled
+    ///
+    /// the token is only checked before the read when not running in a
+    /// coroutine, because the blocking read can't be interrupted
+    pub fn read_or_cancel(
+        &mut self,
+        buf: &mut [u8],
+        token: &CancellationToken,
+    ) -> io::Result<usize> {
+        let ctx = self.ctx_check()?;
+        let timeout = self.read_timeout.get();
+        let CoIo { io, inner, .. } = self;
+        io_or_cancel(io, ctx, timeout, token, || inner.read(buf))
+    }
+}
+
+impl<T: AsRawFd + Write> CoIo<T> {
+    /// write to the inner io, or return an error built from `Cancelled`
+    /// once the token is cancelled
+    ///
+    /// the token is only checked before the write when not running in a
+    /// coroutine, because the blocking write can't be interrupted
+    pub fn write_or_cancel(&mut self, buf: &[u8], token: &CancellationToken) -> io::Result<usize> {
+        let ctx = self.ctx_check()?;
+        let timeout = self.write_timeout.get();
+        let CoIo { io, inner, .. } = self;
+        io_or_cancel(io, ctx, timeout, token, || inner.write(buf))
+    }
+}
+
 // impl<'a, T: AsRawFd + Read> Read for &'a CoIo<T> {
 //     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
 //         let s = unsafe { &mut *(*self as *const _ as *mut _) };
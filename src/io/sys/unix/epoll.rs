@@ -2,10 +2,11 @@ use std::os::unix::io::RawFd;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::time::Duration;
-use std::{cmp, io, isize, ptr};
+use std::{cmp, io, isize};
 
-use super::{from_nix_error, timeout_handler, EventData, IoData, TimerList};
+use super::{from_nix_error, timeout_handler, EventData, IoData, TimerList, READABLE, WRITABLE};
 use crate::coroutine_impl::run_coroutine;
+use crate::io::RegisterOptions;
 use crate::scheduler::get_scheduler;
 use crate::timeout_list::{now, ns_to_ms};
 use crossbeam::queue::SegQueue as mpsc;
@@ -24,6 +25,21 @@ fn create_eventfd() -> io::Result<RawFd> {
 
 pub type SysEvent = EpollEvent;
 
+// convert the epoll events to the readiness bits
+// the error and hang up events would wake up both readers and writers
+fn event_ready(events: EpollFlags) -> usize {
+    let mut ready = 0;
+    if events.intersects(
+        EpollFlags::EPOLLIN | EpollFlags::EPOLLRDHUP | EpollFlags::EPOLLHUP | EpollFlags::EPOLLERR,
+    ) {
+        ready |= READABLE;
+    }
+    if events.intersects(EpollFlags::EPOLLOUT | EpollFlags::EPOLLHUP | EpollFlags::EPOLLERR) {
+        ready |= WRITABLE;
+    }
+    ready
+}
+
 struct SingleSelector {
     epfd: RawFd,
     evfd: RawFd,
@@ -77,6 +93,12 @@ impl Selector {
         Ok(s)
     }
 
+    // number of the single selectors
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.vec.len()
+    }
+
     pub fn select(
         &self,
         id: usize,
@@ -115,6 +137,7 @@ impl Selector {
             }
             let data = unsafe { &mut *(event.data() as *mut EventData) };
             // info!("select got event, data={:p}", data);
+            data.set_ready(event_ready(event.events()));
             data.io_flag.store(true, Ordering::Release);
 
             // first check the atomic co, this may be grab by the worker first
@@ -124,15 +147,10 @@ impl Selector {
             };
             co.prefetch();
 
-            // it's safe to remove the timer since we are running the timer_list in the same thread
-            data.timer.borrow_mut().take().map(|h| {
-                unsafe {
-                    // tell the timer handler not to cancel the io
-                    // it's not always true that you can really remove the timer entry
-                    h.with_mut_data(|value| value.data.event_data = ptr::null_mut());
-                }
-                h.remove()
-            });
+            // remove the timer so that the timer handler would not cancel the io
+            if let Some(h) = data.timer.borrow_mut().take() {
+                h.remove();
+            }
 
             // schedule the coroutine
             run_coroutine(co);
@@ -151,6 +169,15 @@ impl Selector {
         Ok(next_expire)
     }
 
+    // get the earliest io timeout of all the workers in wall clock ns
+    #[cfg(feature = "test-util")]
+    pub fn next_timer_deadline(&self) -> Option<u64> {
+        self.vec
+            .iter()
+            .filter_map(|s| s.timer_list.next_deadline())
+            .min()
+    }
+
     // this will post an os event so that we can wake up the event loop
     #[inline]
     pub fn wakeup(&self, id: usize) {
@@ -162,42 +189,71 @@ impl Selector {
     // register io event to the selector
     #[inline]
     pub fn add_fd(&self, io_data: IoData) -> io::Result<IoData> {
-        let mut info = EpollEvent::new(
-            EpollFlags::EPOLLIN
-                | EpollFlags::EPOLLOUT
-                | EpollFlags::EPOLLRDHUP
-                | EpollFlags::EPOLLET,
-            io_data.as_ref() as *const _ as _,
-        );
+        self.add_fd_with(io_data, &RegisterOptions::new())
+    }
 
-        let fd = io_data.fd;
-        let id = fd as usize % self.vec.len();
-        let single_selector = unsafe { self.vec.get_unchecked(id) };
-        let epfd = single_selector.epfd;
-        info!("add fd to epoll select, fd={:?}", fd);
-        epoll_ctl(epfd, EpollOp::EpollCtlAdd, fd, &mut info)
-            .map_err(from_nix_error)
+    // register io event to the selector with the given options
+    pub fn add_fd_with(&self, io_data: IoData, opts: &RegisterOptions) -> io::Result<IoData> {
+        info!("add fd to epoll select, fd={:?}", io_data.fd);
+        self.epoll_ctl(EpollOp::EpollCtlAdd, &io_data, opts)
             .map(|_| io_data)
     }
 
+    // modify the registration of a registered io
+    pub fn reregister(&self, io_data: &IoData, opts: &RegisterOptions) -> io::Result<()> {
+        info!("modify fd in epoll select, fd={:?}", io_data.fd);
+        if opts.exclusive {
+            // an exclusive registration can't be modified, add it again
+            self.epoll_ctl(EpollOp::EpollCtlDel, io_data, opts)?;
+            return self.epoll_ctl(EpollOp::EpollCtlAdd, io_data, opts);
+        }
+        self.epoll_ctl(EpollOp::EpollCtlMod, io_data, opts)
+    }
+
+    fn epoll_ctl(&self, op: EpollOp, io_data: &IoData, opts: &RegisterOptions) -> io::Result<()> {
+        let mut flags = EpollFlags::empty();
+        // the exclusive flag is only allowed when adding the fd
+        let exclusive = opts.exclusive && op == EpollOp::EpollCtlAdd;
+        if opts.interest.is_readable() {
+            flags |= EpollFlags::EPOLLIN;
+            // EPOLLRDHUP is not allowed with EPOLLEXCLUSIVE, the peer
+            // shutdown is still reported as readable
+            if !exclusive {
+                flags |= EpollFlags::EPOLLRDHUP;
+            }
+        }
+        if opts.interest.is_writable() {
+            flags |= EpollFlags::EPOLLOUT;
+        }
+        if !opts.level_triggered {
+            flags |= EpollFlags::EPOLLET;
+        }
+        if opts.oneshot {
+            flags |= EpollFlags::EPOLLONESHOT;
+        }
+        if exclusive {
+            flags |= EpollFlags::EPOLLEXCLUSIVE;
+        }
+        let mut info = EpollEvent::new(flags, io_data.as_ref() as *const _ as _);
+
+        let id = io_data.selector_hint % self.vec.len();
+        let single_selector = unsafe { self.vec.get_unchecked(id) };
+        epoll_ctl(single_selector.epfd, op, io_data.fd, &mut info).map_err(from_nix_error)
+    }
+
     #[inline]
     pub fn del_fd(&self, io_data: &IoData) {
         use std::ops::Deref;
 
         let mut info = EpollEvent::empty();
 
+        // remove the timer if any, this only happened when cancel an IO
         if let Some(h) = io_data.timer.borrow_mut().take() {
-            unsafe {
-                // mark the timer as removed if any, this only happened
-                // when cancel an IO. what if the timer expired at the same time?
-                // because we run this func in the user space, so the timer handler
-                // will not got the coroutine
-                h.with_mut_data(|value| value.data.event_data = ptr::null_mut());
-            }
+            h.remove();
         }
 
         let fd = io_data.fd;
-        let id = fd as usize % self.vec.len();
+        let id = io_data.selector_hint % self.vec.len();
         let single_selector = unsafe { self.vec.get_unchecked(id) };
         let epfd = single_selector.epfd;
         info!("del fd from epoll select, fd={:?}", fd);
@@ -218,7 +274,7 @@ impl Selector {
     // register the io request to the timeout list
     #[inline]
     pub fn add_io_timer(&self, io: &IoData, timeout: Duration) {
-        let id = io.fd as usize % self.vec.len();
+        let id = io.selector_hint % self.vec.len();
         // info!("io timeout = {:?}", dur);
         let (h, b_new) = unsafe { self.vec.get_unchecked(id) }
             .timer_list
@@ -5,12 +5,13 @@ use std::time::Duration;
 use std::{io, ptr};
 
 use crate::coroutine_impl::run_coroutine;
+use crate::io::RegisterOptions;
 use crate::scheduler::get_scheduler;
 use crate::timeout_list::{now, ns_to_dur};
 use crossbeam::queue::SegQueue as mpsc;
 use smallvec::SmallVec;
 
-use super::{timeout_handler, EventData, IoData, TimerList};
+use super::{timeout_handler, EventData, IoData, TimerList, READABLE, WRITABLE};
 
 pub type SysEvent = libc::kevent;
 
@@ -90,6 +91,12 @@ impl Selector {
         Ok(s)
     }
 
+    // number of the single selectors
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.vec.len()
+    }
+
     pub fn select(
         &self,
         id: usize,
@@ -149,6 +156,12 @@ impl Selector {
             }
             let data = unsafe { &mut *(event.udata as *mut EventData) };
             // info!("select got event, data={:p}", data);
+            let ready = match event.filter {
+                libc::EVFILT_READ => READABLE,
+                libc::EVFILT_WRITE => WRITABLE,
+                _ => 0,
+            };
+            data.set_ready(ready);
             data.io_flag.store(true, Ordering::Release);
 
             // first check the atomic co, this may be grab by the worker first
@@ -158,15 +171,10 @@ impl Selector {
             };
             co.prefetch();
 
-            // it's safe to remove the timer since we are running the timer_list in the same thread
-            data.timer.borrow_mut().take().map(|h| {
-                unsafe {
-                    // tell the timer handler not to cancel the io
-                    // it's not always true that you can really remove the timer entry
-                    h.with_mut_data(|value| value.data.event_data = ptr::null_mut());
-                }
-                h.remove()
-            });
+            // remove the timer so that the timer handler would not cancel the io
+            if let Some(h) = data.timer.borrow_mut().take() {
+                h.remove();
+            }
 
             // schedule the coroutine
             run_coroutine(co);
@@ -185,6 +193,15 @@ impl Selector {
         Ok(next_expire)
     }
 
+    // get the earliest io timeout of all the workers in wall clock ns
+    #[cfg(feature = "test-util")]
+    pub fn next_timer_deadline(&self) -> Option<u64> {
+        self.vec
+            .iter()
+            .filter_map(|s| s.timer_list.next_deadline())
+            .min()
+    }
+
     // this will post an os event so that we can wakeup the event loop
     #[inline]
     pub fn wakeup(&self, id: usize) {
@@ -206,17 +223,58 @@ impl Selector {
     // register io event to the selector
     #[inline]
     pub fn add_fd(&self, io_data: IoData) -> io::Result<IoData> {
+        self.add_fd_with(io_data, &RegisterOptions::new())
+    }
+
+    // register io event to the selector with the given options
+    pub fn add_fd_with(&self, io_data: IoData, opts: &RegisterOptions) -> io::Result<IoData> {
+        info!("add fd to kqueue select, fd={:?}", io_data.fd);
+        self.kevent_add(&io_data, opts).map(|_| io_data)
+    }
+
+    // modify the registration of a registered io
+    pub fn reregister(&self, io_data: &IoData, opts: &RegisterOptions) -> io::Result<()> {
         let fd = io_data.fd;
-        let id = fd as usize % self.vec.len();
+        let id = io_data.selector_hint % self.vec.len();
         let kqfd = unsafe { self.vec.get_unchecked(id) }.kqfd;
-        info!("add fd to kqueue select, fd={:?}", fd);
+        info!("modify fd in kqueue select, fd={:?}", fd);
 
-        let flags = libc::EV_ADD | libc::EV_CLEAR;
-        let udata = io_data.as_ref() as *const _;
-        let changes = [
-            kevent!(fd, libc::EVFILT_READ, flags, udata),
-            kevent!(fd, libc::EVFILT_WRITE, flags, udata),
+        // remove the filters that are not interested any more, the filter
+        // may not exist so the error is ignored
+        let filters = [
+            (libc::EVFILT_READ, opts.interest.is_readable()),
+            (libc::EVFILT_WRITE, opts.interest.is_writable()),
         ];
+        for &(filter, interested) in filters.iter() {
+            if !interested {
+                let change = kevent!(fd, filter, libc::EV_DELETE, ptr::null_mut::<EventData>());
+                unsafe { libc::kevent(kqfd, &change, 1, ptr::null_mut(), 0, ptr::null()) };
+            }
+        }
+        self.kevent_add(io_data, opts)
+    }
+
+    fn kevent_add(&self, io_data: &IoData, opts: &RegisterOptions) -> io::Result<()> {
+        let fd = io_data.fd;
+        let id = io_data.selector_hint % self.vec.len();
+        let kqfd = unsafe { self.vec.get_unchecked(id) }.kqfd;
+
+        // there is no exclusive wakeup for kqueue
+        let mut flags = libc::EV_ADD;
+        if !opts.level_triggered {
+            flags |= libc::EV_CLEAR;
+        }
+        if opts.oneshot {
+            flags |= libc::EV_ONESHOT;
+        }
+        let udata = io_data.as_ref() as *const _;
+        let mut changes = SmallVec::<[libc::kevent; 2]>::new();
+        if opts.interest.is_readable() {
+            changes.push(kevent!(fd, libc::EVFILT_READ, flags, udata));
+        }
+        if opts.interest.is_writable() {
+            changes.push(kevent!(fd, libc::EVFILT_WRITE, flags, udata));
+        }
 
         let n = unsafe {
             libc::kevent(
@@ -231,26 +289,20 @@ impl Selector {
         if n < 0 {
             return Err(io::Error::last_os_error());
         }
-
-        Ok(io_data)
+        Ok(())
     }
 
     #[inline]
     pub fn del_fd(&self, io_data: &IoData) {
         use std::ops::Deref;
 
-        io_data.timer.borrow_mut().take().map(|h| {
-            unsafe {
-                // mark the timer as removed if any, this only happened
-                // when cancel an IO. what if the timer expired at the same time?
-                // because we run this func in the user space, so the timer handler
-                // will not got the coroutine
-                h.with_mut_data(|value| value.data.event_data = ptr::null_mut());
-            }
-        });
+        // remove the timer if any, this only happened when cancel an IO
+        if let Some(h) = io_data.timer.borrow_mut().take() {
+            h.remove();
+        }
 
         let fd = io_data.fd;
-        let id = fd as usize % self.vec.len();
+        let id = io_data.selector_hint % self.vec.len();
         let single_selector = unsafe { self.vec.get_unchecked(id) };
         let kqfd = single_selector.kqfd;
         info!("del fd from kqueue select, fd={:?}", fd);
@@ -287,7 +339,7 @@ impl Selector {
     // register the io request to the timeout list
     #[inline]
     pub fn add_io_timer(&self, io: &IoData, timeout: Duration) {
-        let id = io.fd as usize % self.vec.len();
+        let id = io.selector_hint % self.vec.len();
         // info!("io timeout = {:?}", dur);
         let (h, b_new) = unsafe { self.vec.get_unchecked(id) }
             .timer_list
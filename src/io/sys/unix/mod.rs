@@ -22,14 +22,15 @@ pub mod wait_io;
 use std::cell::RefCell;
 use std::ops::Deref;
 use std::os::unix::io::{AsRawFd, RawFd};
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
-use std::{fmt, io, ptr};
+use std::{fmt, io};
 
 use crate::coroutine_impl::{run_coroutine, CoroutineImpl};
+use crate::io::RegisterOptions;
 use crate::scheduler::get_scheduler;
 use crate::sync::AtomicOption;
-use crate::timeout_list::{TimeOutList, TimeoutHandle};
+use crate::timer_wheel::{TimerHandle as WheelHandle, TimerWheel};
 use crate::yield_now::{get_co_para, set_co_para};
 
 pub use self::select::{Selector, SysEvent};
@@ -39,6 +40,28 @@ pub fn add_socket<T: AsRawFd + ?Sized>(t: &T) -> io::Result<IoData> {
     get_scheduler().get_selector().add_fd(IoData::new(t))
 }
 
+// register the fd to the given selector instead of the one chosen by fd
+#[inline]
+pub fn add_socket_to<T: AsRawFd + ?Sized>(t: &T, selector_id: usize) -> io::Result<IoData> {
+    get_scheduler()
+        .get_selector()
+        .add_fd(IoData::with_selector(t, selector_id))
+}
+
+// register the fd to the selector with the given options
+#[inline]
+pub fn add_socket_with<T: AsRawFd + ?Sized>(t: &T, opts: &RegisterOptions) -> io::Result<IoData> {
+    get_scheduler()
+        .get_selector()
+        .add_fd_with(IoData::new(t), opts)
+}
+
+// modify the registration options of the registered io
+#[inline]
+pub fn reregister_socket(io: &IoData, opts: &RegisterOptions) -> io::Result<()> {
+    get_scheduler().get_selector().reregister(io, opts)
+}
+
 #[inline]
 fn del_socket(io: &IoData) {
     // transfer the io to the selector
@@ -91,14 +114,26 @@ pub struct TimerData {
     event_data: *mut EventData,
 }
 
-pub type TimerList = TimeOutList<TimerData>;
-pub type TimerHandle = TimeoutHandle<TimerData>;
+pub type TimerList = TimerWheel<TimerData>;
+pub type TimerHandle = WheelHandle<TimerData>;
+
+// the readiness bits that the selector records in the event data
+pub const READABLE: usize = 0b01;
+pub const WRITABLE: usize = 0b10;
+// the ready word counts the events above the readiness bits, so that the
+// user could tell if there is a new event since it checked the readiness
+pub const READY_TICK: usize = 0b100;
 
 // event associated io data, must be construct in
 // each file handle, the epoll event.data would point to it
 pub struct EventData {
     pub fd: RawFd,
+    // used to choose the single selector that the fd is registered to
+    pub selector_hint: usize,
     pub io_flag: AtomicBool,
+    // the readiness got from the selector and the event tick,
+    // the readiness is only cleared by the user
+    pub ready: AtomicUsize,
     pub timer: RefCell<Option<TimerHandle>>,
     pub co: AtomicOption<CoroutineImpl>,
 }
@@ -110,12 +145,30 @@ impl EventData {
     pub fn new(fd: RawFd) -> EventData {
         EventData {
             fd,
+            selector_hint: fd as usize,
             io_flag: AtomicBool::new(false),
+            ready: AtomicUsize::new(0),
             timer: RefCell::new(None),
             co: AtomicOption::none(),
         }
     }
 
+    // record the readiness reported by the selector
+    #[inline]
+    pub fn set_ready(&self, ready: usize) {
+        let mut cur = self.ready.load(Ordering::Relaxed);
+        loop {
+            let new = (cur | ready).wrapping_add(READY_TICK);
+            match self
+                .ready
+                .compare_exchange_weak(cur, new, Ordering::Release, Ordering::Relaxed)
+            {
+                Ok(_) => return,
+                Err(v) => cur = v,
+            }
+        }
+    }
+
     pub fn timer_data(&self) -> TimerData {
         TimerData {
             event_data: self as *const _ as *mut _,
@@ -130,15 +183,10 @@ impl EventData {
             Some(co) => co,
         };
 
-        // it's safe to remove the timer since we are running the timer_list in the same thread
-        self.timer.borrow_mut().take().map(|h| {
-            unsafe {
-                // tell the timer function not to cancel the io
-                // it's not always true that you can really remove the timer entry
-                h.with_mut_data(|value| value.data.event_data = ptr::null_mut());
-            }
-            h.remove()
-        });
+        // remove the timer so that the timer handler would not cancel the io
+        if let Some(h) = self.timer.borrow_mut().take() {
+            h.remove();
+        }
 
         // schedule the coroutine
         run_coroutine(co);
@@ -155,6 +203,12 @@ impl IoData {
         IoData(event_data)
     }
 
+    pub fn with_selector<T: AsRawFd + ?Sized>(t: &T, selector_id: usize) -> Self {
+        let mut event_data = EventData::new(t.as_raw_fd());
+        event_data.selector_hint = selector_id;
+        IoData(Arc::new(event_data))
+    }
+
     // clear the io flag
     #[inline]
     pub fn reset(&self) {
@@ -1,8 +1,16 @@
+mod socket_msg;
 mod socket_read;
+mod socket_read_vectored;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod socket_sendfile;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod socket_splice;
 mod socket_write;
 mod socket_write_vectored;
 mod tcp_listener_accpet;
 mod tcp_stream_connect;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod udp_batch;
 mod udp_recv_from;
 mod udp_send_to;
 mod unix_listener_accpet;
@@ -10,11 +18,19 @@ mod unix_recv_from;
 mod unix_send_to;
 mod unix_stream_connect;
 
+pub use self::socket_msg::{recvmsg, sendmsg, SocketRecvMsg, SocketSendMsg};
 pub use self::socket_read::SocketRead;
+pub use self::socket_read_vectored::{readv, SocketReadVectored};
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub use self::socket_sendfile::{sendfile, SocketSendFile};
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub use self::socket_splice::{splice, SocketSplice};
 pub use self::socket_write::SocketWrite;
-pub use self::socket_write_vectored::SocketWriteVectored;
+pub use self::socket_write_vectored::{writev, SocketWriteVectored};
 pub use self::tcp_listener_accpet::TcpListenerAccept;
 pub use self::tcp_stream_connect::TcpStreamConnect;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub use self::udp_batch::{recv_mmsg, send_mmsg, UdpRecvBatch, UdpSendBatch};
 pub use self::udp_recv_from::UdpRecvFrom;
 pub use self::udp_send_to::UdpSendTo;
 pub use self::unix_listener_accpet::UnixListenerAccept;
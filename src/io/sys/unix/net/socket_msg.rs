@@ -0,0 +1,177 @@
+use std::io;
+use std::os::unix::io::RawFd;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+use super::super::{co_io_result, IoData};
+use crate::coroutine_impl::{co_get_handle, CoroutineImpl, EventSource};
+use crate::io::AsIoData;
+use crate::scheduler::get_scheduler;
+use crate::yield_now::yield_with;
+
+// recvmsg with a prepared msghdr, the result is filled into the msghdr
+pub struct SocketRecvMsg<'a> {
+    io_data: &'a IoData,
+    hdr: &'a mut libc::msghdr,
+    flags: libc::c_int,
+    timeout: Option<Duration>,
+}
+
+impl<'a> SocketRecvMsg<'a> {
+    pub fn new<T: AsIoData>(
+        s: &'a T,
+        hdr: &'a mut libc::msghdr,
+        flags: libc::c_int,
+        timeout: Option<Duration>,
+    ) -> Self {
+        SocketRecvMsg {
+            io_data: s.as_io_data(),
+            hdr,
+            flags,
+            timeout,
+        }
+    }
+
+    pub fn done(&mut self) -> io::Result<usize> {
+        loop {
+            co_io_result()?;
+
+            // clear the io_flag
+            self.io_data.io_flag.store(false, Ordering::Relaxed);
+
+            match recvmsg(self.io_data.fd, self.hdr, self.flags) {
+                Ok(n) => return Ok(n),
+                Err(e) => {
+                    let raw_err = e.raw_os_error();
+                    if raw_err == Some(libc::EAGAIN) || raw_err == Some(libc::EWOULDBLOCK) {
+                        // do nothing here
+                    } else {
+                        return Err(e);
+                    }
+                }
+            }
+
+            if self.io_data.io_flag.swap(false, Ordering::Relaxed) {
+                continue;
+            }
+
+            // the result is still WouldBlock, need to try again
+            yield_with(self);
+        }
+    }
+}
+
+impl<'a> EventSource for SocketRecvMsg<'a> {
+    fn subscribe(&mut self, co: CoroutineImpl) {
+        let handle = co_get_handle(&co);
+        let cancel = handle.get_cancel();
+        let io_data = (*self.io_data).clone();
+
+        if let Some(dur) = self.timeout {
+            get_scheduler()
+                .get_selector()
+                .add_io_timer(self.io_data, dur);
+        }
+        self.io_data.co.swap(co, Ordering::Release);
+
+        // there is event, re-run the coroutine
+        if io_data.io_flag.load(Ordering::Acquire) {
+            return io_data.schedule();
+        }
+
+        // register the cancel io data
+        cancel.set_io(io_data);
+        // re-check the cancel status
+        if cancel.is_canceled() {
+            unsafe { cancel.cancel() };
+        }
+    }
+}
+
+// sendmsg with a prepared msghdr
+pub struct SocketSendMsg<'a> {
+    io_data: &'a IoData,
+    hdr: &'a libc::msghdr,
+    flags: libc::c_int,
+    timeout: Option<Duration>,
+}
+
+impl<'a> SocketSendMsg<'a> {
+    pub fn new<T: AsIoData>(
+        s: &'a T,
+        hdr: &'a libc::msghdr,
+        flags: libc::c_int,
+        timeout: Option<Duration>,
+    ) -> Self {
+        SocketSendMsg {
+            io_data: s.as_io_data(),
+            hdr,
+            flags,
+            timeout,
+        }
+    }
+
+    pub fn done(&mut self) -> io::Result<usize> {
+        loop {
+            co_io_result()?;
+
+            // clear the io_flag
+            self.io_data.io_flag.store(false, Ordering::Relaxed);
+
+            match sendmsg(self.io_data.fd, self.hdr, self.flags) {
+                Ok(n) => return Ok(n),
+                Err(e) => {
+                    let raw_err = e.raw_os_error();
+                    if raw_err == Some(libc::EAGAIN) || raw_err == Some(libc::EWOULDBLOCK) {
+                        // do nothing here
+                    } else {
+                        return Err(e);
+                    }
+                }
+            }
+
+            if self.io_data.io_flag.swap(false, Ordering::Relaxed) {
+                continue;
+            }
+
+            // the result is still WouldBlock, need to try again
+            yield_with(self);
+        }
+    }
+}
+
+impl<'a> EventSource for SocketSendMsg<'a> {
+    fn subscribe(&mut self, co: CoroutineImpl) {
+        let io_data = (*self.io_data).clone();
+
+        if let Some(dur) = self.timeout {
+            get_scheduler()
+                .get_selector()
+                .add_io_timer(self.io_data, dur);
+        }
+        self.io_data.co.swap(co, Ordering::Release);
+
+        // there is event, re-run the coroutine
+        if io_data.io_flag.load(Ordering::Acquire) {
+            io_data.schedule();
+        }
+    }
+}
+
+#[inline]
+pub fn recvmsg(fd: RawFd, hdr: &mut libc::msghdr, flags: libc::c_int) -> io::Result<usize> {
+    let ret = unsafe { libc::recvmsg(fd, hdr, flags) };
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(ret as usize)
+}
+
+#[inline]
+pub fn sendmsg(fd: RawFd, hdr: &libc::msghdr, flags: libc::c_int) -> io::Result<usize> {
+    let ret = unsafe { libc::sendmsg(fd, hdr, flags) };
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(ret as usize)
+}
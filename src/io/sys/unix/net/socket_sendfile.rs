@@ -0,0 +1,105 @@
+use std::io;
+use std::os::unix::io::RawFd;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+use super::super::{co_io_result, IoData};
+use crate::coroutine_impl::{co_get_handle, CoroutineImpl, EventSource};
+use crate::io::AsIoData;
+use crate::scheduler::get_scheduler;
+use crate::yield_now::yield_with;
+
+pub struct SocketSendFile<'a> {
+    io_data: &'a IoData,
+    file: RawFd,
+    offset: libc::off_t,
+    len: usize,
+    timeout: Option<Duration>,
+}
+
+impl<'a> SocketSendFile<'a> {
+    pub fn new<T: AsIoData>(
+        s: &'a T,
+        file: RawFd,
+        offset: u64,
+        len: usize,
+        timeout: Option<Duration>,
+    ) -> Self {
+        SocketSendFile {
+            io_data: s.as_io_data(),
+            file,
+            offset: offset as libc::off_t,
+            len,
+            timeout,
+        }
+    }
+
+    pub fn done(&mut self) -> io::Result<usize> {
+        loop {
+            co_io_result()?;
+
+            // clear the io_flag
+            self.io_data.io_flag.store(false, Ordering::Relaxed);
+
+            match sendfile(self.io_data.fd, self.file, &mut self.offset, self.len) {
+                Ok(n) => return Ok(n),
+                Err(e) => {
+                    let raw_err = e.raw_os_error();
+                    if raw_err == Some(libc::EAGAIN) || raw_err == Some(libc::EWOULDBLOCK) {
+                        // do nothing here
+                    } else {
+                        return Err(e);
+                    }
+                }
+            }
+
+            if self.io_data.io_flag.swap(false, Ordering::Relaxed) {
+                continue;
+            }
+
+            // the result is still WouldBlock, need to try again
+            yield_with(self);
+        }
+    }
+}
+
+impl<'a> EventSource for SocketSendFile<'a> {
+    fn subscribe(&mut self, co: CoroutineImpl) {
+        let handle = co_get_handle(&co);
+        let cancel = handle.get_cancel();
+        let io_data = (*self.io_data).clone();
+
+        if let Some(dur) = self.timeout {
+            get_scheduler()
+                .get_selector()
+                .add_io_timer(self.io_data, dur);
+        }
+        self.io_data.co.swap(co, Ordering::Release);
+
+        // there is event, re-run the coroutine
+        if io_data.io_flag.load(Ordering::Acquire) {
+            return io_data.schedule();
+        }
+
+        // register the cancel io data
+        cancel.set_io(io_data);
+        // re-check the cancel status
+        if cancel.is_canceled() {
+            unsafe { cancel.cancel() };
+        }
+    }
+}
+
+#[inline]
+pub fn sendfile(
+    socket: RawFd,
+    file: RawFd,
+    offset: &mut libc::off_t,
+    len: usize,
+) -> io::Result<usize> {
+    let ret = unsafe { libc::sendfile(socket, file, offset, len) };
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(ret as usize)
+}
@@ -1,4 +1,5 @@
 use std::io::{self, IoSlice};
+use std::os::unix::io::RawFd;
 use std::sync::atomic::Ordering;
 use std::time::Duration;
 
@@ -11,35 +12,26 @@ use crate::yield_now::yield_with;
 pub struct SocketWriteVectored<'a> {
     io_data: &'a IoData,
     bufs: &'a [IoSlice<'a>],
-    socket: &'a std::net::TcpStream,
     timeout: Option<Duration>,
 }
 
 impl<'a> SocketWriteVectored<'a> {
-    pub fn new<T: AsIoData>(
-        s: &'a T,
-        socket: &'a std::net::TcpStream,
-        bufs: &'a [IoSlice<'a>],
-        timeout: Option<Duration>,
-    ) -> Self {
+    pub fn new<T: AsIoData>(s: &'a T, bufs: &'a [IoSlice<'a>], timeout: Option<Duration>) -> Self {
         SocketWriteVectored {
             io_data: s.as_io_data(),
             bufs,
-            socket,
             timeout,
         }
     }
 
     pub fn done(&mut self) -> io::Result<usize> {
-        use std::io::Write;
-
         loop {
             co_io_result()?;
 
             // clear the io_flag
             self.io_data.io_flag.store(false, Ordering::Relaxed);
 
-            match self.socket.write_vectored(self.bufs) {
+            match writev(self.io_data.fd, self.bufs) {
                 Ok(n) => return Ok(n),
                 Err(e) => {
                     let raw_err = e.raw_os_error();
@@ -78,3 +70,14 @@ impl<'a> EventSource for SocketWriteVectored<'a> {
         }
     }
 }
+
+// IoSlice is guaranteed to be ABI compatible with iovec on unix
+#[inline]
+pub fn writev(fd: RawFd, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+    let len = std::cmp::min(bufs.len(), libc::c_int::MAX as usize) as libc::c_int;
+    let ret = unsafe { libc::writev(fd, bufs.as_ptr() as *const libc::iovec, len) };
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(ret as usize)
+}
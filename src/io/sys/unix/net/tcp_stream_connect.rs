@@ -34,18 +34,25 @@ impl TcpStreamConnect {
                     Ok((stream, addr))
                 })
             })
-            .and_then(|(stream, addr)| {
-                // before yield we must set the socket to nonblocking mode and registe to selector
-                stream.set_nonblocking(true)?;
-
-                add_socket(&stream).map(|io| TcpStreamConnect {
-                    io_data: OptionCell::new(io),
-                    stream: OptionCell::new(stream),
-                    timeout,
-                    addr,
-                    is_connected: false,
-                })
-            })
+            .and_then(|(stream, addr)| TcpStreamConnect::from_socket(stream, addr, timeout))
+    }
+
+    // connect with an already configured socket
+    pub fn from_socket(
+        stream: Socket,
+        addr: SocketAddr,
+        timeout: Option<Duration>,
+    ) -> io::Result<Self> {
+        // before yield we must set the socket to nonblocking mode and registe to selector
+        stream.set_nonblocking(true)?;
+
+        add_socket(&stream).map(|io| TcpStreamConnect {
+            io_data: OptionCell::new(io),
+            stream: OptionCell::new(stream),
+            timeout,
+            addr,
+            is_connected: false,
+        })
     }
 
     #[inline]
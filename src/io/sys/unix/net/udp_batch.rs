@@ -0,0 +1,266 @@
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use std::{cmp, io, mem, ptr};
+
+use super::super::{co_io_result, IoData};
+use crate::coroutine_impl::{co_get_handle, CoroutineImpl, EventSource};
+use crate::io::AsIoData;
+use crate::net::{RecvMsg, SendMsg, UdpSocket};
+use crate::scheduler::get_scheduler;
+use crate::yield_now::yield_with;
+use socket2::SockAddr;
+
+pub struct UdpRecvBatch<'a, 'b> {
+    io_data: &'a IoData,
+    msgs: &'a mut [RecvMsg<'b>],
+    fd: RawFd,
+    timeout: Option<Duration>,
+}
+
+impl<'a, 'b> UdpRecvBatch<'a, 'b> {
+    pub fn new(socket: &'a UdpSocket, msgs: &'a mut [RecvMsg<'b>]) -> Self {
+        UdpRecvBatch {
+            io_data: socket.as_io_data(),
+            msgs,
+            fd: socket.as_raw_fd(),
+            timeout: socket.read_timeout().unwrap(),
+        }
+    }
+
+    pub fn done(&mut self) -> io::Result<usize> {
+        loop {
+            co_io_result()?;
+
+            // clear the io_flag
+            self.io_data.io_flag.store(false, Ordering::Relaxed);
+
+            match recv_mmsg(self.fd, self.msgs) {
+                Ok(n) => return Ok(n),
+                Err(e) => {
+                    // raw_os_error is faster than kind
+                    let raw_err = e.raw_os_error();
+                    if raw_err == Some(libc::EAGAIN) || raw_err == Some(libc::EWOULDBLOCK) {
+                        // do nothing here
+                    } else {
+                        return Err(e);
+                    }
+                }
+            }
+
+            if self.io_data.io_flag.swap(false, Ordering::Relaxed) {
+                continue;
+            }
+
+            // the result is still WouldBlock, need to try again
+            yield_with(self);
+        }
+    }
+}
+
+impl<'a, 'b> EventSource for UdpRecvBatch<'a, 'b> {
+    fn subscribe(&mut self, co: CoroutineImpl) {
+        let handle = co_get_handle(&co);
+        let cancel = handle.get_cancel();
+        let io_data = (*self.io_data).clone();
+
+        if let Some(dur) = self.timeout {
+            get_scheduler()
+                .get_selector()
+                .add_io_timer(self.io_data, dur);
+        }
+        self.io_data.co.swap(co, Ordering::Release);
+
+        // there is event, re-run the coroutine
+        if io_data.io_flag.load(Ordering::Acquire) {
+            return io_data.schedule();
+        }
+
+        // register the cancel io data
+        cancel.set_io(io_data);
+        // re-check the cancel status
+        if cancel.is_canceled() {
+            unsafe { cancel.cancel() };
+        }
+    }
+}
+
+pub struct UdpSendBatch<'a, 'b> {
+    io_data: &'a IoData,
+    msgs: &'a [SendMsg<'b>],
+    fd: RawFd,
+    timeout: Option<Duration>,
+}
+
+impl<'a, 'b> UdpSendBatch<'a, 'b> {
+    pub fn new(socket: &'a UdpSocket, msgs: &'a [SendMsg<'b>]) -> Self {
+        UdpSendBatch {
+            io_data: socket.as_io_data(),
+            msgs,
+            fd: socket.as_raw_fd(),
+            timeout: socket.write_timeout().unwrap(),
+        }
+    }
+
+    pub fn done(&mut self) -> io::Result<usize> {
+        loop {
+            co_io_result()?;
+
+            // clear the io_flag
+            self.io_data.io_flag.store(false, Ordering::Relaxed);
+
+            match send_mmsg(self.fd, self.msgs) {
+                Ok(n) => return Ok(n),
+                Err(e) => {
+                    // raw_os_error is faster than kind
+                    let raw_err = e.raw_os_error();
+                    if raw_err == Some(libc::EAGAIN) || raw_err == Some(libc::EWOULDBLOCK) {
+                        // do nothing here
+                    } else {
+                        return Err(e);
+                    }
+                }
+            }
+
+            if self.io_data.io_flag.swap(false, Ordering::Relaxed) {
+                continue;
+            }
+
+            // the result is still WouldBlock, need to try again
+            yield_with(self);
+        }
+    }
+}
+
+impl<'a, 'b> EventSource for UdpSendBatch<'a, 'b> {
+    fn subscribe(&mut self, co: CoroutineImpl) {
+        let io_data = (*self.io_data).clone();
+
+        if let Some(dur) = self.timeout {
+            get_scheduler()
+                .get_selector()
+                .add_io_timer(self.io_data, dur);
+        }
+        self.io_data.co.swap(co, Ordering::Release);
+
+        // there is event, re-run the coroutine
+        if io_data.io_flag.load(Ordering::Acquire) {
+            io_data.schedule();
+        }
+    }
+}
+
+// max number of messages for one recv_mmsg/send_mmsg call
+const MAX_BATCH: usize = 1024;
+// number of messages for one recvmmsg/sendmmsg syscall, the headers are
+// kept on the stack so it should fit in the coroutine stack
+const CHUNK: usize = 16;
+
+// receive a batch of datagrams, the socket would only block for the first one
+pub fn recv_mmsg(fd: RawFd, msgs: &mut [RecvMsg<'_>]) -> io::Result<usize> {
+    let n = cmp::min(msgs.len(), MAX_BATCH);
+    let mut total = 0;
+    for chunk in msgs[..n].chunks_mut(CHUNK) {
+        // only the first one may block
+        let flags = if total == 0 {
+            libc::MSG_WAITFORONE
+        } else {
+            libc::MSG_DONTWAIT
+        };
+        let len = chunk.len();
+        match recv_chunk(fd, chunk, flags) {
+            // no more datagrams available
+            Ok(m) if m < len => return Ok(total + m),
+            Ok(m) => total += m,
+            Err(e) if total == 0 => return Err(e),
+            // report the received ones, the error would come again
+            Err(_) => break,
+        }
+    }
+    Ok(total)
+}
+
+fn recv_chunk(fd: RawFd, msgs: &mut [RecvMsg<'_>], flags: libc::c_int) -> io::Result<usize> {
+    let mut addrs: [libc::sockaddr_storage; CHUNK] = unsafe { mem::zeroed() };
+    let mut iovs: [libc::iovec; CHUNK] = unsafe { mem::zeroed() };
+    let mut hdrs: [libc::mmsghdr; CHUNK] = unsafe { mem::zeroed() };
+    for (i, m) in msgs.iter_mut().enumerate() {
+        iovs[i].iov_base = m.buf.as_mut_ptr() as *mut libc::c_void;
+        iovs[i].iov_len = m.buf.len();
+        let hdr = &mut hdrs[i].msg_hdr;
+        hdr.msg_name = &mut addrs[i] as *mut _ as *mut libc::c_void;
+        hdr.msg_namelen = mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+        hdr.msg_iov = &mut iovs[i];
+        hdr.msg_iovlen = 1;
+    }
+
+    let ret = unsafe {
+        libc::recvmmsg(
+            fd,
+            hdrs.as_mut_ptr(),
+            msgs.len() as libc::c_uint,
+            flags,
+            ptr::null_mut(),
+        )
+    };
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let ret = ret as usize;
+    for ((msg, hdr), addr) in msgs.iter_mut().zip(hdrs.iter()).zip(addrs.iter()).take(ret) {
+        msg.len = hdr.msg_len as usize;
+        msg.truncated = hdr.msg_hdr.msg_flags & libc::MSG_TRUNC != 0;
+        msg.addr = unsafe {
+            SockAddr::from_raw_parts(
+                addr as *const _ as *const libc::sockaddr,
+                hdr.msg_hdr.msg_namelen,
+            )
+        }
+        .as_std();
+    }
+    Ok(ret)
+}
+
+// send a batch of datagrams, return the number of messages sent
+pub fn send_mmsg(fd: RawFd, msgs: &[SendMsg<'_>]) -> io::Result<usize> {
+    let n = cmp::min(msgs.len(), MAX_BATCH);
+    let mut total = 0;
+    for chunk in msgs[..n].chunks(CHUNK) {
+        // only the first one may block
+        let flags = if total == 0 { 0 } else { libc::MSG_DONTWAIT };
+        match send_chunk(fd, chunk, flags) {
+            // the socket buffer is full
+            Ok(m) if m < chunk.len() => return Ok(total + m),
+            Ok(m) => total += m,
+            Err(e) if total == 0 => return Err(e),
+            // report the sent ones, the error would come again
+            Err(_) => break,
+        }
+    }
+    Ok(total)
+}
+
+fn send_chunk(fd: RawFd, msgs: &[SendMsg<'_>], flags: libc::c_int) -> io::Result<usize> {
+    let mut addrs: [Option<SockAddr>; CHUNK] = Default::default();
+    let mut iovs: [libc::iovec; CHUNK] = unsafe { mem::zeroed() };
+    let mut hdrs: [libc::mmsghdr; CHUNK] = unsafe { mem::zeroed() };
+    for (i, m) in msgs.iter().enumerate() {
+        iovs[i].iov_base = m.buf.as_ptr() as *mut libc::c_void;
+        iovs[i].iov_len = m.buf.len();
+        let hdr = &mut hdrs[i].msg_hdr;
+        if let Some(addr) = m.addr {
+            let addr = addrs[i].get_or_insert(SockAddr::from(addr));
+            hdr.msg_name = addr.as_ptr() as *mut libc::c_void;
+            hdr.msg_namelen = addr.len();
+        }
+        hdr.msg_iov = &mut iovs[i];
+        hdr.msg_iovlen = 1;
+    }
+
+    let ret = unsafe { libc::sendmmsg(fd, hdrs.as_mut_ptr(), msgs.len() as libc::c_uint, flags) };
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(ret as usize)
+}
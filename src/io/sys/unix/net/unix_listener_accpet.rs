@@ -1,23 +1,20 @@
 use std::io;
-use std::os::unix::net::{self, SocketAddr};
 use std::sync::atomic::Ordering;
 
 use crate::coroutine_impl::{co_get_handle, CoroutineImpl, EventSource};
 use crate::io::sys::{co_io_result, IoData};
 use crate::io::{AsIoData, CoIo};
-use crate::os::unix::net::{UnixListener, UnixStream};
+use crate::os::unix::net::{accept, SocketAddr, UnixListener, UnixStream};
 use crate::yield_now::yield_with;
 
 pub struct UnixListenerAccept<'a> {
     io_data: &'a IoData,
-    socket: &'a net::UnixListener,
 }
 
 impl<'a> UnixListenerAccept<'a> {
     pub fn new(socket: &'a UnixListener) -> io::Result<Self> {
         Ok(UnixListenerAccept {
             io_data: socket.0.as_io_data(),
-            socket: socket.0.inner(),
         })
     }
 
@@ -28,7 +25,7 @@ impl<'a> UnixListenerAccept<'a> {
             // clear the io_flag
             self.io_data.io_flag.store(false, Ordering::Relaxed);
 
-            match self.socket.accept() {
+            match accept(self.io_data.fd) {
                 Ok((s, a)) => {
                     let s = UnixStream::from_coio(CoIo::new(s)?);
                     return Ok((s, a));
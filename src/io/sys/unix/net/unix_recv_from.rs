@@ -1,19 +1,17 @@
-use std::os::unix::net::SocketAddr;
+use std::io;
 use std::sync::atomic::Ordering;
 use std::time::Duration;
-use std::{self, io};
 
 use super::super::{co_io_result, IoData};
 use crate::coroutine_impl::{co_get_handle, CoroutineImpl, EventSource};
 use crate::io::AsIoData;
-use crate::os::unix::net::UnixDatagram;
+use crate::os::unix::net::{recv_from, SocketAddr, UnixDatagram};
 use crate::scheduler::get_scheduler;
 use crate::yield_now::yield_with;
 
 pub struct UnixRecvFrom<'a> {
     io_data: &'a IoData,
     buf: &'a mut [u8],
-    socket: &'a std::os::unix::net::UnixDatagram,
     timeout: Option<Duration>,
 }
 
@@ -22,7 +20,6 @@ impl<'a> UnixRecvFrom<'a> {
         UnixRecvFrom {
             io_data: socket.0.as_io_data(),
             buf,
-            socket: socket.0.inner(),
             timeout: socket.0.read_timeout().unwrap(),
         }
     }
@@ -34,7 +31,7 @@ impl<'a> UnixRecvFrom<'a> {
             // clear the io_flag
             self.io_data.io_flag.store(false, Ordering::Relaxed);
 
-            match self.socket.recv_from(self.buf) {
+            match recv_from(self.io_data.fd, self.buf) {
                 Ok(n) => return Ok(n),
                 Err(e) => {
                     // raw_os_error is faster than kind
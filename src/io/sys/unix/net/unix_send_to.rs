@@ -1,30 +1,27 @@
-use std::path::Path;
+use std::io;
 use std::sync::atomic::Ordering;
 use std::time::Duration;
-use std::{self, io};
 
 use super::super::{co_io_result, IoData};
 use crate::coroutine_impl::{CoroutineImpl, EventSource};
 use crate::io::AsIoData;
-use crate::os::unix::net::UnixDatagram;
+use crate::os::unix::net::{send_to, SocketAddr, UnixDatagram};
 use crate::scheduler::get_scheduler;
 use crate::yield_now::yield_with;
 
 pub struct UnixSendTo<'a> {
     io_data: &'a IoData,
     buf: &'a [u8],
-    socket: &'a std::os::unix::net::UnixDatagram,
-    path: &'a Path,
+    addr: &'a SocketAddr,
     timeout: Option<Duration>,
 }
 
 impl<'a> UnixSendTo<'a> {
-    pub fn new(socket: &'a UnixDatagram, buf: &'a [u8], path: &'a Path) -> io::Result<Self> {
+    pub fn new(socket: &'a UnixDatagram, buf: &'a [u8], addr: &'a SocketAddr) -> io::Result<Self> {
         Ok(UnixSendTo {
             io_data: socket.0.as_io_data(),
             buf,
-            socket: socket.0.inner(),
-            path,
+            addr,
             timeout: socket.write_timeout().unwrap(),
         })
     }
@@ -36,7 +33,7 @@ impl<'a> UnixSendTo<'a> {
             // clear the io_flag
             self.io_data.io_flag.store(false, Ordering::Relaxed);
 
-            match self.socket.send_to(self.buf, self.path) {
+            match send_to(self.io_data.fd, self.buf, self.addr) {
                 Ok(n) => return Ok(n),
                 Err(e) => {
                     // raw_os_error is faster than kind
@@ -20,7 +20,10 @@ pub struct UnixStreamConnect {
 
 impl UnixStreamConnect {
     pub fn new<P: AsRef<Path>>(path: P) -> io::Result<Self> {
-        let path = SockAddr::unix(path)?;
+        UnixStreamConnect::with_addr(SockAddr::unix(path)?)
+    }
+
+    pub fn with_addr(path: SockAddr) -> io::Result<Self> {
         let socket = Socket::new(Domain::unix(), Type::stream(), None)?;
         // before yield we must set the socket to nonblocking mode and registe to selector
         socket.set_nonblocking(true)?;
@@ -2,11 +2,16 @@
 //! `wait_io` is a function that can be used in coroutine
 //! context to wait on the io events
 //!
+use std::io;
 use std::sync::atomic::Ordering;
+use std::time::Duration;
 
+use super::co_io_result;
 use crate::cancel::Cancel;
 use crate::coroutine_impl::{co_get_handle, CoroutineImpl, EventSource};
 use crate::io as io_impl;
+use crate::scheduler::get_scheduler;
+use crate::sync::{CancellationToken, Cancelled};
 use crate::yield_now::yield_with;
 
 pub struct RawIoBlock<'a> {
@@ -44,6 +49,106 @@ impl<'a> EventSource for RawIoBlock<'a> {
     }
 }
 
+// block on io events with an optional timeout
+// the cancel would be checked after yield back like other io operations
+struct TimeoutIoBlock<'a> {
+    io_data: &'a io_impl::IoData,
+    timeout: Option<Duration>,
+}
+
+impl<'a> EventSource for TimeoutIoBlock<'a> {
+    fn subscribe(&mut self, co: CoroutineImpl) {
+        let handle = co_get_handle(&co);
+        let cancel = handle.get_cancel();
+        let io_data = (*self.io_data).clone();
+
+        if let Some(dur) = self.timeout {
+            get_scheduler()
+                .get_selector()
+                .add_io_timer(self.io_data, dur);
+        }
+        self.io_data.co.swap(co, Ordering::Release);
+
+        // there is event, re-run the coroutine
+        if io_data.io_flag.load(Ordering::Acquire) {
+            return io_data.schedule();
+        }
+
+        // register the cancel io data
+        cancel.set_io(io_data);
+        // re-check the cancel status
+        if cancel.is_canceled() {
+            unsafe { cancel.cancel() };
+        }
+    }
+}
+
+// block current coroutine until any io event happens on the io data
+// return `io::ErrorKind::TimedOut` error when timeout
+pub(crate) fn wait_io_timeout(
+    io_data: &io_impl::IoData,
+    timeout: Option<Duration>,
+) -> io::Result<()> {
+    // when io flag is set we do nothing
+    if io_data.io_flag.load(Ordering::Acquire) {
+        return Ok(());
+    }
+    let blocker = TimeoutIoBlock { io_data, timeout };
+    yield_with(&blocker);
+    co_io_result()
+}
+
+// run the nonblocking io until it doesn't return `WouldBlock`, the coroutine
+// waits for the io events between the tries. cancelling the token wakes up
+// the coroutine like an io event, then the `Cancelled` error is returned.
+//
+// `ctx` is the result of the context check, when it's false the io is done
+// directly after checking the token, which can't be interrupted.
+pub(crate) fn io_or_cancel<R, F>(
+    io_data: &io_impl::IoData,
+    ctx: bool,
+    timeout: Option<Duration>,
+    token: &CancellationToken,
+    mut f: F,
+) -> io::Result<R>
+where
+    F: FnMut() -> io::Result<R>,
+{
+    if !ctx {
+        if token.is_cancelled() {
+            return Err(Cancelled(()).into());
+        }
+        return f();
+    }
+
+    let io = (*io_data).clone();
+    // the waker may run in any thread, it only hands the coroutine over
+    // to the scheduler like the cancel of the io does
+    let _waker = token.register(Box::new(move || {
+        io.io_flag.store(true, Ordering::Release);
+        if let Some(co) = io.co.take(Ordering::Acquire) {
+            get_scheduler().schedule(co);
+        }
+    }));
+    loop {
+        // clear the io flag before checking the token, so that
+        // the cancel comes after the check would wake us up
+        io_data.reset();
+        if token.is_cancelled() {
+            // remove the timer that is left by the cancel
+            if let Some(h) = io_data.timer.borrow_mut().take() {
+                h.remove();
+            }
+            return Err(Cancelled(()).into());
+        }
+        match f() {
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {}
+            ret => return ret,
+        }
+        wait_io_timeout(io_data, timeout)?;
+    }
+}
+
 /// This is trait that can block on io events but doing nothong about io
 pub trait WaitIo {
     /// reset the io before io operation
@@ -6,7 +6,8 @@ use std::{io, ptr};
 
 use crate::coroutine_impl::{run_coroutine, CoroutineImpl};
 use crate::scheduler::get_scheduler;
-use crate::timeout_list::{now, ns_to_dur, TimeOutList, TimeoutHandle};
+use crate::timeout_list::{now, ns_to_dur};
+use crate::timer_wheel::{TimerHandle as WheelHandle, TimerWheel};
 use crate::yield_now::set_co_para;
 use miow::iocp::{CompletionPort, CompletionStatus};
 use smallvec::SmallVec;
@@ -21,8 +22,8 @@ pub struct TimerData {
     event_data: *mut EventData,
 }
 
-type TimerList = TimeOutList<TimerData>;
-pub type TimerHandle = TimeoutHandle<TimerData>;
+type TimerList = TimerWheel<TimerData>;
+pub type TimerHandle = WheelHandle<TimerData>;
 
 // event associated io data, must be construct in the coroutine
 // this passed in to the _overlapped version API and will read back
@@ -138,19 +139,11 @@ impl Selector {
             let mut co = data.co.take().expect("can't get co in selector");
             co.prefetch();
 
-            // it's safe to remove the timer since we are
-            // running the timer_list in the same thread
-            // this is not true when running in multi-thread environment
-            data.timer.take().map(|h| {
-                unsafe {
-                    // tell the timer function not to cancel the io
-                    // it's not always true that you can really remove the timer entry
-                    // it's safe in multi-thread env because it only access its own data
-                    h.with_mut_data(|value| value.data.event_data = ptr::null_mut());
-                }
-                // NOT SAFE for multi-thread!!
-                h.remove()
-            });
+            // remove the timer so that the timer function would not cancel the io
+            // the timer can be removed in any thread
+            if let Some(h) = data.timer.take() {
+                h.remove();
+            }
 
             let overlapped = unsafe { &*overlapped };
             // info!("select got overlapped, status = {}", overlapped.Internal);
@@ -195,6 +188,15 @@ impl Selector {
         Ok(next_expire)
     }
 
+    // get the earliest io timeout of all the workers in wall clock ns
+    #[cfg(feature = "test-util")]
+    pub fn next_timer_deadline(&self) -> Option<u64> {
+        self.vec
+            .iter()
+            .filter_map(|s| s.timer_list.next_deadline())
+            .min()
+    }
+
     // this will post an os event so that we can wakeup the event loop
     #[inline]
     pub fn wakeup(&self, id: usize) {
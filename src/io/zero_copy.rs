@@ -0,0 +1,338 @@
+//! zero copy io operations for `TcpStream`
+//!
+//! the data is moved inside the kernel without copying through user space.
+//! when the socket is not ready the coroutine is parked just like the
+//! normal read/write on the stream, and the stream timeouts are honored.
+
+use std::cmp;
+use std::io;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::time::Duration;
+
+use crate::coroutine_impl::is_coroutine;
+use crate::io::net as net_impl;
+use crate::io::sys::wait_io::wait_io_timeout;
+use crate::io::AsIoData;
+use crate::net::TcpStream;
+use crate::yield_now::yield_with;
+
+// the default pipe capacity on linux
+const PIPE_SIZE: usize = 64 * 1024;
+
+#[inline]
+fn would_block(e: &io::Error) -> bool {
+    let raw_err = e.raw_os_error();
+    raw_err == Some(libc::EAGAIN) || raw_err == Some(libc::EWOULDBLOCK)
+}
+
+/// send `len` bytes of the file starting from `offset` to the stream
+///
+/// returns the number of bytes sent, which may be less than `len`. the file
+/// offset is not changed, the caller should advance the offset for the next
+/// call. the `write_timeout` of the stream is honored.
+pub fn sendfile<F: AsRawFd>(
+    stream: &mut TcpStream,
+    file: &F,
+    offset: u64,
+    len: usize,
+) -> io::Result<usize> {
+    let file = file.as_raw_fd();
+    if !stream.ctx_check()? {
+        let mut off = offset as libc::off_t;
+        return net_impl::sendfile(stream.as_raw_fd(), file, &mut off, len);
+    }
+
+    stream.as_io_data().reset();
+    // this is an earlier return try for nonblocking write
+    let mut off = offset as libc::off_t;
+    match net_impl::sendfile(stream.as_raw_fd(), file, &mut off, len) {
+        Ok(n) => return Ok(n),
+        Err(ref e) if would_block(e) => {}
+        Err(e) => return Err(e),
+    }
+
+    let mut s = net_impl::SocketSendFile::new(stream, file, offset, len, stream.write_timeout()?);
+    yield_with(&s);
+    s.done()
+}
+
+// a pipe used as the kernel buffer for splice
+#[derive(Debug)]
+struct Pipe {
+    rd: RawFd,
+    wr: RawFd,
+}
+
+impl Pipe {
+    fn new() -> io::Result<Pipe> {
+        let mut fds = [0; 2];
+        let ret = unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_NONBLOCK | libc::O_CLOEXEC) };
+        if ret < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Pipe {
+            rd: fds[0],
+            wr: fds[1],
+        })
+    }
+}
+
+impl Drop for Pipe {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.rd);
+            libc::close(self.wr);
+        }
+    }
+}
+
+// splice between the stream and the pipe, the stream is waited when would block
+fn splice_stream(
+    stream: &TcpStream,
+    fd_in: RawFd,
+    fd_out: RawFd,
+    len: usize,
+    timeout: Option<Duration>,
+) -> io::Result<usize> {
+    if !stream.ctx_check()? {
+        return net_impl::splice(fd_in, fd_out, len);
+    }
+
+    stream.as_io_data().reset();
+    match net_impl::splice(fd_in, fd_out, len) {
+        Ok(n) => return Ok(n),
+        Err(ref e) if would_block(e) => {}
+        Err(e) => return Err(e),
+    }
+
+    let mut s = net_impl::SocketSplice::new(stream, fd_in, fd_out, len, timeout);
+    yield_with(&s);
+    s.done()
+}
+
+// wait until the nonblocking stream is writable, the coroutine is parked
+// on the selector so that the worker thread is not blocked
+fn wait_writable(stream: &TcpStream, timeout: Option<Duration>) -> io::Result<()> {
+    if is_coroutine() {
+        return wait_io_timeout(stream.as_io_data(), timeout);
+    }
+
+    // in thread context just block the thread
+    let timeout_ms = timeout.map_or(-1, |dur| {
+        cmp::min(dur.as_millis(), libc::c_int::MAX as u128) as libc::c_int
+    });
+    let mut pfd = libc::pollfd {
+        fd: stream.as_raw_fd(),
+        events: libc::POLLOUT,
+        revents: 0,
+    };
+    loop {
+        match unsafe { libc::poll(&mut pfd, 1, timeout_ms) } {
+            0 => return Err(io::Error::new(io::ErrorKind::TimedOut, "timeout")),
+            n if n > 0 => return Ok(()),
+            _ => {}
+        }
+        let err = io::Error::last_os_error();
+        if err.kind() != io::ErrorKind::Interrupted {
+            return Err(err);
+        }
+    }
+}
+
+/// a reusable kernel pipe used by [`splice`]
+///
+/// the pipe keeps the bytes that are read from the source stream but not yet
+/// written to the target stream, e.g. when the `write_timeout` expires, so
+/// that the next `splice` call can resume writing them. a forwarding loop
+/// should create one pipe and pass it to all the `splice` calls.
+///
+/// [`splice`]: fn.splice.html
+#[derive(Debug)]
+pub struct SplicePipe {
+    pipe: Pipe,
+    // the bytes in the pipe that are not written yet
+    pending: usize,
+}
+
+impl SplicePipe {
+    /// create a new empty pipe
+    pub fn new() -> io::Result<SplicePipe> {
+        Ok(SplicePipe {
+            pipe: Pipe::new()?,
+            pending: 0,
+        })
+    }
+
+    /// the number of bytes that are read but not yet written
+    pub fn pending(&self) -> usize {
+        self.pending
+    }
+}
+
+/// move at most `len` bytes from one stream to another through the pipe
+///
+/// the data is read from `from` into the pipe and then all of them are
+/// written to `to`, so the return value is the number of bytes moved, `0`
+/// means `from` reaches EOF. at most 64K bytes are moved in one call. the
+/// `read_timeout` of `from` and the `write_timeout` of `to` are honored.
+///
+/// if `from` is in nonblocking mode a `WouldBlock` error may be returned,
+/// while the write side always waits until all the data is written, even if
+/// `to` is in nonblocking mode.
+///
+/// if writing to `to` fails, e.g. its `write_timeout` expires, the bytes not
+/// yet written are kept in the pipe. the next call with the same pipe writes
+/// them first and returns their number without reading from `from`.
+pub fn splice(
+    pipe: &mut SplicePipe,
+    from: &mut TcpStream,
+    to: &mut TcpStream,
+    len: usize,
+) -> io::Result<usize> {
+    if pipe.pending == 0 {
+        let len = cmp::min(len, PIPE_SIZE);
+        let wr = pipe.pipe.wr;
+        pipe.pending = splice_stream(from, from.as_raw_fd(), wr, len, from.read_timeout()?)?;
+    }
+
+    let n = pipe.pending;
+    let timeout = to.write_timeout()?;
+    while pipe.pending > 0 {
+        // clear the stale event so that waiting below needs a new one
+        to.as_io_data().reset();
+        match splice_stream(to, pipe.pipe.rd, to.as_raw_fd(), pipe.pending, timeout) {
+            Ok(0) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::WriteZero,
+                    "failed to write whole buffer",
+                ))
+            }
+            Ok(m) => pipe.pending -= m,
+            // the stream is in nonblocking mode
+            Err(ref e) if would_block(e) => wait_writable(to, timeout)?,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::TcpListener;
+    use std::io::Read;
+
+    #[test]
+    fn sendfile_and_splice() {
+        let dir = tempdir::TempDir::new("may_zero_copy").unwrap();
+        let path = dir.path().join("data");
+        std::fs::write(&path, b"hello zero copy").unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let proxy = TcpListener::bind("127.0.0.1:0").unwrap();
+        let proxy_addr = proxy.local_addr().unwrap();
+
+        // send the file through sendfile
+        let server = go!(move || {
+            let (mut s, _) = listener.accept().unwrap();
+            let file = std::fs::File::open(&path).unwrap();
+            let mut off = 0;
+            while off < 15 {
+                off += sendfile(&mut s, &file, off as u64, 15 - off).unwrap();
+            }
+        });
+
+        // forward the data through splice
+        let forward = go!(move || {
+            let mut upstream = TcpStream::connect(addr).unwrap();
+            let (mut s, _) = proxy.accept().unwrap();
+            let mut pipe = SplicePipe::new().unwrap();
+            while splice(&mut pipe, &mut upstream, &mut s, 4096).unwrap() > 0 {}
+        });
+
+        let client = go!(move || {
+            let mut s = TcpStream::connect(proxy_addr).unwrap();
+            let mut buf = Vec::new();
+            s.read_to_end(&mut buf).unwrap();
+            assert_eq!(buf, b"hello zero copy");
+        });
+
+        server.join().unwrap();
+        forward.join().unwrap();
+        client.join().unwrap();
+    }
+
+    #[test]
+    fn splice_cancel() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let forward = go!(move || {
+            let mut from = TcpStream::connect(addr).unwrap();
+            let mut to = TcpStream::connect(addr).unwrap();
+            let mut pipe = SplicePipe::new().unwrap();
+            // no data would come, blocked in the selector
+            splice(&mut pipe, &mut from, &mut to, 4096).unwrap();
+        });
+
+        let _peers = (listener.accept().unwrap(), listener.accept().unwrap());
+        crate::sleep::sleep(Duration::from_millis(50));
+        unsafe { forward.coroutine().cancel() };
+        assert!(forward.join().is_err());
+    }
+
+    #[test]
+    fn splice_write_timeout() {
+        use std::io::Write;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let forward = go!(move || {
+            let mut from = TcpStream::connect(addr).unwrap();
+            let mut to = TcpStream::connect(addr).unwrap();
+            // fill up the buffers of the target
+            to.set_nonblocking(true).unwrap();
+            let buf = [0u8; 4096];
+            let mut filled = 0;
+            loop {
+                match to.write(&buf) {
+                    Ok(n) => filled += n,
+                    Err(ref e) if would_block(e) => break,
+                    Err(e) => panic!("write failed: {}", e),
+                }
+            }
+            to.set_nonblocking(false).unwrap();
+            to.set_write_timeout(Some(Duration::from_millis(50)))
+                .unwrap();
+
+            let mut pipe = SplicePipe::new().unwrap();
+            let err = splice(&mut pipe, &mut from, &mut to, 4096).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+            // the data is kept in the pipe
+            let pending = pipe.pending();
+            assert!(pending > 0);
+            (filled, pipe, from, to)
+        });
+
+        let (mut from_peer, _) = listener.accept().unwrap();
+        let (mut to_peer, _) = listener.accept().unwrap();
+        from_peer.write_all(b"hello").unwrap();
+        let (filled, mut pipe, mut from, mut to) = forward.join().unwrap();
+
+        // drain the target, then resume the pending data
+        let reader = go!(move || {
+            let mut buf = vec![0u8; filled + 5];
+            to_peer.read_exact(&mut buf).unwrap();
+            buf.split_off(filled)
+        });
+        let pending = pipe.pending();
+        assert_eq!(
+            splice(&mut pipe, &mut from, &mut to, 4096).unwrap(),
+            pending
+        );
+        assert_eq!(pipe.pending(), 0);
+        assert_eq!(reader.join().unwrap(), b"hello");
+    }
+}
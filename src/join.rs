@@ -1,21 +1,62 @@
 use std::any::Any;
 use std::cell::UnsafeCell;
 use std::fmt;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
 use std::thread::Result;
+use std::time::Duration;
 
 use crate::coroutine_impl::Coroutine;
-use crate::sync::{AtomicOption, Blocker};
+use crate::sync::{AtomicOption, Blocker, CancellationToken};
 use crossbeam::atomic::AtomicCell;
 use generator::Error;
 
+type PanicHook = dyn Fn(Option<&str>, &(dyn Any + Send)) + Send + Sync;
+
+// the hook that is called for the panics of the detached coroutines
+static DETACHED_PANIC_HOOK: RwLock<Option<Arc<PanicHook>>> = RwLock::new(None);
+
+// the join handle is dropped without joining the coroutine
+const DETACHED: usize = 1;
+// the panic data of the coroutine is set
+const PANICKED: usize = 2;
+
+/// Registers a hook for the panics of the detached coroutines
+///
+/// the hook is called with the coroutine name and the panic payload when a
+/// coroutine panics and its `JoinHandle` is dropped without joining it. it's
+/// called in the worker thread that runs the coroutine, or in the thread that
+/// drops the `JoinHandle` if the handle is dropped after the panic, so the
+/// hook should not block.
+///
+/// the cancel panics are not reported. the hook replaces the previous one.
+pub fn set_detached_panic_hook<F>(hook: F)
+where
+    F: Fn(Option<&str>, &(dyn Any + Send)) + Send + Sync + 'static,
+{
+    *DETACHED_PANIC_HOOK.write().unwrap() = Some(Arc::new(hook));
+}
+
+fn report_detached_panic(name: Option<&str>, panic: Box<dyn Any + Send>) {
+    if let Some(&Error::Cancel) = panic.downcast_ref::<Error>() {
+        return;
+    }
+    // don't hold the lock when calling the hook
+    let hook = DETACHED_PANIC_HOOK.read().unwrap().clone();
+    if let Some(hook) = hook {
+        hook(name, &*panic);
+    }
+}
+
 pub struct Join {
     // the coroutine that waiting for this join handler
     to_wake: AtomicOption<Arc<Blocker>>,
     // the flag indicate if the host coroutine is not finished
     // when set to false, the coroutine is done
     state: AtomicBool,
+    // the DETACHED and PANICKED flags, the one that sets the
+    // last flag reports the panic to the detached panic hook
+    detach: AtomicUsize,
 
     // use to set the panic err
     // this is the only place that could set the panic Error
@@ -30,6 +71,7 @@ impl Join {
         Join {
             to_wake: AtomicOption::none(),
             state: AtomicBool::new(true),
+            detach: AtomicUsize::new(0),
             panic,
         }
     }
@@ -40,6 +82,21 @@ impl Join {
         *p = Some(panic);
     }
 
+    // set the flag and report the panic if both flags are set
+    fn set_detach_flag(&self, flag: usize, name: Option<&str>) {
+        if self.detach.fetch_or(flag, Ordering::AcqRel) | flag == DETACHED | PANICKED {
+            let p = unsafe { &mut *self.panic.get() };
+            if let Some(panic) = p.take() {
+                report_detached_panic(name, panic);
+            }
+        }
+    }
+
+    // called after the panic data is set
+    pub fn set_panicked(&self, name: Option<&str>) {
+        self.set_detach_flag(PANICKED, name);
+    }
+
     pub fn trigger(&mut self) {
         self.state.store(false, Ordering::Release);
         if let Some(w) = self.to_wake.take(Ordering::Acquire) {
@@ -47,7 +104,8 @@ impl Join {
         }
     }
 
-    fn wait(&mut self) {
+    // return false if timeout happened or the token is cancelled
+    fn wait(&mut self, dur: Option<Duration>, token: Option<&CancellationToken>) -> bool {
         if self.state.load(Ordering::Acquire) {
             let cur = Blocker::current();
             // register the blocker first
@@ -60,8 +118,14 @@ impl Join {
                 w.unpark();
             }
 
-            cur.park(None).ok();
+            let _waker = token.map(|t| t.wake_blocker(&cur));
+            // the token would wake us up before the coroutine is done
+            if cur.park(dur).is_err() || self.state.load(Ordering::Acquire) {
+                // unregister the blocker
+                self.to_wake.take(Ordering::Acquire);
+            }
         }
+        !self.state.load(Ordering::Acquire)
     }
 }
 
@@ -106,15 +170,67 @@ impl<T> JoinHandle<T> {
     /// block until the coroutine is done
     pub fn wait(&self) {
         let join = unsafe { &mut *self.join.get() };
-        join.wait();
+        join.wait(None, None);
     }
 
     /// Join the coroutine, returning the result it produced.
     pub fn join(self) -> Result<T> {
         let join = unsafe { &mut *self.join.get() };
-        join.wait();
+        join.wait(None, None);
+        self.take_result()
+    }
 
-        // take the result
+    /// Join the coroutine with a timeout
+    ///
+    /// return the handle back if the coroutine is not done before timeout
+    pub fn join_timeout(self, dur: Duration) -> std::result::Result<Result<T>, Self> {
+        let join = unsafe { &mut *self.join.get() };
+        if join.wait(Some(dur), None) {
+            Ok(self.take_result())
+        } else {
+            Err(self)
+        }
+    }
+
+    /// Join the coroutine, or stop waiting when the token is cancelled
+    ///
+    /// return the handle back if the token is cancelled before the
+    /// coroutine is done
+    pub fn join_or_cancel(self, token: &CancellationToken) -> std::result::Result<Result<T>, Self> {
+        let join = unsafe { &mut *self.join.get() };
+        if join.wait(None, Some(token)) {
+            Ok(self.take_result())
+        } else {
+            Err(self)
+        }
+    }
+
+    /// Join the coroutine without blocking
+    ///
+    /// return the handle back if the coroutine is not done yet
+    pub fn try_join(self) -> std::result::Result<Result<T>, Self> {
+        if self.is_done() {
+            Ok(self.take_result())
+        } else {
+            Err(self)
+        }
+    }
+
+    /// Cancel the coroutine and join it
+    ///
+    /// this is the same as calling `Coroutine::cancel` and then `join`. the
+    /// coroutine is unwound at its next blocking point, unless it's inside a
+    /// `CancelGuard`. the result is returned if the coroutine is done before
+    /// the cancel, else the `Error::Cancel` panic is returned.
+    pub fn abort(self) -> Result<T> {
+        if !self.is_done() {
+            unsafe { self.co.cancel() };
+        }
+        self.join()
+    }
+
+    // take the result, must be called after the coroutine is done
+    fn take_result(&self) -> Result<T> {
         self.packet.take().ok_or_else(|| {
             let p = unsafe { &mut *self.panic.get() };
             p.take().unwrap_or_else(|| Box::new(Error::Cancel))
@@ -122,6 +238,13 @@ impl<T> JoinHandle<T> {
     }
 }
 
+impl<T> Drop for JoinHandle<T> {
+    fn drop(&mut self) {
+        let join = unsafe { &*self.join.get() };
+        join.set_detach_flag(DETACHED, self.co.name());
+    }
+}
+
 impl<T> fmt::Debug for JoinHandle<T> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.pad("JoinHandle { .. }")
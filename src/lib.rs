@@ -29,6 +29,7 @@
 #[macro_use]
 extern crate log;
 
+mod blocking_pool;
 mod cancel;
 mod config;
 mod join;
@@ -36,19 +37,38 @@ mod local;
 mod park;
 mod pool;
 mod sleep;
+mod stream;
 #[macro_use]
 mod macros;
 mod coroutine_impl;
 mod scheduler;
 mod scoped;
+mod task_group;
 mod timeout_list;
+mod timer_wheel;
 mod yield_now;
 
 pub mod coroutine;
 pub mod cqueue;
+pub mod fs;
 pub mod io;
 pub mod net;
 pub mod os;
+#[cfg(unix)]
+pub mod process;
+#[cfg(unix)]
+pub mod signal;
 pub mod sync;
+pub mod time;
 pub use crate::config::{config, Config};
 pub use crate::local::LocalKey;
+
+// the internal timer lists, only exposed for the benches
+#[cfg(nightly)]
+#[doc(hidden)]
+pub mod timer_bench {
+    pub use crate::timeout_list::interval_list::TimeOutList;
+    pub use crate::timeout_list::now;
+    pub use crate::timeout_list::TimerThread;
+    pub use crate::timer_wheel::TimerWheel;
+}
@@ -1,7 +1,8 @@
 use std::any::TypeId;
-use std::cell::{RefCell, UnsafeCell};
+use std::cell::{Cell, RefCell, UnsafeCell};
 use std::collections::HashMap;
 use std::hash::{BuildHasherDefault, Hasher};
+use std::mem;
 use std::ptr::NonNull;
 use std::sync::Arc;
 
@@ -28,7 +29,7 @@ impl CoroutineLocal {
         Box::new(CoroutineLocal {
             co,
             join,
-            local_data: RefCell::new(HashMap::default()),
+            local_data: RefCell::new(inherited_data()),
         })
     }
 
@@ -57,11 +58,50 @@ fn with<F: FnOnce(&LocalMap) -> R, R>(f: F) -> R {
     }
 }
 
-pub type LocalMap = RefCell<HashMap<TypeId, Box<dyn Opaque>, BuildHasherDefault<IdHasher>>>;
+// clone the inherited local data of the current context for a new coroutine
+fn inherited_data() -> HashMap<TypeId, Box<Slot<dyn Opaque>>, BuildHasherDefault<IdHasher>> {
+    with(|data| {
+        data.borrow()
+            .iter()
+            .filter_map(|(key, slot)| slot.inherit.map(|i| (*key, i.inherit(slot))))
+            .collect()
+    })
+}
+
+pub type LocalMap = RefCell<HashMap<TypeId, Box<Slot<dyn Opaque>>, BuildHasherDefault<IdHasher>>>;
 
 pub trait Opaque {}
 impl<T> Opaque for T {}
 
+// the local data stored in the map
+pub struct Slot<T: ?Sized> {
+    // the number of the running `with` closures on the value
+    borrows: Cell<usize>,
+    // only set for the inherited local
+    inherit: Option<&'static dyn Inherit>,
+    value: T,
+}
+
+impl<T: ?Sized> Slot<T> {
+    fn check_unborrowed(&self) {
+        assert_eq!(self.borrows.get(), 0, "coroutine local is already borrowed");
+    }
+}
+
+// decrease the borrow count when the `with` closure is done
+struct BorrowGuard<'a>(&'a Cell<usize>);
+
+impl<'a> Drop for BorrowGuard<'a> {
+    fn drop(&mut self) {
+        self.0.set(self.0.get() - 1);
+    }
+}
+
+// clone the inherited local data for a child coroutine
+pub trait Inherit {
+    fn inherit(&self, slot: &Slot<dyn Opaque>) -> Box<Slot<dyn Opaque>>;
+}
+
 /// A key for local data stored in a coroutine.
 ///
 /// This type is generated by the `coroutine_local!` macro and performs very
@@ -72,6 +112,12 @@ impl<T> Opaque for T {}
 /// coroutine-local data requires the `'static` bound to ensure it lives long
 /// enough. When a key is accessed for the first time the coroutine's data is
 /// initialized with the provided initialization expression to the macro.
+///
+/// A key declared with `inherit` is an inherited local, the value of it is
+/// cloned into the new coroutines spawned from the current context, so that
+/// the context like a request id can be propagated to the child coroutines.
+/// The child coroutine gets its own copy, changing it would not affect the
+/// parent.
 pub struct LocalKey<T> {
     // "private" fields which have to be public to get around macro hygiene, not
     // included in the stability story for this type. Can change at any time.
@@ -79,6 +125,8 @@ pub struct LocalKey<T> {
     pub __key: fn() -> TypeId,
     #[doc(hidden)]
     pub __init: fn() -> T,
+    #[doc(hidden)]
+    pub __inherit: Option<fn(&T) -> T>,
 }
 
 pub struct IdHasher {
@@ -137,10 +185,88 @@ impl<T: 'static> LocalKey<T> {
         with(|data| {
             let raw_pointer = {
                 let mut data = data.borrow_mut();
-                let entry = data.entry(key).or_insert_with(|| Box::new((self.__init)()));
-                &**entry as *const dyn Opaque as *const T
+                let entry = data
+                    .entry(key)
+                    .or_insert_with(|| self.new_slot((self.__init)()));
+                &**entry as *const Slot<dyn Opaque> as *const Slot<T>
             };
-            unsafe { f(&*raw_pointer) }
+            let slot = unsafe { &*raw_pointer };
+            slot.borrows.set(slot.borrows.get() + 1);
+            let _guard = BorrowGuard(&slot.borrows);
+            f(&slot.value)
+        })
+    }
+
+    /// Sets the value of this coroutine-local key, the old value is dropped
+    ///
+    /// the initialization expression is not run if the key is not accessed
+    /// before.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the key is currently accessed by `with`
+    pub fn set(&'static self, value: T) {
+        let key = (self.__key)();
+        let old = with(|data| {
+            let mut data = data.borrow_mut();
+            if let Some(slot) = data.get(&key) {
+                slot.check_unborrowed();
+            }
+            data.insert(key, self.new_slot(value))
+        });
+        // drop the old value out of the borrow
+        drop(old);
+    }
+
+    /// Replaces the value of this coroutine-local key, returning the old value
+    ///
+    /// the key is initialized first if it's not accessed before.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the key is currently accessed by `with`
+    pub fn replace(&'static self, value: T) -> T {
+        let key = (self.__key)();
+        with(|data| {
+            let mut data = data.borrow_mut();
+            let entry = data
+                .entry(key)
+                .or_insert_with(|| self.new_slot((self.__init)()));
+            entry.check_unborrowed();
+            let slot = unsafe { &mut *(&mut **entry as *mut Slot<dyn Opaque> as *mut Slot<T>) };
+            mem::replace(&mut slot.value, value)
+        })
+    }
+
+    /// Takes the value of this coroutine-local key, leaving `Default::default()`
+    ///
+    /// # Panics
+    ///
+    /// Panics if the key is currently accessed by `with`
+    pub fn take(&'static self) -> T
+    where
+        T: Default,
+    {
+        self.replace(T::default())
+    }
+
+    fn new_slot(&'static self, value: T) -> Box<Slot<dyn Opaque>> {
+        Box::new(Slot {
+            borrows: Cell::new(0),
+            inherit: self.__inherit.map(|_| self as &'static dyn Inherit),
+            value,
+        })
+    }
+}
+
+impl<T: 'static> Inherit for LocalKey<T> {
+    fn inherit(&self, slot: &Slot<dyn Opaque>) -> Box<Slot<dyn Opaque>> {
+        let slot = unsafe { &*(slot as *const Slot<dyn Opaque> as *const Slot<T>) };
+        let clone = self.__inherit.expect("not an inherited local");
+        Box::new(Slot {
+            borrows: Cell::new(0),
+            inherit: slot.inherit,
+            value: clone(&slot.value),
         })
     }
 }
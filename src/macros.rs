@@ -156,9 +156,33 @@ macro_rules! join {
 ///
 /// The data associated with each coroutine local is per-coroutine,
 /// so different coroutines will contain different values.
+///
+/// With the `inherit` prefix, the value is cloned into the child coroutines
+/// spawned from the current context, the type must implement `Clone`.
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate may;
+///
+/// coroutine_local!(inherit static REQUEST_ID: u64 = 0);
+///
+/// fn main() {
+///     REQUEST_ID.set(42);
+///     let id = go!(|| REQUEST_ID.with(|id| *id)).join().unwrap();
+///     assert_eq!(id, 42);
+/// }
+/// ```
 #[macro_export]
 macro_rules! coroutine_local {
     (static $NAME:ident : $t:ty = $e:expr) => {
+        $crate::coroutine_local!(@key $NAME, $t, $e, None);
+    };
+    (inherit static $NAME:ident : $t:ty = $e:expr) => {
+        $crate::coroutine_local!(
+            @key $NAME, $t, $e, Some(<$t as ::std::clone::Clone>::clone)
+        );
+    };
+    (@key $NAME:ident, $t:ty, $e:expr, $inherit:expr) => {
         static $NAME: $crate::LocalKey<$t> = {
             fn __init() -> $t {
                 $e
@@ -170,6 +194,7 @@ macro_rules! coroutine_local {
             $crate::LocalKey {
                 __init: __init,
                 __key: __key,
+                __inherit: $inherit,
             }
         };
     };
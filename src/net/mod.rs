@@ -1,8 +1,23 @@
 //! Networking primitives
 //!
 
+#[cfg(unix)]
+pub(crate) mod msg;
+#[cfg(unix)]
+pub mod raw;
+#[cfg(unix)]
+pub(crate) mod sockopt;
 mod tcp;
+#[cfg(feature = "tls")]
+pub mod tls;
 mod udp;
 
-pub use self::tcp::{TcpListener, TcpStream};
-pub use self::udp::UdpSocket;
+#[cfg(unix)]
+pub use self::raw::RawSocket;
+#[cfg(unix)]
+pub use self::tcp::TcpSocket;
+pub use self::tcp::{TcpListener, TcpListenerBuilder, TcpStream};
+pub use self::udp::{RecvMsg, SendMsg, UdpSocket};
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub use self::msg::{RecvMeta, SendMeta};
@@ -0,0 +1,208 @@
+//! ancillary data (control messages) support for `sendmsg`/`recvmsg`
+
+use std::mem;
+use std::ptr;
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// the control message buffer size, enough for all the supported messages
+const CMSG_BUF_SIZE: usize = 512;
+
+// an aligned buffer for the control messages
+pub(crate) struct CmsgBuf {
+    buf: [u64; CMSG_BUF_SIZE / 8],
+    len: usize,
+}
+
+impl CmsgBuf {
+    pub fn new() -> Self {
+        CmsgBuf {
+            buf: [0; CMSG_BUF_SIZE / 8],
+            len: 0,
+        }
+    }
+
+    // append a control message, return false if there is no enough space
+    pub fn push<T: Copy>(&mut self, level: libc::c_int, ty: libc::c_int, data: &[T]) -> bool {
+        let size = mem::size_of_val(data);
+        let space = unsafe { libc::CMSG_SPACE(size as _) } as usize;
+        if self.len + space > CMSG_BUF_SIZE {
+            return false;
+        }
+        unsafe {
+            let cmsg = (self.buf.as_mut_ptr() as *mut u8).add(self.len) as *mut libc::cmsghdr;
+            (*cmsg).cmsg_level = level;
+            (*cmsg).cmsg_type = ty;
+            (*cmsg).cmsg_len = libc::CMSG_LEN(size as _) as _;
+            ptr::copy_nonoverlapping(data.as_ptr() as *const u8, libc::CMSG_DATA(cmsg), size);
+        }
+        self.len += space;
+        true
+    }
+
+    // set the msghdr control fields to the written messages
+    pub fn fill_send(&mut self, hdr: &mut libc::msghdr) {
+        if self.len == 0 {
+            hdr.msg_control = ptr::null_mut();
+            hdr.msg_controllen = 0;
+        } else {
+            hdr.msg_control = self.buf.as_mut_ptr() as *mut libc::c_void;
+            hdr.msg_controllen = self.len as _;
+        }
+    }
+
+    // set the msghdr control fields to the whole buffer for receiving
+    pub fn fill_recv(&mut self, hdr: &mut libc::msghdr) {
+        hdr.msg_control = self.buf.as_mut_ptr() as *mut libc::c_void;
+        hdr.msg_controllen = CMSG_BUF_SIZE as _;
+    }
+}
+
+// iterate the received control messages, `f` gets level, type and the data
+pub(crate) fn for_each_cmsg<F>(hdr: &libc::msghdr, mut f: F)
+where
+    F: FnMut(libc::c_int, libc::c_int, &[u8]),
+{
+    if hdr.msg_control.is_null() {
+        return;
+    }
+    unsafe {
+        let mut cmsg = libc::CMSG_FIRSTHDR(hdr);
+        while !cmsg.is_null() {
+            let data = libc::CMSG_DATA(cmsg);
+            let head = data as usize - cmsg as usize;
+            let len = ((*cmsg).cmsg_len as usize).saturating_sub(head);
+            f(
+                (*cmsg).cmsg_level,
+                (*cmsg).cmsg_type,
+                std::slice::from_raw_parts(data, len),
+            );
+            cmsg = libc::CMSG_NXTHDR(hdr, cmsg);
+        }
+    }
+}
+
+// read a value from the unaligned control message data
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn read_data<T: Copy>(data: &[u8]) -> Option<T> {
+    if data.len() < mem::size_of::<T>() {
+        return None;
+    }
+    Some(unsafe { ptr::read_unaligned(data.as_ptr() as *const T) })
+}
+
+/// The ancillary data to send with a datagram
+///
+/// used by [`UdpSocket::send_msg`](struct.UdpSocket.html#method.send_msg)
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SendMeta {
+    /// the source address of the datagram (`IP_PKTINFO`/`IPV6_PKTINFO`)
+    pub src_addr: Option<IpAddr>,
+    /// split the data into segments of this size in the kernel (`UDP_SEGMENT`)
+    pub segment_size: Option<u16>,
+    /// the ECN bits of the datagram (`IP_TOS`/`IPV6_TCLASS`)
+    pub ecn: Option<u8>,
+}
+
+/// The ancillary data of a received datagram
+///
+/// returned by [`UdpSocket::recv_msg`](struct.UdpSocket.html#method.recv_msg),
+/// the optional fields are only filled when the corresponding socket option
+/// is enabled.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RecvMeta {
+    /// number of bytes received
+    pub len: usize,
+    /// the source address of the datagram
+    pub addr: Option<SocketAddr>,
+    /// the destination address of the datagram (`IP_PKTINFO`/`IPV6_RECVPKTINFO`)
+    pub dst_addr: Option<IpAddr>,
+    /// the kernel receive timestamp (`SO_TIMESTAMPNS`)
+    pub timestamp: Option<SystemTime>,
+    /// the size of each coalesced segment (`UDP_GRO`)
+    pub segment_size: Option<u16>,
+    /// the ECN bits of the datagram (`IP_RECVTOS`/`IPV6_RECVTCLASS`)
+    pub ecn: Option<u8>,
+    /// the datagram is larger than the buffers and the rest part is discarded
+    pub truncated: bool,
+    /// some control messages are discarded due to lack of buffer space
+    pub ctrl_truncated: bool,
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) fn encode_send_meta(meta: &SendMeta, v6: bool, buf: &mut CmsgBuf) {
+    match meta.src_addr {
+        Some(IpAddr::V4(ip)) => {
+            let mut info: libc::in_pktinfo = unsafe { mem::zeroed() };
+            info.ipi_spec_dst.s_addr = u32::from(ip).to_be();
+            buf.push(libc::IPPROTO_IP, libc::IP_PKTINFO, &[info]);
+        }
+        Some(IpAddr::V6(ip)) => {
+            let mut info: libc::in6_pktinfo = unsafe { mem::zeroed() };
+            info.ipi6_addr.s6_addr = ip.octets();
+            buf.push(libc::IPPROTO_IPV6, libc::IPV6_PKTINFO, &[info]);
+        }
+        None => {}
+    }
+
+    if let Some(size) = meta.segment_size {
+        buf.push(libc::SOL_UDP, libc::UDP_SEGMENT, &[size]);
+    }
+
+    if let Some(ecn) = meta.ecn {
+        let ecn = (ecn & 0b11) as libc::c_int;
+        if v6 {
+            buf.push(libc::IPPROTO_IPV6, libc::IPV6_TCLASS, &[ecn]);
+        } else {
+            buf.push(libc::IPPROTO_IP, libc::IP_TOS, &[ecn]);
+        }
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) fn decode_recv_meta(hdr: &libc::msghdr, meta: &mut RecvMeta) {
+    meta.truncated = hdr.msg_flags & libc::MSG_TRUNC != 0;
+    meta.ctrl_truncated = hdr.msg_flags & libc::MSG_CTRUNC != 0;
+
+    for_each_cmsg(hdr, |level, ty, data| match (level, ty) {
+        (libc::IPPROTO_IP, libc::IP_PKTINFO) => {
+            if let Some(info) = read_data::<libc::in_pktinfo>(data) {
+                let ip = Ipv4Addr::from(u32::from_be(info.ipi_addr.s_addr));
+                meta.dst_addr = Some(IpAddr::V4(ip));
+            }
+        }
+        (libc::IPPROTO_IPV6, libc::IPV6_PKTINFO) => {
+            if let Some(info) = read_data::<libc::in6_pktinfo>(data) {
+                meta.dst_addr = Some(IpAddr::V6(Ipv6Addr::from(info.ipi6_addr.s6_addr)));
+            }
+        }
+        (libc::SOL_SOCKET, libc::SCM_TIMESTAMPNS) => {
+            if let Some(ts) = read_data::<libc::timespec>(data) {
+                let dur = Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32);
+                meta.timestamp = Some(UNIX_EPOCH + dur);
+            }
+        }
+        (libc::SOL_UDP, libc::UDP_GRO) => {
+            if let Some(size) = read_data::<libc::c_int>(data) {
+                meta.segment_size = Some(size as u16);
+            }
+        }
+        // the kernel gives one byte for IP_TOS
+        (libc::IPPROTO_IP, libc::IP_TOS) => {
+            if let Some(tos) = read_data::<u8>(data) {
+                meta.ecn = Some(tos & 0b11);
+            }
+        }
+        (libc::IPPROTO_IPV6, libc::IPV6_TCLASS) => {
+            if let Some(tclass) = read_data::<libc::c_int>(data) {
+                meta.ecn = Some((tclass & 0b11) as u8);
+            }
+        }
+        _ => {}
+    });
+}
@@ -0,0 +1,203 @@
+//! Coroutine aware raw sockets
+//!
+//! the `socket2` types that are needed to create a [`RawSocket`] are
+//! re-exported here.
+//!
+//! [`RawSocket`]: struct.RawSocket.html
+
+use std::io;
+use std::mem;
+use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd};
+use std::time::Duration;
+
+use crate::io::CoIo;
+pub use socket2::{Domain, Protocol, SockAddr, Socket, Type};
+
+/// A coroutine aware wrapper of any datagram style socket
+///
+/// this is useful for the sockets that are not covered by `UdpSocket`, e.g.
+/// raw IP sockets, `AF_PACKET` and netlink sockets. the socket options are
+/// available through [`socket`](#method.socket).
+#[derive(Debug)]
+pub struct RawSocket(CoIo<Socket>);
+
+/// The result of [`RawSocket::recv_msg`](struct.RawSocket.html#method.recv_msg)
+#[derive(Debug)]
+pub struct RawRecvMeta {
+    /// number of bytes received
+    pub len: usize,
+    /// the source address of the message
+    pub addr: SockAddr,
+    /// number of bytes of the ancillary data written into the control buffer
+    pub control_len: usize,
+    /// the message is larger than the buffers and the rest part is discarded
+    pub truncated: bool,
+    /// the ancillary data is larger than the control buffer
+    pub ctrl_truncated: bool,
+}
+
+impl RawSocket {
+    /// create a new socket, the arguments are passed to `socket(2)`
+    pub fn new(domain: Domain, ty: Type, protocol: Option<Protocol>) -> io::Result<RawSocket> {
+        RawSocket::from_socket(Socket::new(domain, ty, protocol)?)
+    }
+
+    /// convert a `socket2::Socket` into `RawSocket`
+    pub fn from_socket(socket: Socket) -> io::Result<RawSocket> {
+        Ok(RawSocket(CoIo::new(socket)?))
+    }
+
+    /// get the underlying socket to access the socket options
+    pub fn socket(&self) -> &Socket {
+        self.0.inner()
+    }
+
+    /// convert back to the `socket2::Socket`
+    pub fn into_socket(self) -> Socket {
+        self.0.into_inner()
+    }
+
+    pub fn bind(&self, addr: &SockAddr) -> io::Result<()> {
+        self.0.inner().bind(addr)
+    }
+
+    /// set the default destination address
+    ///
+    /// this is only supported for the connectionless sockets which would
+    /// not block on connect
+    pub fn connect(&self, addr: &SockAddr) -> io::Result<()> {
+        self.0.inner().connect(addr)
+    }
+
+    pub fn local_addr(&self) -> io::Result<SockAddr> {
+        self.0.inner().local_addr()
+    }
+
+    pub fn peer_addr(&self) -> io::Result<SockAddr> {
+        self.0.inner().peer_addr()
+    }
+
+    pub fn send_to(&self, buf: &[u8], addr: &SockAddr) -> io::Result<usize> {
+        self.send_msg(&[io::IoSlice::new(buf)], Some(addr), &[])
+    }
+
+    pub fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SockAddr)> {
+        let meta = self.recv_msg(&mut [io::IoSliceMut::new(buf)], &mut [])?;
+        Ok((meta.len, meta.addr))
+    }
+
+    pub fn send(&self, buf: &[u8]) -> io::Result<usize> {
+        self.send_msg(&[io::IoSlice::new(buf)], None, &[])
+    }
+
+    pub fn recv(&self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut iov = libc::iovec {
+            iov_base: buf.as_mut_ptr() as *mut libc::c_void,
+            iov_len: buf.len(),
+        };
+        let mut hdr: libc::msghdr = unsafe { mem::zeroed() };
+        hdr.msg_iov = &mut iov;
+        hdr.msg_iovlen = 1;
+        self.0.recv_msg_hdr(&mut hdr, 0)
+    }
+
+    /// send a message with the raw ancillary data
+    ///
+    /// `addr` is the destination address, `None` means the connected peer.
+    /// `control` is a sequence of `cmsghdr` which would be passed to the
+    /// kernel as it is.
+    pub fn send_msg(
+        &self,
+        bufs: &[io::IoSlice<'_>],
+        addr: Option<&SockAddr>,
+        control: &[u8],
+    ) -> io::Result<usize> {
+        let mut hdr: libc::msghdr = unsafe { mem::zeroed() };
+        if let Some(addr) = addr {
+            hdr.msg_name = addr.as_ptr() as *mut libc::c_void;
+            hdr.msg_namelen = addr.len();
+        }
+        hdr.msg_iov = bufs.as_ptr() as *mut libc::iovec;
+        hdr.msg_iovlen = bufs.len() as _;
+        if !control.is_empty() {
+            hdr.msg_control = control.as_ptr() as *mut libc::c_void;
+            hdr.msg_controllen = control.len() as _;
+        }
+        self.0.send_msg_hdr(&hdr, 0)
+    }
+
+    /// receive a message with the raw ancillary data
+    ///
+    /// the ancillary data is written into `control` as a sequence of
+    /// `cmsghdr`, an empty `control` means no ancillary data is wanted.
+    pub fn recv_msg(
+        &self,
+        bufs: &mut [io::IoSliceMut<'_>],
+        control: &mut [u8],
+    ) -> io::Result<RawRecvMeta> {
+        let mut addr: libc::sockaddr_storage = unsafe { mem::zeroed() };
+        let mut hdr: libc::msghdr = unsafe { mem::zeroed() };
+        hdr.msg_name = &mut addr as *mut _ as *mut libc::c_void;
+        hdr.msg_namelen = mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+        hdr.msg_iov = bufs.as_mut_ptr() as *mut libc::iovec;
+        hdr.msg_iovlen = bufs.len() as _;
+        if !control.is_empty() {
+            hdr.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+            hdr.msg_controllen = control.len() as _;
+        }
+
+        let len = self.0.recv_msg_hdr(&mut hdr, 0)?;
+        let addr = unsafe {
+            SockAddr::from_raw_parts(&addr as *const _ as *const libc::sockaddr, hdr.msg_namelen)
+        };
+        Ok(RawRecvMeta {
+            len,
+            addr,
+            control_len: hdr.msg_controllen as usize,
+            truncated: hdr.msg_flags & libc::MSG_TRUNC != 0,
+            ctrl_truncated: hdr.msg_flags & libc::MSG_CTRUNC != 0,
+        })
+    }
+
+    pub fn set_read_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        self.0.set_read_timeout(dur)
+    }
+
+    pub fn set_write_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        self.0.set_write_timeout(dur)
+    }
+
+    pub fn read_timeout(&self) -> io::Result<Option<Duration>> {
+        self.0.read_timeout()
+    }
+
+    pub fn write_timeout(&self) -> io::Result<Option<Duration>> {
+        self.0.write_timeout()
+    }
+
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        self.0.set_nonblocking(nonblocking)
+    }
+
+    pub fn take_error(&self) -> io::Result<Option<io::Error>> {
+        self.0.inner().take_error()
+    }
+}
+
+impl AsRawFd for RawSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0.as_raw_fd()
+    }
+}
+
+impl FromRawFd for RawSocket {
+    unsafe fn from_raw_fd(fd: RawFd) -> RawSocket {
+        RawSocket::from_socket(Socket::from_raw_fd(fd)).expect("can't convert to RawSocket")
+    }
+}
+
+impl IntoRawFd for RawSocket {
+    fn into_raw_fd(self) -> RawFd {
+        self.0.into_raw_fd()
+    }
+}
@@ -0,0 +1,176 @@
+//! raw socket option helpers for the options not covered by std or socket2
+
+use std::os::unix::io::RawFd;
+use std::time::Duration;
+use std::{cmp, io, mem};
+
+pub fn setsockopt<T: Copy>(
+    fd: RawFd,
+    level: libc::c_int,
+    name: libc::c_int,
+    val: T,
+) -> io::Result<()> {
+    let ret = unsafe {
+        libc::setsockopt(
+            fd,
+            level,
+            name,
+            &val as *const T as *const libc::c_void,
+            mem::size_of::<T>() as libc::socklen_t,
+        )
+    };
+    if ret == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+pub fn getsockopt<T: Copy>(fd: RawFd, level: libc::c_int, name: libc::c_int) -> io::Result<T> {
+    let mut val: T = unsafe { mem::zeroed() };
+    let mut len = mem::size_of::<T>() as libc::socklen_t;
+    let ret = unsafe {
+        libc::getsockopt(
+            fd,
+            level,
+            name,
+            &mut val as *mut T as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    if ret == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(val)
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+const KEEPALIVE_IDLE: libc::c_int = libc::TCP_KEEPALIVE;
+#[cfg(not(any(target_os = "macos", target_os = "ios")))]
+const KEEPALIVE_IDLE: libc::c_int = libc::TCP_KEEPIDLE;
+
+pub fn set_keepalive(fd: RawFd, keepalive: bool) -> io::Result<()> {
+    setsockopt(
+        fd,
+        libc::SOL_SOCKET,
+        libc::SO_KEEPALIVE,
+        keepalive as libc::c_int,
+    )
+}
+
+pub fn keepalive(fd: RawFd) -> io::Result<bool> {
+    getsockopt::<libc::c_int>(fd, libc::SOL_SOCKET, libc::SO_KEEPALIVE).map(|v| v != 0)
+}
+
+// the keepalive durations are in seconds, at least 1 second
+fn dur_to_secs(dur: Duration) -> libc::c_int {
+    cmp::max(cmp::min(dur.as_secs(), libc::c_int::MAX as u64), 1) as libc::c_int
+}
+
+pub fn set_keepalive_idle(fd: RawFd, idle: Duration) -> io::Result<()> {
+    setsockopt(fd, libc::IPPROTO_TCP, KEEPALIVE_IDLE, dur_to_secs(idle))
+}
+
+pub fn keepalive_idle(fd: RawFd) -> io::Result<Duration> {
+    getsockopt::<libc::c_int>(fd, libc::IPPROTO_TCP, KEEPALIVE_IDLE)
+        .map(|secs| Duration::from_secs(secs as u64))
+}
+
+pub fn set_keepalive_interval(fd: RawFd, interval: Duration) -> io::Result<()> {
+    setsockopt(
+        fd,
+        libc::IPPROTO_TCP,
+        libc::TCP_KEEPINTVL,
+        dur_to_secs(interval),
+    )
+}
+
+pub fn keepalive_interval(fd: RawFd) -> io::Result<Duration> {
+    getsockopt::<libc::c_int>(fd, libc::IPPROTO_TCP, libc::TCP_KEEPINTVL)
+        .map(|secs| Duration::from_secs(secs as u64))
+}
+
+pub fn set_keepalive_count(fd: RawFd, count: u32) -> io::Result<()> {
+    setsockopt(
+        fd,
+        libc::IPPROTO_TCP,
+        libc::TCP_KEEPCNT,
+        count as libc::c_int,
+    )
+}
+
+pub fn keepalive_count(fd: RawFd) -> io::Result<u32> {
+    getsockopt::<libc::c_int>(fd, libc::IPPROTO_TCP, libc::TCP_KEEPCNT).map(|v| v as u32)
+}
+
+pub fn set_linger(fd: RawFd, linger: Option<Duration>) -> io::Result<()> {
+    let val = libc::linger {
+        l_onoff: linger.is_some() as libc::c_int,
+        l_linger: linger.map(|d| d.as_secs() as libc::c_int).unwrap_or(0),
+    };
+    setsockopt(fd, libc::SOL_SOCKET, libc::SO_LINGER, val)
+}
+
+pub fn linger(fd: RawFd) -> io::Result<Option<Duration>> {
+    let val: libc::linger = getsockopt(fd, libc::SOL_SOCKET, libc::SO_LINGER)?;
+    if val.l_onoff == 0 {
+        return Ok(None);
+    }
+    Ok(Some(Duration::from_secs(val.l_linger as u64)))
+}
+
+pub fn set_send_buffer_size(fd: RawFd, size: usize) -> io::Result<()> {
+    setsockopt(fd, libc::SOL_SOCKET, libc::SO_SNDBUF, size as libc::c_int)
+}
+
+pub fn send_buffer_size(fd: RawFd) -> io::Result<usize> {
+    getsockopt::<libc::c_int>(fd, libc::SOL_SOCKET, libc::SO_SNDBUF).map(|v| v as usize)
+}
+
+pub fn set_recv_buffer_size(fd: RawFd, size: usize) -> io::Result<()> {
+    setsockopt(fd, libc::SOL_SOCKET, libc::SO_RCVBUF, size as libc::c_int)
+}
+
+pub fn recv_buffer_size(fd: RawFd) -> io::Result<usize> {
+    getsockopt::<libc::c_int>(fd, libc::SOL_SOCKET, libc::SO_RCVBUF).map(|v| v as usize)
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn set_user_timeout(fd: RawFd, timeout: Option<Duration>) -> io::Result<()> {
+    // zero means use the system default
+    let ms = timeout
+        .map(|d| cmp::min(d.as_millis(), libc::c_uint::MAX as u128) as libc::c_uint)
+        .unwrap_or(0);
+    setsockopt(fd, libc::IPPROTO_TCP, libc::TCP_USER_TIMEOUT, ms)
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn user_timeout(fd: RawFd) -> io::Result<Option<Duration>> {
+    let ms: libc::c_uint = getsockopt(fd, libc::IPPROTO_TCP, libc::TCP_USER_TIMEOUT)?;
+    if ms == 0 {
+        return Ok(None);
+    }
+    Ok(Some(Duration::from_millis(ms as u64)))
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn set_quickack(fd: RawFd, quickack: bool) -> io::Result<()> {
+    setsockopt(
+        fd,
+        libc::IPPROTO_TCP,
+        libc::TCP_QUICKACK,
+        quickack as libc::c_int,
+    )
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn quickack(fd: RawFd) -> io::Result<bool> {
+    getsockopt::<libc::c_int>(fd, libc::IPPROTO_TCP, libc::TCP_QUICKACK).map(|v| v != 0)
+}
+
+pub fn set_tos(fd: RawFd, tos: u32) -> io::Result<()> {
+    setsockopt(fd, libc::IPPROTO_IP, libc::IP_TOS, tos as libc::c_int)
+}
+
+pub fn tos(fd: RawFd) -> io::Result<u32> {
+    getsockopt::<libc::c_int>(fd, libc::IPPROTO_IP, libc::IP_TOS).map(|v| v as u32)
+}
@@ -5,7 +5,16 @@ use std::time::Duration;
 use crate::coroutine_impl::is_coroutine;
 use crate::io as io_impl;
 use crate::io::net as net_impl;
+#[cfg(unix)]
+use crate::scheduler::get_scheduler;
+
+#[cfg(unix)]
+use super::sockopt;
+#[cfg(unix)]
+use crate::io::sys::wait_io::io_or_cancel;
 use crate::sync::atomic_dur::AtomicDuration;
+#[cfg(unix)]
+use crate::sync::CancellationToken;
 use crate::yield_now::yield_with;
 
 // ===== TcpStream =====
@@ -161,6 +170,18 @@ impl TcpStream {
         self.sys.ttl()
     }
 
+    // return Ok(true) if the io should go through the coroutine path
+    #[cfg(unix)]
+    pub(crate) fn ctx_check(&self) -> io::Result<bool> {
+        if self
+            .ctx
+            .check_nonblocking(|b| self.sys.set_nonblocking(b))?
+        {
+            return Ok(false);
+        }
+        self.ctx.check_context(|b| self.sys.set_nonblocking(b))
+    }
+
     // convert std::net::TcpStream to Self without add_socket
     pub(crate) fn from_stream(s: net::TcpStream, io: io_impl::IoData) -> Self {
         TcpStream {
@@ -173,6 +194,145 @@ impl TcpStream {
     }
 }
 
+// socket options that are not provided by std
+#[cfg(unix)]
+impl TcpStream {
+    /// set the `SO_KEEPALIVE` option
+    pub fn set_keepalive(&self, keepalive: bool) -> io::Result<()> {
+        sockopt::set_keepalive(self.as_raw_fd(), keepalive)
+    }
+
+    /// get the `SO_KEEPALIVE` option
+    pub fn keepalive(&self) -> io::Result<bool> {
+        sockopt::keepalive(self.as_raw_fd())
+    }
+
+    /// set the idle time before the first keepalive probe is sent, in seconds
+    pub fn set_keepalive_idle(&self, idle: Duration) -> io::Result<()> {
+        sockopt::set_keepalive_idle(self.as_raw_fd(), idle)
+    }
+
+    /// get the idle time before the first keepalive probe is sent
+    pub fn keepalive_idle(&self) -> io::Result<Duration> {
+        sockopt::keepalive_idle(self.as_raw_fd())
+    }
+
+    /// set the interval between keepalive probes, in seconds
+    pub fn set_keepalive_interval(&self, interval: Duration) -> io::Result<()> {
+        sockopt::set_keepalive_interval(self.as_raw_fd(), interval)
+    }
+
+    /// get the interval between keepalive probes
+    pub fn keepalive_interval(&self) -> io::Result<Duration> {
+        sockopt::keepalive_interval(self.as_raw_fd())
+    }
+
+    /// set the number of unacknowledged probes before the connection is dropped
+    pub fn set_keepalive_count(&self, count: u32) -> io::Result<()> {
+        sockopt::set_keepalive_count(self.as_raw_fd(), count)
+    }
+
+    /// get the number of unacknowledged probes before the connection is dropped
+    pub fn keepalive_count(&self) -> io::Result<u32> {
+        sockopt::keepalive_count(self.as_raw_fd())
+    }
+
+    /// set the `SO_LINGER` option, `None` disables it
+    ///
+    /// note that a non-zero linger would block the thread in `close`, only
+    /// `Some(Duration::from_secs(0))` is recommended in coroutine context
+    pub fn set_linger(&self, linger: Option<Duration>) -> io::Result<()> {
+        sockopt::set_linger(self.as_raw_fd(), linger)
+    }
+
+    /// get the `SO_LINGER` option
+    pub fn linger(&self) -> io::Result<Option<Duration>> {
+        sockopt::linger(self.as_raw_fd())
+    }
+
+    /// set the `SO_SNDBUF` option
+    pub fn set_send_buffer_size(&self, size: usize) -> io::Result<()> {
+        sockopt::set_send_buffer_size(self.as_raw_fd(), size)
+    }
+
+    /// get the `SO_SNDBUF` option
+    pub fn send_buffer_size(&self) -> io::Result<usize> {
+        sockopt::send_buffer_size(self.as_raw_fd())
+    }
+
+    /// set the `SO_RCVBUF` option
+    pub fn set_recv_buffer_size(&self, size: usize) -> io::Result<()> {
+        sockopt::set_recv_buffer_size(self.as_raw_fd(), size)
+    }
+
+    /// get the `SO_RCVBUF` option
+    pub fn recv_buffer_size(&self) -> io::Result<usize> {
+        sockopt::recv_buffer_size(self.as_raw_fd())
+    }
+
+    /// set the `TCP_USER_TIMEOUT` option, `None` means the system default
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn set_user_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        sockopt::set_user_timeout(self.as_raw_fd(), timeout)
+    }
+
+    /// get the `TCP_USER_TIMEOUT` option
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn user_timeout(&self) -> io::Result<Option<Duration>> {
+        sockopt::user_timeout(self.as_raw_fd())
+    }
+
+    /// set the `TCP_QUICKACK` option
+    ///
+    /// the kernel may reset it after sending ack, so it's usually set
+    /// before each read
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn set_quickack(&self, quickack: bool) -> io::Result<()> {
+        sockopt::set_quickack(self.as_raw_fd(), quickack)
+    }
+
+    /// get the `TCP_QUICKACK` option
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn quickack(&self) -> io::Result<bool> {
+        sockopt::quickack(self.as_raw_fd())
+    }
+
+    /// set the `IP_TOS` option, only valid for ipv4 sockets
+    pub fn set_tos(&self, tos: u32) -> io::Result<()> {
+        sockopt::set_tos(self.as_raw_fd(), tos)
+    }
+
+    /// get the `IP_TOS` option
+    pub fn tos(&self) -> io::Result<u32> {
+        sockopt::tos(self.as_raw_fd())
+    }
+}
+
+#[cfg(unix)]
+impl TcpStream {
+    /// read from the stream, or return an error built from `Cancelled`
+    /// once the token is cancelled
+    ///
+    /// the token is only checked before the read when not running in a
+    /// coroutine, because the blocking read can't be interrupted
+    pub fn read_or_cancel(&self, buf: &mut [u8], token: &CancellationToken) -> io::Result<usize> {
+        let ctx = self.ctx_check()?;
+        let timeout = self.read_timeout.get();
+        io_or_cancel(&self.io, ctx, timeout, token, || (&self.sys).read(buf))
+    }
+
+    /// write to the stream, or return an error built from `Cancelled`
+    /// once the token is cancelled
+    ///
+    /// the token is only checked before the write when not running in a
+    /// coroutine, because the blocking write can't be interrupted
+    pub fn write_or_cancel(&self, buf: &[u8], token: &CancellationToken) -> io::Result<usize> {
+        let ctx = self.ctx_check()?;
+        let timeout = self.write_timeout.get();
+        io_or_cancel(&self.io, ctx, timeout, token, || (&self.sys).write(buf))
+    }
+}
+
 impl Read for TcpStream {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         if self
@@ -206,6 +366,37 @@ impl Read for TcpStream {
         yield_with(&reader);
         reader.done()
     }
+
+    #[cfg(unix)]
+    fn read_vectored(&mut self, bufs: &mut [io::IoSliceMut<'_>]) -> io::Result<usize> {
+        if self
+            .ctx
+            .check_nonblocking(|b| self.sys.set_nonblocking(b))?
+            || !self.ctx.check_context(|b| self.sys.set_nonblocking(b))?
+        {
+            return self.sys.read_vectored(bufs);
+        }
+
+        self.io.reset();
+        // this is an earlier return try for nonblocking read
+        match self.sys.read_vectored(bufs) {
+            Ok(n) => return Ok(n),
+            Err(e) => {
+                // raw_os_error is faster than kind
+                let raw_err = e.raw_os_error();
+                if raw_err == Some(libc::EAGAIN) || raw_err == Some(libc::EWOULDBLOCK) {
+                    // do nothing here
+                } else {
+                    return Err(e);
+                }
+            }
+        }
+
+        let timeout = self.read_timeout.get();
+        let mut reader = net_impl::SocketReadVectored::new(self, bufs, timeout);
+        yield_with(&reader);
+        reader.done()
+    }
 }
 
 impl Write for TcpStream {
@@ -269,8 +460,7 @@ impl Write for TcpStream {
             }
         }
 
-        let mut writer =
-            net_impl::SocketWriteVectored::new(self, &self.sys, bufs, self.write_timeout.get());
+        let mut writer = net_impl::SocketWriteVectored::new(self, bufs, self.write_timeout.get());
         yield_with(&writer);
         writer.done()
     }
@@ -337,28 +527,7 @@ impl TcpListener {
     }
 
     pub fn bind<A: ToSocketAddrs>(addr: A) -> io::Result<TcpListener> {
-        use socket2::{Domain, Socket, Type};
-        let mut addrs = addr.to_socket_addrs()?;
-        let addr = addrs.next().unwrap();
-        let listener = match &addr {
-            SocketAddr::V4(_) => Socket::new(Domain::ipv4(), Type::stream(), None)?,
-            SocketAddr::V6(_) => Socket::new(Domain::ipv6(), Type::stream(), None)?,
-        };
-
-        // windows not have reuset port but reuse address is not safe
-        listener.set_reuse_address(true)?;
-
-        #[cfg(unix)]
-        listener.set_reuse_port(true)?;
-
-        listener.bind(&addr.into())?;
-        for addr in addrs {
-            listener.bind(&addr.into())?;
-        }
-        listener.listen(256)?;
-
-        let s = listener.into_tcp_listener();
-        TcpListener::new(s)
+        TcpListenerBuilder::new().bind(addr)
     }
 
     pub fn accept(&self) -> io::Result<(TcpStream, SocketAddr)> {
@@ -395,6 +564,24 @@ impl TcpListener {
         a.done()
     }
 
+    /// accept a new connection, or return an error built from `Cancelled`
+    /// once the token is cancelled
+    ///
+    /// the token is only checked before the accept when not running in a
+    /// coroutine, because the blocking accept can't be interrupted
+    #[cfg(unix)]
+    pub fn accept_or_cancel(
+        &self,
+        token: &CancellationToken,
+    ) -> io::Result<(TcpStream, SocketAddr)> {
+        let nonblocking = self
+            .ctx
+            .check_nonblocking(|b| self.sys.set_nonblocking(b))?;
+        let ctx = !nonblocking && self.ctx.check_context(|b| self.sys.set_nonblocking(b))?;
+        let (s, a) = io_or_cancel(&self.io, ctx, None, token, || self.sys.accept())?;
+        TcpStream::new(s).map(|s| (s, a))
+    }
+
     pub fn incoming(&self) -> Incoming {
         Incoming { listener: self }
     }
@@ -440,6 +627,380 @@ impl io_impl::AsIoData for TcpListener {
     }
 }
 
+// ===== TcpListenerBuilder =====
+//
+//
+
+/// A builder to configure the listening socket before binding it
+///
+/// The default settings are the same as `TcpListener::bind`
+///
+/// # Examples
+///
+/// ```no_run
+/// use may::net::TcpListenerBuilder;
+///
+/// let listener = TcpListenerBuilder::new()
+///     .backlog(1024)
+///     .bind("127.0.0.1:8080")
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct TcpListenerBuilder {
+    backlog: i32,
+    reuse_address: bool,
+    #[cfg(unix)]
+    reuse_port: bool,
+    only_v6: Option<bool>,
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fastopen: Option<u32>,
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    defer_accept: Option<u32>,
+}
+
+impl Default for TcpListenerBuilder {
+    fn default() -> Self {
+        TcpListenerBuilder::new()
+    }
+}
+
+impl TcpListenerBuilder {
+    /// create a builder with the default settings
+    pub fn new() -> Self {
+        TcpListenerBuilder {
+            backlog: 256,
+            // windows not have reuset port but reuse address is not safe
+            reuse_address: true,
+            #[cfg(unix)]
+            reuse_port: true,
+            only_v6: None,
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            fastopen: None,
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            defer_accept: None,
+        }
+    }
+
+    /// set the max length of the pending connections queue, default is 256
+    pub fn backlog(&mut self, backlog: i32) -> &mut Self {
+        self.backlog = backlog;
+        self
+    }
+
+    /// set the `SO_REUSEADDR` option, default is `true`
+    pub fn reuse_address(&mut self, reuse: bool) -> &mut Self {
+        self.reuse_address = reuse;
+        self
+    }
+
+    /// set the `SO_REUSEPORT` option, default is `true`
+    #[cfg(unix)]
+    pub fn reuse_port(&mut self, reuse: bool) -> &mut Self {
+        self.reuse_port = reuse;
+        self
+    }
+
+    /// set the `IPV6_V6ONLY` option, only valid for ipv6 addresses
+    pub fn only_v6(&mut self, only_v6: bool) -> &mut Self {
+        self.only_v6 = Some(only_v6);
+        self
+    }
+
+    /// enable `TCP_FASTOPEN` with the given max pending syn queue length
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn fastopen(&mut self, queue_len: u32) -> &mut Self {
+        self.fastopen = Some(queue_len);
+        self
+    }
+
+    /// enable `TCP_DEFER_ACCEPT`, the connection is only accepted when data
+    /// arrives or the given seconds elapsed
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn defer_accept(&mut self, secs: u32) -> &mut Self {
+        self.defer_accept = Some(secs);
+        self
+    }
+
+    fn listen(&self, addr: &SocketAddr) -> io::Result<net::TcpListener> {
+        use socket2::{Domain, Socket, Type};
+        let listener = match addr {
+            SocketAddr::V4(_) => Socket::new(Domain::ipv4(), Type::stream(), None)?,
+            SocketAddr::V6(_) => Socket::new(Domain::ipv6(), Type::stream(), None)?,
+        };
+
+        listener.set_reuse_address(self.reuse_address)?;
+
+        #[cfg(unix)]
+        listener.set_reuse_port(self.reuse_port)?;
+
+        if let (SocketAddr::V6(_), Some(only_v6)) = (addr, self.only_v6) {
+            listener.set_only_v6(only_v6)?;
+        }
+
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        {
+            use super::sockopt::setsockopt;
+            let fd = listener.as_raw_fd();
+            if let Some(qlen) = self.fastopen {
+                setsockopt(
+                    fd,
+                    libc::IPPROTO_TCP,
+                    libc::TCP_FASTOPEN,
+                    qlen as libc::c_int,
+                )?;
+            }
+            if let Some(secs) = self.defer_accept {
+                setsockopt(
+                    fd,
+                    libc::IPPROTO_TCP,
+                    libc::TCP_DEFER_ACCEPT,
+                    secs as libc::c_int,
+                )?;
+            }
+        }
+
+        listener.bind(&(*addr).into())?;
+        listener.listen(self.backlog)?;
+        Ok(listener.into_tcp_listener())
+    }
+
+    /// create the `TcpListener` with the configured options
+    pub fn bind<A: ToSocketAddrs>(&self, addr: A) -> io::Result<TcpListener> {
+        let addr = first_addr(addr)?;
+        self.listen(&addr).and_then(TcpListener::new)
+    }
+
+    /// create one listener for each io worker that all bind to the same address
+    ///
+    /// `SO_REUSEPORT` is always enabled so that the kernel would balance the
+    /// incoming connections among the listeners, and each listener is
+    /// registered to a different worker's selector. the `n`th listener is
+    /// registered to worker `n`, spawn its accept coroutine by
+    /// [`Builder::worker`] so that a new connection only wakes up and runs on
+    /// the owning worker.
+    ///
+    /// when the port is `0` all the listeners would share the port that the
+    /// first one bound to.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use may::coroutine;
+    /// use may::net::TcpListenerBuilder;
+    ///
+    /// let listeners = TcpListenerBuilder::new().bind_sharded("0.0.0.0:8080").unwrap();
+    /// for (id, listener) in listeners.into_iter().enumerate() {
+    ///     let builder = coroutine::Builder::new().worker(id);
+    ///     unsafe {
+    ///         builder.spawn(move || {
+    ///             for stream in listener.incoming() {
+    ///                 // handle the stream on worker `id`
+    ///                 drop(stream);
+    ///             }
+    ///         })
+    ///     }
+    ///     .unwrap();
+    /// }
+    /// ```
+    ///
+    /// [`Builder::worker`]: ../coroutine/struct.Builder.html#method.worker
+    #[cfg(unix)]
+    pub fn bind_sharded<A: ToSocketAddrs>(&self, addr: A) -> io::Result<Vec<TcpListener>> {
+        let mut addr = first_addr(addr)?;
+        let mut builder = self.clone();
+        builder.reuse_port = true;
+
+        let shards = get_scheduler().get_selector().size();
+        let mut listeners = Vec::with_capacity(shards);
+        for id in 0..shards {
+            let s = builder.listen(&addr)?;
+            addr = s.local_addr()?;
+            s.set_nonblocking(true)?;
+            let io = io_impl::add_socket_to(&s, id)?;
+            listeners.push(TcpListener {
+                io,
+                ctx: io_impl::IoContext::new(),
+                sys: s,
+            });
+        }
+        Ok(listeners)
+    }
+}
+
+fn first_addr<A: ToSocketAddrs>(addr: A) -> io::Result<SocketAddr> {
+    addr.to_socket_addrs()?.next().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "could not resolve to any addresses",
+        )
+    })
+}
+
+// ===== TcpSocket =====
+//
+//
+
+/// A TCP socket that has not yet been converted to a `TcpStream` or
+/// `TcpListener`
+///
+/// It's used to configure the options that must be set before connecting,
+/// e.g. binding to a local address or the buffer sizes.
+///
+/// # Examples
+///
+/// ```no_run
+/// use may::net::TcpSocket;
+///
+/// let socket = TcpSocket::new_v4().unwrap();
+/// socket.set_send_buffer_size(64 * 1024).unwrap();
+/// let stream = socket.connect("127.0.0.1:8080".parse().unwrap()).unwrap();
+/// ```
+#[cfg(unix)]
+#[derive(Debug)]
+pub struct TcpSocket {
+    sys: socket2::Socket,
+}
+
+#[cfg(unix)]
+impl TcpSocket {
+    fn new(domain: socket2::Domain) -> io::Result<TcpSocket> {
+        let sys = socket2::Socket::new(domain, socket2::Type::stream(), None)?;
+        Ok(TcpSocket { sys })
+    }
+
+    /// create a new ipv4 tcp socket
+    pub fn new_v4() -> io::Result<TcpSocket> {
+        TcpSocket::new(socket2::Domain::ipv4())
+    }
+
+    /// create a new ipv6 tcp socket
+    pub fn new_v6() -> io::Result<TcpSocket> {
+        TcpSocket::new(socket2::Domain::ipv6())
+    }
+
+    /// bind the socket to the given local address
+    pub fn bind(&self, addr: SocketAddr) -> io::Result<()> {
+        self.sys.bind(&addr.into())
+    }
+
+    /// get the local address of the socket
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.sys.local_addr().and_then(|addr| {
+            addr.as_inet()
+                .map(SocketAddr::V4)
+                .or_else(|| addr.as_inet6().map(SocketAddr::V6))
+                .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "not an inet address"))
+        })
+    }
+
+    /// set the `SO_REUSEADDR` option
+    pub fn set_reuse_address(&self, reuse: bool) -> io::Result<()> {
+        self.sys.set_reuse_address(reuse)
+    }
+
+    /// set the `SO_REUSEPORT` option
+    pub fn set_reuse_port(&self, reuse: bool) -> io::Result<()> {
+        self.sys.set_reuse_port(reuse)
+    }
+
+    /// set the `TCP_NODELAY` option
+    pub fn set_nodelay(&self, nodelay: bool) -> io::Result<()> {
+        self.sys.set_nodelay(nodelay)
+    }
+
+    /// set the `SO_KEEPALIVE` option
+    pub fn set_keepalive(&self, keepalive: bool) -> io::Result<()> {
+        sockopt::set_keepalive(self.as_raw_fd(), keepalive)
+    }
+
+    /// set the idle time before the first keepalive probe is sent, in seconds
+    pub fn set_keepalive_idle(&self, idle: Duration) -> io::Result<()> {
+        sockopt::set_keepalive_idle(self.as_raw_fd(), idle)
+    }
+
+    /// set the interval between keepalive probes, in seconds
+    pub fn set_keepalive_interval(&self, interval: Duration) -> io::Result<()> {
+        sockopt::set_keepalive_interval(self.as_raw_fd(), interval)
+    }
+
+    /// set the number of unacknowledged probes before the connection is dropped
+    pub fn set_keepalive_count(&self, count: u32) -> io::Result<()> {
+        sockopt::set_keepalive_count(self.as_raw_fd(), count)
+    }
+
+    /// set the `SO_LINGER` option, `None` disables it
+    pub fn set_linger(&self, linger: Option<Duration>) -> io::Result<()> {
+        sockopt::set_linger(self.as_raw_fd(), linger)
+    }
+
+    /// set the `SO_SNDBUF` option
+    pub fn set_send_buffer_size(&self, size: usize) -> io::Result<()> {
+        sockopt::set_send_buffer_size(self.as_raw_fd(), size)
+    }
+
+    /// set the `SO_RCVBUF` option, it must be set before connect to take
+    /// effect on the tcp window scale
+    pub fn set_recv_buffer_size(&self, size: usize) -> io::Result<()> {
+        sockopt::set_recv_buffer_size(self.as_raw_fd(), size)
+    }
+
+    /// set the `TCP_USER_TIMEOUT` option, `None` means the system default
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn set_user_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        sockopt::set_user_timeout(self.as_raw_fd(), timeout)
+    }
+
+    /// set the `IP_TOS` option, only valid for ipv4 sockets
+    pub fn set_tos(&self, tos: u32) -> io::Result<()> {
+        sockopt::set_tos(self.as_raw_fd(), tos)
+    }
+
+    /// connect to the remote address and convert the socket to a `TcpStream`
+    ///
+    /// in coroutine context only the coroutine is blocked
+    pub fn connect(self, addr: SocketAddr) -> io::Result<TcpStream> {
+        self.connect_impl(addr, None)
+    }
+
+    /// same as `connect` but with a timeout
+    pub fn connect_timeout(self, addr: SocketAddr, timeout: Duration) -> io::Result<TcpStream> {
+        self.connect_impl(addr, Some(timeout))
+    }
+
+    fn connect_impl(self, addr: SocketAddr, timeout: Option<Duration>) -> io::Result<TcpStream> {
+        if !is_coroutine() {
+            match timeout {
+                None => self.sys.connect(&addr.into())?,
+                Some(dur) => self.sys.connect_timeout(&addr.into(), dur)?,
+            }
+            let s = self.sys.into_tcp_stream();
+            s.set_nonblocking(true)?;
+            let io = io_impl::add_socket(&s)?;
+            return Ok(TcpStream::from_stream(s, io));
+        }
+
+        let mut c = net_impl::TcpStreamConnect::from_socket(self.sys, addr, timeout)?;
+        if c.check_connected()? {
+            return c.done();
+        }
+        yield_with(&c);
+        c.done()
+    }
+
+    /// start listening and convert the socket to a `TcpListener`
+    pub fn listen(self, backlog: i32) -> io::Result<TcpListener> {
+        self.sys.listen(backlog)?;
+        TcpListener::new(self.sys.into_tcp_listener())
+    }
+}
+
+#[cfg(unix)]
+impl AsRawFd for TcpSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.sys.as_raw_fd()
+    }
+}
+
 // ===== Incoming =====
 //
 //
@@ -0,0 +1,285 @@
+//! TLS streams over the coroutine aware `TcpStream`, based on rustls
+//!
+//! the handshake and all the io are driven by the underlying `TcpStream`,
+//! so the read/write timeouts and the coroutine cancellation work as usual.
+//!
+//! the handshake runs on the coroutine stack and needs much more space than
+//! the default stack size, a stack of at least `0x8000` (in usize) is
+//! recommended for the coroutines that use the TLS streams.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use may::net::tls::rustls::pki_types::ServerName;
+//! use may::net::tls::rustls::{ClientConfig, RootCertStore};
+//! use may::net::tls::TlsConnector;
+//! use may::net::TcpStream;
+//! use std::convert::TryFrom;
+//! use std::io::{Read, Write};
+//! use std::sync::Arc;
+//!
+//! let roots = RootCertStore::empty();
+//! let config = ClientConfig::builder()
+//!     .with_root_certificates(roots)
+//!     .with_no_client_auth();
+//! let connector = TlsConnector::new(Arc::new(config));
+//!
+//! let tcp = TcpStream::connect("example.com:443").unwrap();
+//! let name = ServerName::try_from("example.com").unwrap();
+//! let mut stream = connector.connect(name, tcp).unwrap();
+//! stream.write_all(b"GET / HTTP/1.0\r\n\r\n").unwrap();
+//! let mut rsp = Vec::new();
+//! stream.read_to_end(&mut rsp).unwrap();
+//! ```
+
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::TcpStream;
+use crate::sync::Mutex;
+use rustls::pki_types::ServerName;
+use rustls::{ClientConfig, ClientConnection, Connection, ServerConfig, ServerConnection};
+
+/// re-export of the rustls crate to build the configs
+pub use rustls;
+
+// the size of the buffer to read the tls records from the socket
+const READ_BUF_SIZE: usize = 16 * 1024 + 256;
+
+fn tls_err(e: rustls::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+/// A TLS client that wraps the `TcpStream` into `TlsStream`
+#[derive(Clone)]
+pub struct TlsConnector {
+    config: Arc<ClientConfig>,
+}
+
+impl TlsConnector {
+    pub fn new(config: Arc<ClientConfig>) -> Self {
+        TlsConnector { config }
+    }
+
+    /// do the client handshake over the connected stream
+    pub fn connect(
+        &self,
+        server_name: ServerName<'static>,
+        stream: TcpStream,
+    ) -> io::Result<TlsStream> {
+        let conn = ClientConnection::new(self.config.clone(), server_name).map_err(tls_err)?;
+        TlsStream::handshake(Connection::Client(conn), stream)
+    }
+}
+
+impl From<Arc<ClientConfig>> for TlsConnector {
+    fn from(config: Arc<ClientConfig>) -> Self {
+        TlsConnector::new(config)
+    }
+}
+
+/// A TLS server that wraps the accepted `TcpStream` into `TlsStream`
+#[derive(Clone)]
+pub struct TlsAcceptor {
+    config: Arc<ServerConfig>,
+}
+
+impl TlsAcceptor {
+    pub fn new(config: Arc<ServerConfig>) -> Self {
+        TlsAcceptor { config }
+    }
+
+    /// do the server handshake over the accepted stream
+    pub fn accept(&self, stream: TcpStream) -> io::Result<TlsStream> {
+        let conn = ServerConnection::new(self.config.clone()).map_err(tls_err)?;
+        TlsStream::handshake(Connection::Server(conn), stream)
+    }
+}
+
+impl From<Arc<ServerConfig>> for TlsAcceptor {
+    fn from(config: Arc<ServerConfig>) -> Self {
+        TlsAcceptor::new(config)
+    }
+}
+
+// the tls state shared by all the cloned streams
+struct Session {
+    conn: std::sync::Mutex<Connection>,
+    // only one reader could feed the records into the session at a time
+    read_buf: Mutex<Vec<u8>>,
+    // keep the order of the records written to the socket
+    write_lock: Mutex<()>,
+}
+
+/// A TLS stream over the `TcpStream`
+///
+/// the stream could be cloned by [`try_clone`] to read and write in different
+/// coroutines at the same time, all the clones share the same TLS session.
+///
+/// [`try_clone`]: #method.try_clone
+pub struct TlsStream {
+    sock: TcpStream,
+    session: Arc<Session>,
+}
+
+impl TlsStream {
+    fn handshake(mut conn: Connection, mut sock: TcpStream) -> io::Result<TlsStream> {
+        while conn.is_handshaking() {
+            conn.complete_io(&mut sock)?;
+        }
+        // flush the remaining records of the handshake, if any
+        while conn.wants_write() {
+            conn.write_tls(&mut sock)?;
+        }
+
+        Ok(TlsStream {
+            sock,
+            session: Arc::new(Session {
+                conn: std::sync::Mutex::new(conn),
+                read_buf: Mutex::new(vec![0; READ_BUF_SIZE]),
+                write_lock: Mutex::new(()),
+            }),
+        })
+    }
+
+    /// create a new handle that shares the TLS session
+    ///
+    /// the timeouts of the new handle are independent, so that a reader and
+    /// a writer could be set up with different timeouts.
+    pub fn try_clone(&self) -> io::Result<TlsStream> {
+        Ok(TlsStream {
+            sock: self.sock.try_clone()?,
+            session: self.session.clone(),
+        })
+    }
+
+    /// get the underlying `TcpStream`
+    pub fn get_ref(&self) -> &TcpStream {
+        &self.sock
+    }
+
+    /// run the function with the TLS connection, e.g. to get the negotiated
+    /// protocol or the peer certificates
+    pub fn with_connection<R, F: FnOnce(&Connection) -> R>(&self, f: F) -> R {
+        f(&self.session.conn.lock().unwrap())
+    }
+
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.sock.peer_addr()
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.sock.local_addr()
+    }
+
+    pub fn set_read_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        self.sock.set_read_timeout(dur)
+    }
+
+    pub fn set_write_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        self.sock.set_write_timeout(dur)
+    }
+
+    pub fn read_timeout(&self) -> io::Result<Option<Duration>> {
+        self.sock.read_timeout()
+    }
+
+    pub fn write_timeout(&self) -> io::Result<Option<Duration>> {
+        self.sock.write_timeout()
+    }
+
+    /// shut down the stream
+    ///
+    /// the `close_notify` alert is sent before shutting down the write half
+    pub fn shutdown(&mut self, how: Shutdown) -> io::Result<()> {
+        if how != Shutdown::Read {
+            let _guard = self.session.write_lock.lock().unwrap();
+            let out = {
+                let mut conn = self.session.conn.lock().unwrap();
+                conn.send_close_notify();
+                take_tls(&mut conn)?
+            };
+            self.sock.write_all(&out)?;
+        }
+        self.sock.shutdown(how)
+    }
+
+    // write out the pending records
+    fn flush_tls(&mut self) -> io::Result<()> {
+        let _guard = self.session.write_lock.lock().unwrap();
+        let out = take_tls(&mut self.session.conn.lock().unwrap())?;
+        self.sock.write_all(&out)
+    }
+}
+
+// take all the pending records that need to be written to the socket
+fn take_tls(conn: &mut Connection) -> io::Result<Vec<u8>> {
+    let mut out = Vec::new();
+    while conn.wants_write() {
+        conn.write_tls(&mut out)?;
+    }
+    Ok(out)
+}
+
+impl Read for TlsStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let session = self.session.clone();
+        let mut read_buf = session.read_buf.lock().unwrap();
+        loop {
+            let need_write = {
+                let mut conn = session.conn.lock().unwrap();
+                match conn.reader().read(buf) {
+                    Ok(n) => return Ok(n),
+                    Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                    Err(e) => return Err(e),
+                }
+                conn.wants_write()
+            };
+            // the session may need to reply, e.g. for key update
+            if need_write {
+                self.flush_tls()?;
+            }
+
+            // never hold the session lock when blocking on the socket
+            let n = self.sock.read(&mut read_buf)?;
+            let mut conn = session.conn.lock().unwrap();
+            let mut data = &read_buf[..n];
+            // a zero read tells the session that the peer is closed
+            loop {
+                conn.read_tls(&mut data)?;
+                conn.process_new_packets().map_err(tls_err)?;
+                if data.is_empty() {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+impl Write for TlsStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let _guard = self.session.write_lock.lock().unwrap();
+        let (n, out) = {
+            let mut conn = self.session.conn.lock().unwrap();
+            let n = conn.writer().write(buf)?;
+            (n, take_tls(&mut conn)?)
+        };
+        self.sock.write_all(&out)?;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.flush_tls()?;
+        self.sock.flush()
+    }
+}
+
+impl std::fmt::Debug for TlsStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("TlsStream")
+            .field("sock", &self.sock)
+            .finish()
+    }
+}
@@ -1,10 +1,20 @@
 use std::io;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use std::mem;
 use std::net::{self, Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs};
 use std::time::Duration;
 
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use super::msg::{self, RecvMeta, SendMeta};
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use super::sockopt;
 use crate::io as io_impl;
 use crate::io::net as net_impl;
+#[cfg(unix)]
+use crate::io::sys::wait_io::io_or_cancel;
 use crate::sync::atomic_dur::AtomicDuration;
+#[cfg(unix)]
+use crate::sync::CancellationToken;
 use crate::yield_now::yield_with;
 
 #[derive(Debug)]
@@ -139,6 +149,53 @@ impl UdpSocket {
         reader.done()
     }
 
+    /// send data to the address, or return an error built from `Cancelled`
+    /// once the token is cancelled
+    ///
+    /// the token is only checked before the send when not running in a
+    /// coroutine, because the blocking send can't be interrupted
+    #[cfg(unix)]
+    pub fn send_to_or_cancel<A: ToSocketAddrs>(
+        &self,
+        buf: &[u8],
+        addr: A,
+        token: &CancellationToken,
+    ) -> io::Result<usize> {
+        let ctx = self.ctx_check()?;
+        let timeout = self.write_timeout.get();
+        io_or_cancel(&self.io, ctx, timeout, token, || {
+            self.sys.send_to(buf, &addr)
+        })
+    }
+
+    /// receive data from the socket, or return an error built from
+    /// `Cancelled` once the token is cancelled
+    ///
+    /// the token is only checked before the receive when not running in a
+    /// coroutine, because the blocking receive can't be interrupted
+    #[cfg(unix)]
+    pub fn recv_from_or_cancel(
+        &self,
+        buf: &mut [u8],
+        token: &CancellationToken,
+    ) -> io::Result<(usize, SocketAddr)> {
+        let ctx = self.ctx_check()?;
+        let timeout = self.read_timeout.get();
+        io_or_cancel(&self.io, ctx, timeout, token, || self.sys.recv_from(buf))
+    }
+
+    // return Ok(true) if the io should go through the coroutine path
+    #[cfg(unix)]
+    fn ctx_check(&self) -> io::Result<bool> {
+        if self
+            .ctx
+            .check_nonblocking(|b| self.sys.set_nonblocking(b))?
+        {
+            return Ok(false);
+        }
+        self.ctx.check_context(|b| self.sys.set_nonblocking(b))
+    }
+
     pub fn send(&self, buf: &[u8]) -> io::Result<usize> {
         if self
             .ctx
@@ -205,6 +262,75 @@ impl UdpSocket {
         reader.done()
     }
 
+    /// receive data from the connected peer into multiple buffers
+    #[cfg(unix)]
+    pub fn recv_vectored(&self, bufs: &mut [io::IoSliceMut<'_>]) -> io::Result<usize> {
+        use std::os::unix::io::AsRawFd;
+
+        if self
+            .ctx
+            .check_nonblocking(|b| self.sys.set_nonblocking(b))?
+            || !self.ctx.check_context(|b| self.sys.set_nonblocking(b))?
+        {
+            // this can't be nonblocking!!
+            return net_impl::readv(self.sys.as_raw_fd(), bufs);
+        }
+
+        self.io.reset();
+        // this is an earlier return try for nonblocking read
+        match net_impl::readv(self.sys.as_raw_fd(), bufs) {
+            Ok(n) => return Ok(n),
+            Err(e) => {
+                // raw_os_error is faster than kind
+                let raw_err = e.raw_os_error();
+                if raw_err == Some(libc::EAGAIN) || raw_err == Some(libc::EWOULDBLOCK) {
+                    // do nothing here
+                } else {
+                    return Err(e);
+                }
+            }
+        }
+
+        let timeout = self.read_timeout.get();
+        let mut reader = net_impl::SocketReadVectored::new(self, bufs, timeout);
+        yield_with(&reader);
+        reader.done()
+    }
+
+    /// send data from multiple buffers to the connected peer
+    #[cfg(unix)]
+    pub fn send_vectored(&self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize> {
+        use std::os::unix::io::AsRawFd;
+
+        if self
+            .ctx
+            .check_nonblocking(|b| self.sys.set_nonblocking(b))?
+            || !self.ctx.check_context(|b| self.sys.set_nonblocking(b))?
+        {
+            // this can't be nonblocking!!
+            return net_impl::writev(self.sys.as_raw_fd(), bufs);
+        }
+
+        self.io.reset();
+        // this is an earlier return try for nonblocking write
+        match net_impl::writev(self.sys.as_raw_fd(), bufs) {
+            Ok(n) => return Ok(n),
+            Err(e) => {
+                // raw_os_error is faster than kind
+                let raw_err = e.raw_os_error();
+                if raw_err == Some(libc::EAGAIN) || raw_err == Some(libc::EWOULDBLOCK) {
+                    // do nothing here
+                } else {
+                    return Err(e);
+                }
+            }
+        }
+
+        let mut writer = net_impl::SocketWriteVectored::new(self, bufs, self.write_timeout.get());
+        yield_with(&writer);
+        writer.done()
+    }
+
     pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
         self.ctx.set_nonblocking(nonblocking);
         Ok(())
@@ -300,6 +426,307 @@ impl io_impl::AsIoData for UdpSocket {
     }
 }
 
+// ===== Batch IO =====
+//
+//
+
+/// A datagram buffer used by [`UdpSocket::recv_batch`]
+///
+/// [`UdpSocket::recv_batch`]: struct.UdpSocket.html#method.recv_batch
+#[derive(Debug)]
+pub struct RecvMsg<'a> {
+    /// the buffer to receive the datagram
+    pub buf: &'a mut [u8],
+    /// number of bytes received into the buffer
+    pub len: usize,
+    /// the source address of the datagram
+    pub addr: Option<SocketAddr>,
+    /// the datagram is larger than the buffer and the rest part is discarded
+    pub truncated: bool,
+}
+
+impl<'a> RecvMsg<'a> {
+    /// create a message with the receive buffer
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        RecvMsg {
+            buf,
+            len: 0,
+            addr: None,
+            truncated: false,
+        }
+    }
+
+    /// the received data
+    pub fn data(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+}
+
+/// A datagram to send by [`UdpSocket::send_batch`]
+///
+/// [`UdpSocket::send_batch`]: struct.UdpSocket.html#method.send_batch
+#[derive(Debug, Clone, Copy)]
+pub struct SendMsg<'a> {
+    /// the data to send
+    pub buf: &'a [u8],
+    /// the destination address, `None` means the connected peer
+    pub addr: Option<SocketAddr>,
+}
+
+impl<'a> SendMsg<'a> {
+    /// create a message that is sent to the given address
+    pub fn new(buf: &'a [u8], addr: SocketAddr) -> Self {
+        SendMsg {
+            buf,
+            addr: Some(addr),
+        }
+    }
+
+    /// create a message that is sent to the connected peer
+    pub fn connected(buf: &'a [u8]) -> Self {
+        SendMsg { buf, addr: None }
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+impl UdpSocket {
+    /// receive multiple datagrams with one `recvmmsg` call
+    ///
+    /// returns the number of messages received, the `len`, `addr` and
+    /// `truncated` fields of them are filled. it only waits for the first
+    /// datagram, the others are received if they are already available.
+    pub fn recv_batch(&self, msgs: &mut [RecvMsg<'_>]) -> io::Result<usize> {
+        if msgs.is_empty() {
+            return Ok(0);
+        }
+
+        if self
+            .ctx
+            .check_nonblocking(|b| self.sys.set_nonblocking(b))?
+            || !self.ctx.check_context(|b| self.sys.set_nonblocking(b))?
+        {
+            return net_impl::recv_mmsg(self.as_raw_fd(), msgs);
+        }
+
+        self.io.reset();
+        // this is an earlier return try for nonblocking read
+        match net_impl::recv_mmsg(self.as_raw_fd(), msgs) {
+            Ok(n) => return Ok(n),
+            Err(e) => {
+                // raw_os_error is faster than kind
+                let raw_err = e.raw_os_error();
+                if raw_err == Some(libc::EAGAIN) || raw_err == Some(libc::EWOULDBLOCK) {
+                    // do nothing here
+                } else {
+                    return Err(e);
+                }
+            }
+        }
+
+        let mut reader = net_impl::UdpRecvBatch::new(self, msgs);
+        yield_with(&reader);
+        reader.done()
+    }
+
+    /// send multiple datagrams with one `sendmmsg` call
+    ///
+    /// returns the number of messages sent, which may be less than the
+    /// number of the given messages
+    pub fn send_batch(&self, msgs: &[SendMsg<'_>]) -> io::Result<usize> {
+        if msgs.is_empty() {
+            return Ok(0);
+        }
+
+        if self
+            .ctx
+            .check_nonblocking(|b| self.sys.set_nonblocking(b))?
+            || !self.ctx.check_context(|b| self.sys.set_nonblocking(b))?
+        {
+            return net_impl::send_mmsg(self.as_raw_fd(), msgs);
+        }
+
+        self.io.reset();
+        // this is an earlier return try for nonblocking write
+        match net_impl::send_mmsg(self.as_raw_fd(), msgs) {
+            Ok(n) => return Ok(n),
+            Err(e) => {
+                // raw_os_error is faster than kind
+                let raw_err = e.raw_os_error();
+                if raw_err == Some(libc::EAGAIN) || raw_err == Some(libc::EWOULDBLOCK) {
+                    // do nothing here
+                } else {
+                    return Err(e);
+                }
+            }
+        }
+
+        let mut writer = net_impl::UdpSendBatch::new(self, msgs);
+        yield_with(&writer);
+        writer.done()
+    }
+}
+
+// ===== Message IO =====
+//
+//
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+impl UdpSocket {
+    fn is_ipv6(&self) -> io::Result<bool> {
+        self.sys.local_addr().map(|a| a.is_ipv6())
+    }
+
+    /// enable receiving the destination address by `IP_PKTINFO`/`IPV6_RECVPKTINFO`
+    pub fn set_recv_pktinfo(&self, on: bool) -> io::Result<()> {
+        let fd = self.as_raw_fd();
+        let on = on as libc::c_int;
+        if self.is_ipv6()? {
+            sockopt::setsockopt(fd, libc::IPPROTO_IPV6, libc::IPV6_RECVPKTINFO, on)
+        } else {
+            sockopt::setsockopt(fd, libc::IPPROTO_IP, libc::IP_PKTINFO, on)
+        }
+    }
+
+    /// enable receiving the kernel timestamp by `SO_TIMESTAMPNS`
+    pub fn set_recv_timestampns(&self, on: bool) -> io::Result<()> {
+        let on = on as libc::c_int;
+        sockopt::setsockopt(self.as_raw_fd(), libc::SOL_SOCKET, libc::SO_TIMESTAMPNS, on)
+    }
+
+    /// enable the generic receive offload by `UDP_GRO`
+    ///
+    /// multiple datagrams may be coalesced into one buffer, the segment size
+    /// is reported by [`RecvMeta::segment_size`](struct.RecvMeta.html)
+    pub fn set_gro(&self, on: bool) -> io::Result<()> {
+        let on = on as libc::c_int;
+        sockopt::setsockopt(self.as_raw_fd(), libc::SOL_UDP, libc::UDP_GRO, on)
+    }
+
+    /// enable receiving the ECN bits by `IP_RECVTOS`/`IPV6_RECVTCLASS`
+    pub fn set_recv_ecn(&self, on: bool) -> io::Result<()> {
+        let fd = self.as_raw_fd();
+        let on = on as libc::c_int;
+        if self.is_ipv6()? {
+            sockopt::setsockopt(fd, libc::IPPROTO_IPV6, libc::IPV6_RECVTCLASS, on)
+        } else {
+            sockopt::setsockopt(fd, libc::IPPROTO_IP, libc::IP_RECVTOS, on)
+        }
+    }
+
+    /// send a datagram with the ancillary data
+    ///
+    /// `addr` is the destination address, `None` means the connected peer.
+    pub fn send_msg(
+        &self,
+        bufs: &[io::IoSlice<'_>],
+        addr: Option<SocketAddr>,
+        meta: &SendMeta,
+    ) -> io::Result<usize> {
+        let addr = addr.map(socket2::SockAddr::from);
+        let mut cmsg = msg::CmsgBuf::new();
+        let v6 = meta.ecn.is_some() && self.is_ipv6()?;
+        msg::encode_send_meta(meta, v6, &mut cmsg);
+
+        let mut hdr: libc::msghdr = unsafe { mem::zeroed() };
+        if let Some(ref addr) = addr {
+            hdr.msg_name = addr.as_ptr() as *mut libc::c_void;
+            hdr.msg_namelen = addr.len();
+        }
+        hdr.msg_iov = bufs.as_ptr() as *mut libc::iovec;
+        hdr.msg_iovlen = bufs.len() as _;
+        cmsg.fill_send(&mut hdr);
+
+        self.send_msg_hdr(&hdr)
+    }
+
+    /// receive a datagram with the ancillary data
+    pub fn recv_msg(&self, bufs: &mut [io::IoSliceMut<'_>]) -> io::Result<RecvMeta> {
+        let mut addr: libc::sockaddr_storage = unsafe { mem::zeroed() };
+        let mut cmsg = msg::CmsgBuf::new();
+
+        let mut hdr: libc::msghdr = unsafe { mem::zeroed() };
+        hdr.msg_name = &mut addr as *mut _ as *mut libc::c_void;
+        hdr.msg_namelen = mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+        hdr.msg_iov = bufs.as_mut_ptr() as *mut libc::iovec;
+        hdr.msg_iovlen = bufs.len() as _;
+        cmsg.fill_recv(&mut hdr);
+
+        let len = self.recv_msg_hdr(&mut hdr)?;
+        let mut meta = RecvMeta {
+            len,
+            ..RecvMeta::default()
+        };
+        meta.addr = unsafe {
+            socket2::SockAddr::from_raw_parts(
+                &addr as *const _ as *const libc::sockaddr,
+                hdr.msg_namelen,
+            )
+        }
+        .as_std();
+        msg::decode_recv_meta(&hdr, &mut meta);
+        Ok(meta)
+    }
+
+    fn send_msg_hdr(&self, hdr: &libc::msghdr) -> io::Result<usize> {
+        if self
+            .ctx
+            .check_nonblocking(|b| self.sys.set_nonblocking(b))?
+            || !self.ctx.check_context(|b| self.sys.set_nonblocking(b))?
+        {
+            return net_impl::sendmsg(self.as_raw_fd(), hdr, 0);
+        }
+
+        self.io.reset();
+        // this is an earlier return try for nonblocking write
+        match net_impl::sendmsg(self.as_raw_fd(), hdr, 0) {
+            Ok(n) => return Ok(n),
+            Err(e) => {
+                // raw_os_error is faster than kind
+                let raw_err = e.raw_os_error();
+                if raw_err == Some(libc::EAGAIN) || raw_err == Some(libc::EWOULDBLOCK) {
+                    // do nothing here
+                } else {
+                    return Err(e);
+                }
+            }
+        }
+
+        let mut writer = net_impl::SocketSendMsg::new(self, hdr, 0, self.write_timeout.get());
+        yield_with(&writer);
+        writer.done()
+    }
+
+    fn recv_msg_hdr(&self, hdr: &mut libc::msghdr) -> io::Result<usize> {
+        if self
+            .ctx
+            .check_nonblocking(|b| self.sys.set_nonblocking(b))?
+            || !self.ctx.check_context(|b| self.sys.set_nonblocking(b))?
+        {
+            return net_impl::recvmsg(self.as_raw_fd(), hdr, 0);
+        }
+
+        self.io.reset();
+        // this is an earlier return try for nonblocking read
+        match net_impl::recvmsg(self.as_raw_fd(), hdr, 0) {
+            Ok(n) => return Ok(n),
+            Err(e) => {
+                // raw_os_error is faster than kind
+                let raw_err = e.raw_os_error();
+                if raw_err == Some(libc::EAGAIN) || raw_err == Some(libc::EWOULDBLOCK) {
+                    // do nothing here
+                } else {
+                    return Err(e);
+                }
+            }
+        }
+
+        let timeout = self.read_timeout.get();
+        let mut reader = net_impl::SocketRecvMsg::new(self, hdr, 0, timeout);
+        yield_with(&reader);
+        reader.done()
+    }
+}
+
 // ===== UNIX ext =====
 //
 //
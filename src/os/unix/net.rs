@@ -1,17 +1,285 @@
 //! Unix-specific networking functionality
 
+use std::ffi::OsStr;
 use std::fmt;
 use std::io;
+use std::mem;
 use std::net::Shutdown;
+use std::os::unix::ffi::OsStrExt;
 use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd};
-use std::os::unix::net::{self, SocketAddr};
+use std::os::unix::net;
 use std::path::Path;
 use std::time::Duration;
 
 use crate::coroutine_impl::is_coroutine;
 use crate::io::sys::net as net_impl;
-use crate::io::CoIo;
+use crate::io::sys::wait_io::io_or_cancel;
+use crate::io::{AsIoData, CoIo};
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use crate::net::msg::decode_recv_meta;
+use crate::net::msg::{for_each_cmsg, CmsgBuf};
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use crate::net::sockopt::{getsockopt, setsockopt};
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use crate::net::RecvMeta;
+use crate::sync::CancellationToken;
 use crate::yield_now::yield_with;
+use socket2::{Domain, SockAddr, Socket, Type};
+
+fn sun_path_offset(addr: &libc::sockaddr_un) -> usize {
+    let base = addr as *const _ as usize;
+    let path = &addr.sun_path as *const _ as usize;
+    path - base
+}
+
+enum AddressKind<'a> {
+    Unnamed,
+    Pathname(&'a Path),
+    Abstract(&'a [u8]),
+}
+
+/// An address associated with a Unix socket.
+///
+/// Besides the unnamed and pathname addresses this also represents the linux
+/// abstract namespace addresses, which are not bound to the filesystem.
+///
+/// # Examples
+///
+/// ```no_run
+/// use may::os::unix::net::UnixListener;
+///
+/// let socket = UnixListener::bind("/tmp/sock").unwrap();
+/// let addr = socket.local_addr().expect("Couldn't get local address");
+/// ```
+#[derive(Clone)]
+pub struct SocketAddr {
+    addr: libc::sockaddr_un,
+    len: libc::socklen_t,
+}
+
+impl SocketAddr {
+    fn new<F>(f: F) -> io::Result<SocketAddr>
+    where
+        F: FnOnce(*mut libc::sockaddr, *mut libc::socklen_t) -> libc::c_int,
+    {
+        unsafe {
+            let mut addr: libc::sockaddr_un = mem::zeroed();
+            let mut len = mem::size_of::<libc::sockaddr_un>() as libc::socklen_t;
+            if f(&mut addr as *mut _ as *mut _, &mut len) == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            SocketAddr::from_parts(addr, len)
+        }
+    }
+
+    fn from_parts(addr: libc::sockaddr_un, mut len: libc::socklen_t) -> io::Result<SocketAddr> {
+        if len == 0 {
+            // when there is a datagram from unnamed unix socket
+            // linux returns zero bytes of address
+            len = sun_path_offset(&addr) as libc::socklen_t;
+        } else if addr.sun_family != libc::AF_UNIX as libc::sa_family_t {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "file descriptor did not correspond to a Unix socket",
+            ));
+        }
+        Ok(SocketAddr { addr, len })
+    }
+
+    // build the address from the raw bytes of `sun_path`
+    fn from_path_bytes(bytes: &[u8], nul_terminated: bool) -> io::Result<SocketAddr> {
+        let mut addr: libc::sockaddr_un = unsafe { mem::zeroed() };
+        addr.sun_family = libc::AF_UNIX as libc::sa_family_t;
+        let extra = nul_terminated as usize;
+        if bytes.len() + extra > addr.sun_path.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "path must be shorter than SUN_LEN",
+            ));
+        }
+        for (dst, src) in addr.sun_path.iter_mut().zip(bytes) {
+            *dst = *src as libc::c_char;
+        }
+        let len = sun_path_offset(&addr) + bytes.len() + extra;
+        Ok(SocketAddr {
+            addr,
+            len: len as libc::socklen_t,
+        })
+    }
+
+    /// Creates an address that refers to the given filesystem path.
+    pub fn from_pathname<P: AsRef<Path>>(path: P) -> io::Result<SocketAddr> {
+        let bytes = path.as_ref().as_os_str().as_bytes();
+        if bytes.contains(&0) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "paths may not contain interior null bytes",
+            ));
+        }
+        SocketAddr::from_path_bytes(bytes, true)
+    }
+
+    /// Creates an address in the linux abstract namespace.
+    ///
+    /// The `name` doesn't include the leading null byte and is not required
+    /// to be null terminated. An empty `name` is the name of a single null
+    /// byte, it doesn't request an auto bound address.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn from_abstract_name(name: &[u8]) -> io::Result<SocketAddr> {
+        let mut bytes = Vec::with_capacity(name.len() + 1);
+        bytes.push(0);
+        bytes.extend_from_slice(name);
+        SocketAddr::from_path_bytes(&bytes, false)
+    }
+
+    /// Returns true if and only if the address is unnamed.
+    pub fn is_unnamed(&self) -> bool {
+        matches!(self.address(), AddressKind::Unnamed)
+    }
+
+    /// Returns the contents of this address if it is a `pathname` address.
+    pub fn as_pathname(&self) -> Option<&Path> {
+        match self.address() {
+            AddressKind::Pathname(path) => Some(path),
+            _ => None,
+        }
+    }
+
+    /// Returns the name of this address if it is in the abstract namespace.
+    ///
+    /// The returned name doesn't include the leading null byte.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn as_abstract_name(&self) -> Option<&[u8]> {
+        match self.address() {
+            AddressKind::Abstract(name) => Some(name),
+            _ => None,
+        }
+    }
+
+    fn address(&self) -> AddressKind<'_> {
+        let len = self.len as usize - sun_path_offset(&self.addr);
+        let path = unsafe { &*(&self.addr.sun_path as *const [libc::c_char] as *const [u8]) };
+
+        // linux returns zero bytes of address for unnamed socket
+        if len == 0
+            || (cfg!(not(any(target_os = "linux", target_os = "android")))
+                && self.addr.sun_path[0] == 0)
+        {
+            AddressKind::Unnamed
+        } else if self.addr.sun_path[0] == 0 {
+            AddressKind::Abstract(&path[1..len])
+        } else {
+            // strip the trailing null byte if there is one
+            let len = path[..len].iter().position(|&b| b == 0).unwrap_or(len);
+            AddressKind::Pathname(OsStr::from_bytes(&path[..len]).as_ref())
+        }
+    }
+
+    fn as_ptr(&self) -> *const libc::sockaddr {
+        &self.addr as *const _ as *const libc::sockaddr
+    }
+
+    fn to_sock_addr(&self) -> SockAddr {
+        unsafe { SockAddr::from_raw_parts(self.as_ptr(), self.len) }
+    }
+}
+
+impl fmt::Debug for SocketAddr {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self.address() {
+            AddressKind::Unnamed => write!(fmt, "(unnamed)"),
+            AddressKind::Abstract(name) => {
+                write!(fmt, "\"")?;
+                for &b in name {
+                    for c in std::ascii::escape_default(b) {
+                        write!(fmt, "{}", c as char)?;
+                    }
+                }
+                write!(fmt, "\" (abstract)")
+            }
+            AddressKind::Pathname(path) => write!(fmt, "{:?} (pathname)", path),
+        }
+    }
+}
+
+impl PartialEq for SocketAddr {
+    fn eq(&self, other: &SocketAddr) -> bool {
+        let len = self.len as usize - sun_path_offset(&self.addr);
+        let other_len = other.len as usize - sun_path_offset(&other.addr);
+        self.addr.sun_path[..len] == other.addr.sun_path[..other_len]
+    }
+}
+
+impl Eq for SocketAddr {}
+
+// accept a connection, the new fd is close-on-exec
+pub(crate) fn accept(fd: RawFd) -> io::Result<(net::UnixStream, SocketAddr)> {
+    let mut addr: libc::sockaddr_un = unsafe { mem::zeroed() };
+    let mut len = mem::size_of::<libc::sockaddr_un>() as libc::socklen_t;
+    let storage = &mut addr as *mut _ as *mut libc::sockaddr;
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    let sock = unsafe { libc::accept4(fd, storage, &mut len, libc::SOCK_CLOEXEC) };
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    let sock = unsafe {
+        let sock = libc::accept(fd, storage, &mut len);
+        if sock >= 0 {
+            libc::ioctl(sock, libc::FIOCLEX);
+        }
+        sock
+    };
+
+    if sock < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let stream = unsafe { net::UnixStream::from_raw_fd(sock) };
+    Ok((stream, SocketAddr::from_parts(addr, len)?))
+}
+
+pub(crate) fn recv_from(fd: RawFd, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+    let mut count = 0;
+    let addr = SocketAddr::new(|addr, len| unsafe {
+        count = libc::recvfrom(fd, buf.as_mut_ptr() as *mut _, buf.len(), 0, addr, len);
+        if count > 0 {
+            1
+        } else if count == 0 {
+            0
+        } else {
+            -1
+        }
+    })?;
+    Ok((count as usize, addr))
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const SEND_FLAGS: libc::c_int = libc::MSG_NOSIGNAL;
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+const SEND_FLAGS: libc::c_int = 0;
+
+pub(crate) fn send_to(fd: RawFd, buf: &[u8], addr: &SocketAddr) -> io::Result<usize> {
+    let count = unsafe {
+        libc::sendto(
+            fd,
+            buf.as_ptr() as *const _,
+            buf.len(),
+            SEND_FLAGS,
+            addr.as_ptr(),
+            addr.len,
+        )
+    };
+    if count < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(count as usize)
+}
+
+fn local_addr(fd: RawFd) -> io::Result<SocketAddr> {
+    SocketAddr::new(|addr, len| unsafe { libc::getsockname(fd, addr, len) })
+}
+
+fn peer_addr(fd: RawFd) -> io::Result<SocketAddr> {
+    SocketAddr::new(|addr, len| unsafe { libc::getpeername(fd, addr, len) })
+}
 
 /// A Unix stream socket.
 ///
@@ -80,6 +348,47 @@ impl UnixStream {
         c.done()
     }
 
+    /// Connects to the socket specified by the address.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use may::os::unix::net::{SocketAddr, UnixStream};
+    ///
+    /// let addr = SocketAddr::from_pathname("/tmp/sock").unwrap();
+    /// let socket = UnixStream::connect_addr(&addr).expect("Couldn't connect");
+    /// ```
+    pub fn connect_addr(addr: &SocketAddr) -> io::Result<UnixStream> {
+        if !is_coroutine() {
+            let socket = Socket::new(Domain::unix(), Type::stream(), None)?;
+            socket.connect(&addr.to_sock_addr())?;
+            return Ok(UnixStream(CoIo::new(socket.into_unix_stream())?));
+        }
+
+        let mut c = net_impl::UnixStreamConnect::with_addr(addr.to_sock_addr())?;
+
+        if c.check_connected()? {
+            return c.done();
+        }
+
+        yield_with(&c);
+        c.done()
+    }
+
+    /// Connects to the socket named by `name` in the linux abstract namespace.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use may::os::unix::net::UnixStream;
+    ///
+    /// let socket = UnixStream::connect_abstract(b"sidecar").expect("Couldn't connect");
+    /// ```
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn connect_abstract(name: &[u8]) -> io::Result<UnixStream> {
+        UnixStream::connect_addr(&SocketAddr::from_abstract_name(name)?)
+    }
+
     /// Creates an unnamed pair of connected sockets.
     ///
     /// Returns two `UnixStream`s which are connected to each other.
@@ -135,7 +444,7 @@ impl UnixStream {
     /// let addr = socket.local_addr().expect("Couldn't get local address");
     /// ```
     pub fn local_addr(&self) -> io::Result<SocketAddr> {
-        self.0.inner().local_addr()
+        local_addr(self.as_raw_fd())
     }
 
     /// Returns the socket address of the remote half of this connection.
@@ -149,7 +458,7 @@ impl UnixStream {
     /// let addr = socket.peer_addr().expect("Couldn't get peer address");
     /// ```
     pub fn peer_addr(&self) -> io::Result<SocketAddr> {
-        self.0.inner().peer_addr()
+        peer_addr(self.as_raw_fd())
     }
 
     /// Sets the read timeout for the socket.
@@ -272,12 +581,102 @@ impl UnixStream {
     pub fn shutdown(&self, how: Shutdown) -> io::Result<()> {
         self.0.inner().shutdown(how)
     }
+
+    /// Sends data on the socket together with the file descriptors.
+    ///
+    /// The fds are passed to the peer by `SCM_RIGHTS`, they are still owned
+    /// by the caller after the call. On success, returns the number of bytes
+    /// written, the fds are sent along with the first byte of the data, so
+    /// `buf` must not be empty.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use may::os::unix::net::UnixStream;
+    /// use std::net::TcpListener;
+    /// use std::os::unix::io::AsRawFd;
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:8080").unwrap();
+    /// let socket = UnixStream::connect("/tmp/sock").unwrap();
+    /// socket.send_with_fds(b"listener", &[listener.as_raw_fd()]).unwrap();
+    /// ```
+    pub fn send_with_fds(&self, buf: &[u8], fds: &[RawFd]) -> io::Result<usize> {
+        send_with_fds(&self.0, buf, fds)
+    }
+
+    /// Receives data from the socket together with the passed file descriptors.
+    ///
+    /// On success, returns the number of bytes read and the number of fds
+    /// stored in `fds`. The received fds are owned by the caller and have the
+    /// close-on-exec flag set, any fds beyond the capacity of `fds` are closed.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use may::os::unix::net::UnixStream;
+    /// use std::net::TcpListener;
+    /// use std::os::unix::io::FromRawFd;
+    ///
+    /// let socket = UnixStream::connect("/tmp/sock").unwrap();
+    /// let (mut buf, mut fds) = ([0; 64], [0; 1]);
+    /// let (_, n) = socket.recv_with_fds(&mut buf, &mut fds).unwrap();
+    /// if n == 1 {
+    ///     let listener = unsafe { TcpListener::from_raw_fd(fds[0]) };
+    /// }
+    /// ```
+    pub fn recv_with_fds(&self, buf: &mut [u8], fds: &mut [RawFd]) -> io::Result<(usize, usize)> {
+        recv_with_fds(&self.0, buf, fds)
+    }
+
+    /// Returns the credentials of the process that created the peer socket.
+    ///
+    /// On linux this is `SO_PEERCRED`, other platforms use `getpeereid` which
+    /// doesn't report the pid.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use may::os::unix::net::UnixStream;
+    ///
+    /// let socket = UnixStream::connect("/tmp/sock").unwrap();
+    /// let cred = socket.peer_cred().expect("Couldn't get peer credentials");
+    /// println!("peer uid: {}", cred.uid);
+    /// ```
+    pub fn peer_cred(&self) -> io::Result<UCred> {
+        peer_cred(self.as_raw_fd())
+    }
+
+    /// Reads from the socket, or returns an error built from `Cancelled`
+    /// once the token is cancelled.
+    ///
+    /// The token is only checked before the read when not running in a
+    /// coroutine, because the blocking read can't be interrupted.
+    pub fn read_or_cancel(
+        &mut self,
+        buf: &mut [u8],
+        token: &CancellationToken,
+    ) -> io::Result<usize> {
+        self.0.read_or_cancel(buf, token)
+    }
+
+    /// Writes to the socket, or returns an error built from `Cancelled`
+    /// once the token is cancelled.
+    ///
+    /// The token is only checked before the write when not running in a
+    /// coroutine, because the blocking write can't be interrupted.
+    pub fn write_or_cancel(&mut self, buf: &[u8], token: &CancellationToken) -> io::Result<usize> {
+        self.0.write_or_cancel(buf, token)
+    }
 }
 
 impl io::Read for UnixStream {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         self.0.read(buf)
     }
+
+    fn read_vectored(&mut self, bufs: &mut [io::IoSliceMut<'_>]) -> io::Result<usize> {
+        self.0.read_vectored(bufs)
+    }
 }
 
 // impl<'a> io::Read for &'a UnixStream {
@@ -291,6 +690,10 @@ impl io::Write for UnixStream {
         self.0.write(buf)
     }
 
+    fn write_vectored(&mut self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize> {
+        self.0.write_vectored(bufs)
+    }
+
     fn flush(&mut self) -> io::Result<()> {
         self.0.flush()
     }
@@ -387,6 +790,41 @@ impl UnixListener {
         Ok(UnixListener(CoIo::new(listener)?))
     }
 
+    /// Creates a new `UnixListener` bound to the specified address.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use may::os::unix::net::{SocketAddr, UnixListener};
+    ///
+    /// let addr = SocketAddr::from_pathname("/path/to/the/socket").unwrap();
+    /// let listener = UnixListener::bind_addr(&addr).expect("Couldn't bind");
+    /// ```
+    pub fn bind_addr(addr: &SocketAddr) -> io::Result<UnixListener> {
+        let socket = Socket::new(Domain::unix(), Type::stream(), None)?;
+        socket.bind(&addr.to_sock_addr())?;
+        socket.listen(128)?;
+        Ok(UnixListener(CoIo::new(socket.into_unix_listener())?))
+    }
+
+    /// Creates a new `UnixListener` bound to `name` in the linux abstract
+    /// namespace.
+    ///
+    /// The abstract socket doesn't create any file and disappears when all
+    /// the references to it are closed.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use may::os::unix::net::UnixListener;
+    ///
+    /// let listener = UnixListener::bind_abstract(b"sidecar").expect("Couldn't bind");
+    /// ```
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn bind_abstract(name: &[u8]) -> io::Result<UnixListener> {
+        UnixListener::bind_addr(&SocketAddr::from_abstract_name(name)?)
+    }
+
     /// Accepts a new incoming connection to this listener.
     ///
     /// This function will block the calling thread until a new Unix connection
@@ -407,12 +845,12 @@ impl UnixListener {
     /// ```
     pub fn accept(&self) -> io::Result<(UnixStream, SocketAddr)> {
         if !self.0.ctx_check()? {
-            let (s, a) = self.0.inner().accept()?;
+            let (s, a) = accept(self.as_raw_fd())?;
             return Ok((UnixStream(CoIo::new(s)?), a));
         }
 
         self.0.io_reset();
-        match self.0.inner().accept() {
+        match accept(self.as_raw_fd()) {
             Ok((s, a)) => return Ok((UnixStream(CoIo::new(s)?), a)),
             Err(e) => {
                 // raw_os_error is faster than kind
@@ -462,7 +900,7 @@ impl UnixListener {
     /// let addr = listener.local_addr().expect("Couldn't get local address");
     /// ```
     pub fn local_addr(&self) -> io::Result<SocketAddr> {
-        self.0.inner().local_addr()
+        local_addr(self.as_raw_fd())
     }
 
     /// Moves the socket into or out of nonblocking mode.
@@ -528,6 +966,21 @@ impl UnixListener {
     pub fn incoming(&self) -> Incoming {
         Incoming { listener: self }
     }
+
+    /// Accepts a new incoming connection, or returns an error built from
+    /// `Cancelled` once the token is cancelled.
+    ///
+    /// The token is only checked before the accept when not running in a
+    /// coroutine, because the blocking accept can't be interrupted.
+    pub fn accept_or_cancel(
+        &self,
+        token: &CancellationToken,
+    ) -> io::Result<(UnixStream, SocketAddr)> {
+        let ctx = self.0.ctx_check()?;
+        let fd = self.as_raw_fd();
+        let (s, a) = io_or_cancel(self.0.as_io_data(), ctx, None, token, || accept(fd))?;
+        Ok((UnixStream(CoIo::new(s)?), a))
+    }
 }
 
 impl AsRawFd for UnixListener {
@@ -652,6 +1105,37 @@ impl UnixDatagram {
         Ok(UnixDatagram(CoIo::new(datagram)?))
     }
 
+    /// Creates a Unix datagram socket bound to the given address.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use may::os::unix::net::{SocketAddr, UnixDatagram};
+    ///
+    /// let addr = SocketAddr::from_pathname("/path/to/the/socket").unwrap();
+    /// let sock = UnixDatagram::bind_addr(&addr).expect("Couldn't bind");
+    /// ```
+    pub fn bind_addr(addr: &SocketAddr) -> io::Result<UnixDatagram> {
+        let socket = Socket::new(Domain::unix(), Type::dgram(), None)?;
+        socket.bind(&addr.to_sock_addr())?;
+        Ok(UnixDatagram(CoIo::new(socket.into_unix_datagram())?))
+    }
+
+    /// Creates a Unix datagram socket bound to `name` in the linux abstract
+    /// namespace.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use may::os::unix::net::UnixDatagram;
+    ///
+    /// let sock = UnixDatagram::bind_abstract(b"sidecar").expect("Couldn't bind");
+    /// ```
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn bind_abstract(name: &[u8]) -> io::Result<UnixDatagram> {
+        UnixDatagram::bind_addr(&SocketAddr::from_abstract_name(name)?)
+    }
+
     /// Creates a Unix Datagram socket which is not bound to any address.
     ///
     /// # Examples
@@ -725,6 +1209,40 @@ impl UnixDatagram {
         self.0.inner().connect(path)
     }
 
+    /// Connects the socket to the specified address.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use may::os::unix::net::{SocketAddr, UnixDatagram};
+    ///
+    /// let addr = SocketAddr::from_pathname("/path/to/the/socket").unwrap();
+    /// let sock = UnixDatagram::unbound().unwrap();
+    /// sock.connect_addr(&addr).expect("Couldn't connect");
+    /// ```
+    pub fn connect_addr(&self, addr: &SocketAddr) -> io::Result<()> {
+        let ret = unsafe { libc::connect(self.as_raw_fd(), addr.as_ptr(), addr.len) };
+        if ret == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// Connects the socket to `name` in the linux abstract namespace.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use may::os::unix::net::UnixDatagram;
+    ///
+    /// let sock = UnixDatagram::unbound().unwrap();
+    /// sock.connect_abstract(b"sidecar").expect("Couldn't connect");
+    /// ```
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn connect_abstract(&self, name: &[u8]) -> io::Result<()> {
+        self.connect_addr(&SocketAddr::from_abstract_name(name)?)
+    }
+
     /// Creates a new independently owned handle to the underlying socket.
     ///
     /// The returned `UnixDatagram` is a reference to the same socket that this
@@ -757,7 +1275,7 @@ impl UnixDatagram {
     /// let addr = sock.local_addr().expect("Couldn't get local address");
     /// ```
     pub fn local_addr(&self) -> io::Result<SocketAddr> {
-        self.0.inner().local_addr()
+        local_addr(self.as_raw_fd())
     }
 
     /// Returns the address of this socket's peer.
@@ -777,7 +1295,7 @@ impl UnixDatagram {
     /// let addr = sock.peer_addr().expect("Couldn't get peer address");
     /// ```
     pub fn peer_addr(&self) -> io::Result<SocketAddr> {
-        self.0.inner().peer_addr()
+        peer_addr(self.as_raw_fd())
     }
 
     /// Receives data from the socket.
@@ -800,12 +1318,12 @@ impl UnixDatagram {
     pub fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
         if !self.0.ctx_check()? {
             // this can't be nonblocking!!
-            return self.0.inner().recv_from(buf);
+            return recv_from(self.as_raw_fd(), buf);
         }
 
         self.0.io_reset();
         // this is an earlier return try for nonblocking read
-        match self.0.inner().recv_from(buf) {
+        match recv_from(self.as_raw_fd(), buf) {
             Ok(n) => return Ok(n),
             Err(e) => {
                 // raw_os_error is faster than kind
@@ -875,14 +1393,35 @@ impl UnixDatagram {
     /// sock.send_to(b"omelette au fromage", "/some/sock").expect("send_to function failed");
     /// ```
     pub fn send_to<P: AsRef<Path>>(&self, buf: &[u8], path: P) -> io::Result<usize> {
+        self.send_to_addr(buf, &SocketAddr::from_pathname(path)?)
+    }
+
+    /// Sends data on the socket to the specified address.
+    ///
+    /// This can be used to reply to a sender that is bound to an abstract
+    /// address, the address is the one returned by [`recv_from`].
+    ///
+    /// [`recv_from`]: #method.recv_from
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use may::os::unix::net::UnixDatagram;
+    ///
+    /// let sock = UnixDatagram::bind("/path/to/the/socket").unwrap();
+    /// let mut buf = [0; 64];
+    /// let (n, addr) = sock.recv_from(&mut buf).unwrap();
+    /// sock.send_to_addr(&buf[..n], &addr).expect("send_to_addr function failed");
+    /// ```
+    pub fn send_to_addr(&self, buf: &[u8], addr: &SocketAddr) -> io::Result<usize> {
         if !self.0.ctx_check()? {
             // this can't be nonblocking!!
-            return self.0.inner().send_to(buf, path);
+            return send_to(self.as_raw_fd(), buf, addr);
         }
 
         self.0.io_reset();
         // this is an earlier return try for nonblocking read
-        match self.0.inner().send_to(buf, path.as_ref()) {
+        match send_to(self.as_raw_fd(), buf, addr) {
             Ok(n) => return Ok(n),
             Err(e) => {
                 // raw_os_error is faster than kind
@@ -895,7 +1434,7 @@ impl UnixDatagram {
             }
         }
 
-        let mut writer = net_impl::UnixSendTo::new(self, buf, path.as_ref())?;
+        let mut writer = net_impl::UnixSendTo::new(self, buf, addr)?;
         yield_with(&writer);
         writer.done()
     }
@@ -942,6 +1481,90 @@ impl UnixDatagram {
         writer.done()
     }
 
+    /// Receives data from the socket into multiple buffers.
+    ///
+    /// On success, returns the number of bytes read.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use may::os::unix::net::UnixDatagram;
+    /// use std::io::IoSliceMut;
+    ///
+    /// let sock = UnixDatagram::bind("/path/to/the/socket").unwrap();
+    /// let (mut head, mut body) = ([0; 4], [0; 60]);
+    /// let mut bufs = [IoSliceMut::new(&mut head), IoSliceMut::new(&mut body)];
+    /// sock.recv_vectored(&mut bufs).expect("recv_vectored function failed");
+    /// ```
+    pub fn recv_vectored(&self, bufs: &mut [io::IoSliceMut<'_>]) -> io::Result<usize> {
+        if !self.0.ctx_check()? {
+            // this can't be nonblocking!!
+            return net_impl::readv(self.as_raw_fd(), bufs);
+        }
+
+        self.0.io_reset();
+        // this is an earlier return try for nonblocking read
+        match net_impl::readv(self.as_raw_fd(), bufs) {
+            Ok(n) => return Ok(n),
+            Err(e) => {
+                // raw_os_error is faster than kind
+                let raw_err = e.raw_os_error();
+                if raw_err == Some(libc::EAGAIN) || raw_err == Some(libc::EWOULDBLOCK) {
+                    // do nothing here
+                } else {
+                    return Err(e);
+                }
+            }
+        }
+
+        let timeout = self.read_timeout().unwrap();
+        let mut reader = net_impl::SocketReadVectored::new(&self.0, bufs, timeout);
+        yield_with(&reader);
+        reader.done()
+    }
+
+    /// Sends data from multiple buffers on the socket to the socket's peer.
+    ///
+    /// On success, returns the number of bytes written.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use may::os::unix::net::UnixDatagram;
+    /// use std::io::IoSlice;
+    ///
+    /// let sock = UnixDatagram::unbound().unwrap();
+    /// sock.connect("/some/sock").expect("Couldn't connect");
+    /// let bufs = [IoSlice::new(b"omelette "), IoSlice::new(b"au fromage")];
+    /// sock.send_vectored(&bufs).expect("send_vectored function failed");
+    /// ```
+    pub fn send_vectored(&self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize> {
+        if !self.0.ctx_check()? {
+            // this can't be nonblocking!!
+            return net_impl::writev(self.as_raw_fd(), bufs);
+        }
+
+        self.0.io_reset();
+        // this is an earlier return try for nonblocking write
+        match net_impl::writev(self.as_raw_fd(), bufs) {
+            Ok(n) => return Ok(n),
+            Err(e) => {
+                // raw_os_error is faster than kind
+                let raw_err = e.raw_os_error();
+                if raw_err == Some(libc::EAGAIN) || raw_err == Some(libc::EWOULDBLOCK) {
+                    // do nothing here
+                } else {
+                    return Err(e);
+                }
+            }
+        }
+
+        let timeout = self.0.write_timeout().unwrap();
+        let mut writer = net_impl::SocketWriteVectored::new(&self.0, bufs, timeout);
+        yield_with(&writer);
+        writer.done()
+    }
+
     /// Sets the read timeout for the socket.
     ///
     /// If the provided value is `None`, then [`recv`] and [`recv_from`] calls will
@@ -1068,6 +1691,166 @@ impl UnixDatagram {
     pub fn shutdown(&self, how: Shutdown) -> io::Result<()> {
         self.0.inner().shutdown(how)
     }
+
+    /// Sends a datagram to the socket's peer together with the file descriptors.
+    ///
+    /// The fds are passed to the peer by `SCM_RIGHTS`, they are still owned
+    /// by the caller after the call. On success, returns the number of bytes
+    /// written.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use may::os::unix::net::UnixDatagram;
+    /// use std::io;
+    /// use std::os::unix::io::AsRawFd;
+    ///
+    /// let sock = UnixDatagram::unbound().unwrap();
+    /// sock.connect("/some/sock").expect("Couldn't connect");
+    /// let stdin = io::stdin();
+    /// sock.send_with_fds(b"stdin", &[stdin.as_raw_fd()]).unwrap();
+    /// ```
+    pub fn send_with_fds(&self, buf: &[u8], fds: &[RawFd]) -> io::Result<usize> {
+        send_with_fds(&self.0, buf, fds)
+    }
+
+    /// Receives a datagram together with the passed file descriptors.
+    ///
+    /// On success, returns the number of bytes read and the number of fds
+    /// stored in `fds`. The received fds are owned by the caller and have the
+    /// close-on-exec flag set, any fds beyond the capacity of `fds` are closed.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use may::os::unix::net::UnixDatagram;
+    ///
+    /// let sock = UnixDatagram::bind("/path/to/the/socket").unwrap();
+    /// let (mut buf, mut fds) = ([0; 64], [0; 4]);
+    /// let (len, n) = sock.recv_with_fds(&mut buf, &mut fds).unwrap();
+    /// println!("received {} bytes and fds {:?}", len, &fds[..n]);
+    /// ```
+    pub fn recv_with_fds(&self, buf: &mut [u8], fds: &mut [RawFd]) -> io::Result<(usize, usize)> {
+        recv_with_fds(&self.0, buf, fds)
+    }
+
+    /// Returns the credentials of the process that created the peer socket.
+    ///
+    /// The socket must be connected, e.g. created by [`pair`].
+    ///
+    /// [`pair`]: #method.pair
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use may::os::unix::net::UnixDatagram;
+    ///
+    /// let (sock, _) = UnixDatagram::pair().unwrap();
+    /// let cred = sock.peer_cred().expect("Couldn't get peer credentials");
+    /// println!("peer uid: {}", cred.uid);
+    /// ```
+    pub fn peer_cred(&self) -> io::Result<UCred> {
+        peer_cred(self.as_raw_fd())
+    }
+
+    /// Enables receiving the kernel timestamp of the datagrams by `SO_TIMESTAMPNS`.
+    ///
+    /// The timestamp is reported by [`recv_msg`].
+    ///
+    /// [`recv_msg`]: #method.recv_msg
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn set_recv_timestampns(&self, on: bool) -> io::Result<()> {
+        let on = on as libc::c_int;
+        setsockopt(self.as_raw_fd(), libc::SOL_SOCKET, libc::SO_TIMESTAMPNS, on)
+    }
+
+    /// Receives a datagram with the ancillary data into multiple buffers.
+    ///
+    /// Only the `len`, `timestamp`, `truncated` and `ctrl_truncated` fields of
+    /// the returned `RecvMeta` are meaningful for unix sockets.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use may::os::unix::net::UnixDatagram;
+    /// use std::io::IoSliceMut;
+    ///
+    /// let sock = UnixDatagram::bind("/path/to/the/socket").unwrap();
+    /// sock.set_recv_timestampns(true).unwrap();
+    /// let mut buf = [0; 64];
+    /// let meta = sock.recv_msg(&mut [IoSliceMut::new(&mut buf)]).unwrap();
+    /// println!("received {} bytes at {:?}", meta.len, meta.timestamp);
+    /// ```
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn recv_msg(&self, bufs: &mut [io::IoSliceMut<'_>]) -> io::Result<RecvMeta> {
+        let mut cmsg = CmsgBuf::new();
+        let mut hdr: libc::msghdr = unsafe { mem::zeroed() };
+        hdr.msg_iov = bufs.as_mut_ptr() as *mut libc::iovec;
+        hdr.msg_iovlen = bufs.len() as _;
+        cmsg.fill_recv(&mut hdr);
+
+        let len = self.0.recv_msg_hdr(&mut hdr, 0)?;
+        let mut meta = RecvMeta {
+            len,
+            ..RecvMeta::default()
+        };
+        decode_recv_meta(&hdr, &mut meta);
+        Ok(meta)
+    }
+
+    /// Sends a datagram gathered from multiple buffers to the socket's peer.
+    ///
+    /// This is the sending side of [`recv_msg`]. The ancillary data of
+    /// [`UdpSocket::send_msg`] is specific to IP, use [`send_with_fds`] to pass
+    /// file descriptors along with the datagram.
+    ///
+    /// [`recv_msg`]: #method.recv_msg
+    /// [`send_with_fds`]: #method.send_with_fds
+    /// [`UdpSocket::send_msg`]: ../../../net/struct.UdpSocket.html#method.send_msg
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use may::os::unix::net::UnixDatagram;
+    /// use std::io::IoSlice;
+    ///
+    /// let sock = UnixDatagram::unbound().unwrap();
+    /// sock.connect("/some/sock").expect("Couldn't connect");
+    /// let bufs = [IoSlice::new(b"head"), IoSlice::new(b"body")];
+    /// sock.send_msg(&bufs).expect("send_msg function failed");
+    /// ```
+    pub fn send_msg(&self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize> {
+        let mut hdr: libc::msghdr = unsafe { mem::zeroed() };
+        hdr.msg_iov = bufs.as_ptr() as *mut libc::iovec;
+        hdr.msg_iovlen = bufs.len() as _;
+        self.0.send_msg_hdr(&hdr, 0)
+    }
+
+    /// Sends a datagram gathered from multiple buffers to the specified address.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use may::os::unix::net::UnixDatagram;
+    /// use std::io::IoSlice;
+    ///
+    /// let sock = UnixDatagram::unbound().unwrap();
+    /// let bufs = [IoSlice::new(b"head"), IoSlice::new(b"body")];
+    /// sock.send_msg_to(&bufs, "/some/sock").expect("send_msg_to function failed");
+    /// ```
+    pub fn send_msg_to<P: AsRef<Path>>(
+        &self,
+        bufs: &[io::IoSlice<'_>],
+        path: P,
+    ) -> io::Result<usize> {
+        let addr = SocketAddr::from_pathname(path)?;
+        let mut hdr: libc::msghdr = unsafe { mem::zeroed() };
+        hdr.msg_name = addr.as_ptr() as *mut libc::c_void;
+        hdr.msg_namelen = addr.len;
+        hdr.msg_iov = bufs.as_ptr() as *mut libc::iovec;
+        hdr.msg_iovlen = bufs.len() as _;
+        self.0.send_msg_hdr(&hdr, 0)
+    }
 }
 
 impl AsRawFd for UnixDatagram {
@@ -1089,6 +1872,109 @@ impl IntoRawFd for UnixDatagram {
     }
 }
 
+/// Credentials of the peer process of a unix socket
+///
+/// returned by [`UnixStream::peer_cred`](struct.UnixStream.html#method.peer_cred)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UCred {
+    /// the process id of the peer, `None` if the platform doesn't report it
+    pub pid: Option<libc::pid_t>,
+    /// the effective user id of the peer
+    pub uid: libc::uid_t,
+    /// the effective group id of the peer
+    pub gid: libc::gid_t,
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn peer_cred(fd: RawFd) -> io::Result<UCred> {
+    let cred: libc::ucred = getsockopt(fd, libc::SOL_SOCKET, libc::SO_PEERCRED)?;
+    Ok(UCred {
+        pid: Some(cred.pid),
+        uid: cred.uid,
+        gid: cred.gid,
+    })
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn peer_cred(fd: RawFd) -> io::Result<UCred> {
+    let mut uid = 0;
+    let mut gid = 0;
+    if unsafe { libc::getpeereid(fd, &mut uid, &mut gid) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(UCred {
+        pid: None,
+        uid,
+        gid,
+    })
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const RECV_FLAGS: libc::c_int = libc::MSG_CMSG_CLOEXEC;
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+const RECV_FLAGS: libc::c_int = 0;
+
+fn send_with_fds<T: AsRawFd>(io: &CoIo<T>, buf: &[u8], fds: &[RawFd]) -> io::Result<usize> {
+    let mut cmsg = CmsgBuf::new();
+    if !fds.is_empty() && !cmsg.push(libc::SOL_SOCKET, libc::SCM_RIGHTS, fds) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "too many fds to send",
+        ));
+    }
+
+    let mut iov = libc::iovec {
+        iov_base: buf.as_ptr() as *mut libc::c_void,
+        iov_len: buf.len(),
+    };
+    let mut hdr: libc::msghdr = unsafe { mem::zeroed() };
+    hdr.msg_iov = &mut iov;
+    hdr.msg_iovlen = 1;
+    cmsg.fill_send(&mut hdr);
+
+    io.send_msg_hdr(&hdr, 0)
+}
+
+fn recv_with_fds<T: AsRawFd>(
+    io: &CoIo<T>,
+    buf: &mut [u8],
+    fds: &mut [RawFd],
+) -> io::Result<(usize, usize)> {
+    let mut cmsg = CmsgBuf::new();
+    let mut iov = libc::iovec {
+        iov_base: buf.as_mut_ptr() as *mut libc::c_void,
+        iov_len: buf.len(),
+    };
+    let mut hdr: libc::msghdr = unsafe { mem::zeroed() };
+    hdr.msg_iov = &mut iov;
+    hdr.msg_iovlen = 1;
+    cmsg.fill_recv(&mut hdr);
+
+    let len = io.recv_msg_hdr(&mut hdr, RECV_FLAGS)?;
+
+    let mut n = 0;
+    for_each_cmsg(&hdr, |level, ty, data| {
+        if level != libc::SOL_SOCKET || ty != libc::SCM_RIGHTS {
+            return;
+        }
+        for chunk in data.chunks_exact(mem::size_of::<RawFd>()) {
+            let fd = RawFd::from_ne_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+            if n < fds.len() {
+                #[cfg(not(any(target_os = "linux", target_os = "android")))]
+                unsafe {
+                    libc::ioctl(fd, libc::FIOCLEX);
+                }
+                fds[n] = fd;
+                n += 1;
+            } else {
+                // no room for the fd, don't leak it
+                unsafe { libc::close(fd) };
+            }
+        }
+    });
+    Ok((len, n))
+}
+
 #[cfg(all(test, not(target_os = "emscripten")))]
 mod test {
     use std::io;
@@ -1375,6 +2261,43 @@ mod test {
         assert_eq!(msg, &buf[..]);
     }
 
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[test]
+    fn datagram_msg() {
+        let dir = tmpdir();
+        let path = dir.path().join("sock");
+
+        let sock1 = or_panic!(UnixDatagram::bind(&path));
+        let sock2 = or_panic!(UnixDatagram::unbound());
+        or_panic!(sock1.set_recv_timestampns(true));
+
+        let j = go!(move || {
+            // block on the empty socket first
+            let (mut head, mut body) = ([0; 5], [0; 8]);
+            let mut bufs = [
+                io::IoSliceMut::new(&mut head),
+                io::IoSliceMut::new(&mut body),
+            ];
+            let meta = or_panic!(sock1.recv_msg(&mut bufs));
+            assert_eq!(meta.len, 13);
+            assert!(meta.timestamp.is_some());
+            assert_eq!(&head, b"hello");
+            assert_eq!(&body, b" datagra");
+
+            let mut buf = [0; 16];
+            let meta = or_panic!(sock1.recv_msg(&mut [io::IoSliceMut::new(&mut buf)]));
+            assert_eq!(&buf[..meta.len], b"connected");
+        });
+
+        crate::coroutine::sleep(Duration::from_millis(50));
+        let bufs = [io::IoSlice::new(b"hello"), io::IoSlice::new(b" datagra")];
+        assert_eq!(or_panic!(sock2.send_msg_to(&bufs, &path)), 13);
+        or_panic!(sock2.connect(&path));
+        let bufs = [io::IoSlice::new(b"conn"), io::IoSlice::new(b"ected")];
+        assert_eq!(or_panic!(sock2.send_msg(&bufs)), 9);
+        j.join().unwrap();
+    }
+
     #[test]
     fn datagram_pair() {
         let msg1 = b"hello";
@@ -1398,6 +2321,130 @@ mod test {
         thread.join().unwrap();
     }
 
+    #[test]
+    fn vectored() {
+        let (mut s1, mut s2) = or_panic!(UnixStream::pair());
+        let (d1, d2) = or_panic!(UnixDatagram::pair());
+        let thread = go!(move || {
+            // block on the empty sockets first
+            let (mut head, mut body) = ([0; 2], [0; 4]);
+            let mut bufs = [
+                io::IoSliceMut::new(&mut head),
+                io::IoSliceMut::new(&mut body),
+            ];
+            assert_eq!(or_panic!(s1.read_vectored(&mut bufs)), 6);
+            assert_eq!((&head, &body), (b"he", b"llo!"));
+
+            let mut bufs = [
+                io::IoSliceMut::new(&mut head),
+                io::IoSliceMut::new(&mut body),
+            ];
+            assert_eq!(or_panic!(d1.recv_vectored(&mut bufs)), 5);
+            assert_eq!((&head, &body[..3]), (b"wo", &b"rld"[..]));
+        });
+
+        ::std::thread::sleep(Duration::from_millis(50));
+        let bufs = [io::IoSlice::new(b"hel"), io::IoSlice::new(b"lo!")];
+        assert_eq!(or_panic!(s2.write_vectored(&bufs)), 6);
+        let bufs = [io::IoSlice::new(b"wor"), io::IoSlice::new(b"ld")];
+        assert_eq!(or_panic!(d2.send_vectored(&bufs)), 5);
+
+        thread.join().unwrap();
+    }
+
+    #[test]
+    fn pass_fds() {
+        let (s1, s2) = or_panic!(UnixStream::pair());
+        let (d1, d2) = or_panic!(UnixDatagram::pair());
+        let thread = go!(move || {
+            let (mut buf, mut fds) = ([0; 16], [-1; 2]);
+            let (len, n) = or_panic!(s1.recv_with_fds(&mut buf, &mut fds));
+            assert_eq!((&buf[..len], n), (&b"stream"[..], 1));
+            let mut file = unsafe { UnixStream::from_raw_fd(fds[0]) };
+            or_panic!(file.write_all(b"hello"));
+
+            // the extra fd is dropped when there is no room for it
+            let mut fds = [-1; 1];
+            let (len, n) = or_panic!(d1.recv_with_fds(&mut buf, &mut fds));
+            assert_eq!((&buf[..len], n), (&b"datagram"[..], 1));
+            unsafe { libc::close(fds[0]) };
+        });
+
+        let (mut a, b) = or_panic!(UnixStream::pair());
+        or_panic!(s2.send_with_fds(b"stream", &[b.as_raw_fd()]));
+        drop(b);
+        let mut buf = [0; 5];
+        or_panic!(a.read_exact(&mut buf));
+        assert_eq!(&buf, b"hello");
+
+        or_panic!(d2.send_with_fds(b"datagram", &[a.as_raw_fd(), a.as_raw_fd()]));
+        thread.join().unwrap();
+    }
+
+    #[test]
+    fn peer_cred() {
+        let (s1, _s2) = or_panic!(UnixStream::pair());
+        let cred = or_panic!(s1.peer_cred());
+        assert_eq!(cred.uid, unsafe { libc::geteuid() });
+        assert_eq!(cred.gid, unsafe { libc::getegid() });
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        assert_eq!(cred.pid, Some(unsafe { libc::getpid() }));
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[test]
+    fn abstract_namespace() {
+        let name = format!("may-test-{}", unsafe { libc::getpid() });
+        let listener = or_panic!(UnixListener::bind_abstract(name.as_bytes()));
+        let addr = or_panic!(listener.local_addr());
+        assert_eq!(addr.as_abstract_name(), Some(name.as_bytes()));
+        assert_eq!(addr.as_pathname(), None);
+        assert_eq!(format!("{:?}", addr), format!("\"{}\" (abstract)", name));
+
+        let client_name = name.clone();
+        let thread = go!(move || {
+            let mut stream = or_panic!(UnixStream::connect_abstract(client_name.as_bytes()));
+            let peer = or_panic!(stream.peer_addr());
+            assert_eq!(peer.as_abstract_name(), Some(client_name.as_bytes()));
+            or_panic!(stream.write_all(b"hello"));
+        });
+
+        let (mut stream, addr) = or_panic!(listener.accept());
+        assert!(addr.is_unnamed());
+        let mut buf = [0; 5];
+        or_panic!(stream.read_exact(&mut buf));
+        assert_eq!(&buf, b"hello");
+        thread.join().unwrap();
+
+        // reply to an abstract datagram sender
+        let server_name = format!("{}-dgram", name);
+        let server = or_panic!(UnixDatagram::bind_abstract(server_name.as_bytes()));
+        let client_name = format!("{}-dgram-client", name);
+        let client = or_panic!(UnixDatagram::bind_abstract(client_name.as_bytes()));
+        or_panic!(client.connect_abstract(server_name.as_bytes()));
+        or_panic!(client.send(b"ping"));
+        let (n, from) = or_panic!(server.recv_from(&mut buf));
+        assert_eq!(&buf[..n], b"ping");
+        assert_eq!(from, or_panic!(client.local_addr()));
+        or_panic!(server.send_to_addr(b"pong", &from));
+        let n = or_panic!(client.recv(&mut buf));
+        assert_eq!(&buf[..n], b"pong");
+    }
+
+    #[test]
+    fn pathname_addr() {
+        let dir = tmpdir();
+        let path = dir.path().join("sock");
+        let addr = or_panic!(SocketAddr::from_pathname(&path));
+        let listener = or_panic!(UnixListener::bind_addr(&addr));
+        let local = or_panic!(listener.local_addr());
+        assert_eq!(local.as_pathname(), Some(path.as_path()));
+        assert_eq!(local, addr);
+        assert!(or_panic!(UnixStream::connect_addr(&addr))
+            .peer_addr()
+            .is_ok());
+    }
+
     #[test]
     fn abstract_namespace_not_allowed() {
         assert!(UnixStream::connect("\0asdf").is_err());
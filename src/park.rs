@@ -7,7 +7,7 @@ use std::time::Duration;
 
 use crate::cancel::Cancel;
 use crate::coroutine_impl::{co_cancel_data, run_coroutine, CoroutineImpl, EventSource};
-use crate::scheduler::get_scheduler;
+use crate::scheduler::{get_scheduler, TimerData};
 use crate::sync::atomic_dur::AtomicDuration;
 use crate::sync::AtomicOption;
 use crate::timeout_list::TimeoutHandle;
@@ -31,7 +31,7 @@ pub struct Park {
     // timeout settings in ms, 0 is none (park forever)
     timeout: AtomicDuration,
     // timer handle, can be null
-    timeout_handle: AtomicPtr<TimeoutHandle<Arc<AtomicOption<CoroutineImpl>>>>,
+    timeout_handle: AtomicPtr<TimeoutHandle<TimerData>>,
     // a flag if kernel is entered
     wait_kernel: AtomicBool,
 }
@@ -63,8 +63,8 @@ impl Park {
     #[inline]
     fn set_timeout_handle(
         &self,
-        handle: Option<TimeoutHandle<Arc<AtomicOption<CoroutineImpl>>>>,
-    ) -> Option<TimeoutHandle<Arc<AtomicOption<CoroutineImpl>>>> {
+        handle: Option<TimeoutHandle<TimerData>>,
+    ) -> Option<TimeoutHandle<TimerData>> {
         let ptr = match handle {
             None => ptr::null_mut(),
             Some(h) => h.into_ptr(),
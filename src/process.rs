@@ -0,0 +1,552 @@
+//! Coroutine aware child process spawning
+//!
+//! This module is a thin wrapper of `std::process`. The stdio pipes of the
+//! child are non-blocking and wrapped in `CoIo`, and waiting for the child
+//! would park the coroutine instead of blocking the worker thread.
+//!
+//! On Linux the exit of the child is watched through a `pidfd` registered in
+//! the selector, on other platforms (or old kernels without `pidfd` support)
+//! the wait is dispatched to the blocking thread pool.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use may::process::Command;
+//!
+//! let output = Command::new("echo").arg("hello").output().unwrap();
+//! assert_eq!(output.stdout, b"hello\n");
+//! ```
+
+use std::ffi::OsStr;
+use std::io::{self, Read};
+use std::path::Path;
+use std::process::{self, ExitStatus, Output, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::blocking_pool;
+use crate::coroutine_impl::is_coroutine;
+use crate::io::CoIo;
+use crate::time;
+
+/// The handle of the child's stdin, it's a non-blocking pipe
+pub type ChildStdin = CoIo<process::ChildStdin>;
+/// The handle of the child's stdout, it's a non-blocking pipe
+pub type ChildStdout = CoIo<process::ChildStdout>;
+/// The handle of the child's stderr, it's a non-blocking pipe
+pub type ChildStderr = CoIo<process::ChildStderr>;
+
+/// A process builder, wrapper of `std::process::Command`.
+///
+/// All the configuration methods are the same as the std ones, the
+/// [`spawn`], [`output`] and [`status`] methods are coroutine aware.
+///
+/// [`spawn`]: #method.spawn
+/// [`output`]: #method.output
+/// [`status`]: #method.status
+#[derive(Debug)]
+pub struct Command {
+    sys: process::Command,
+    kill_on_drop: bool,
+    // whether the stdin, stdout and stderr are configured by the user
+    stdio_set: [bool; 3],
+}
+
+impl Command {
+    /// Constructs a new `Command` for launching the program at path `program`.
+    pub fn new<S: AsRef<OsStr>>(program: S) -> Command {
+        Command {
+            sys: process::Command::new(program),
+            kill_on_drop: false,
+            stdio_set: [false; 3],
+        }
+    }
+
+    /// Adds an argument to pass to the program.
+    pub fn arg<S: AsRef<OsStr>>(&mut self, arg: S) -> &mut Command {
+        self.sys.arg(arg);
+        self
+    }
+
+    /// Adds multiple arguments to pass to the program.
+    pub fn args<I, S>(&mut self, args: I) -> &mut Command
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        self.sys.args(args);
+        self
+    }
+
+    /// Inserts or updates an environment variable mapping.
+    pub fn env<K, V>(&mut self, key: K, val: V) -> &mut Command
+    where
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>,
+    {
+        self.sys.env(key, val);
+        self
+    }
+
+    /// Adds or updates multiple environment variable mappings.
+    pub fn envs<I, K, V>(&mut self, vars: I) -> &mut Command
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>,
+    {
+        self.sys.envs(vars);
+        self
+    }
+
+    /// Removes an environment variable mapping.
+    pub fn env_remove<K: AsRef<OsStr>>(&mut self, key: K) -> &mut Command {
+        self.sys.env_remove(key);
+        self
+    }
+
+    /// Clears the entire environment map for the child process.
+    pub fn env_clear(&mut self) -> &mut Command {
+        self.sys.env_clear();
+        self
+    }
+
+    /// Sets the working directory for the child process.
+    pub fn current_dir<P: AsRef<Path>>(&mut self, dir: P) -> &mut Command {
+        self.sys.current_dir(dir);
+        self
+    }
+
+    /// Configuration for the child process's standard input (stdin) handle.
+    pub fn stdin<T: Into<Stdio>>(&mut self, cfg: T) -> &mut Command {
+        self.sys.stdin(cfg);
+        self.stdio_set[0] = true;
+        self
+    }
+
+    /// Configuration for the child process's standard output (stdout) handle.
+    pub fn stdout<T: Into<Stdio>>(&mut self, cfg: T) -> &mut Command {
+        self.sys.stdout(cfg);
+        self.stdio_set[1] = true;
+        self
+    }
+
+    /// Configuration for the child process's standard error (stderr) handle.
+    pub fn stderr<T: Into<Stdio>>(&mut self, cfg: T) -> &mut Command {
+        self.sys.stderr(cfg);
+        self.stdio_set[2] = true;
+        self
+    }
+
+    /// Controls whether the child process would be killed when the `Child`
+    /// handle is dropped before the child exits.
+    ///
+    /// The killed child is reaped in the background. Default is `false`.
+    pub fn kill_on_drop(&mut self, kill_on_drop: bool) -> &mut Command {
+        self.kill_on_drop = kill_on_drop;
+        self
+    }
+
+    /// get the inner std command ref
+    pub fn inner(&self) -> &process::Command {
+        &self.sys
+    }
+
+    /// get the inner std command mut ref, useful for platform extensions
+    pub fn inner_mut(&mut self) -> &mut process::Command {
+        &mut self.sys
+    }
+
+    /// Executes the command as a child process, returning a handle to it.
+    pub fn spawn(&mut self) -> io::Result<Child> {
+        let sys = self.sys.spawn()?;
+        Child::new(sys, self.kill_on_drop)
+    }
+
+    /// Executes the command as a child process, waiting for it to finish and
+    /// collecting all of its output.
+    ///
+    /// By default the stdout and stderr are captured and stdin is set to
+    /// null, the streams that are configured by the user are kept.
+    pub fn output(&mut self) -> io::Result<Output> {
+        let [stdin, stdout, stderr] = self.stdio_set;
+        if !stdin {
+            self.sys.stdin(Stdio::null());
+        }
+        if !stdout {
+            self.sys.stdout(Stdio::piped());
+        }
+        if !stderr {
+            self.sys.stderr(Stdio::piped());
+        }
+        let child = self.spawn();
+        // the defaults only apply to this call, inherit is the same as
+        // not configured for `spawn` and `status`
+        if !stdin {
+            self.sys.stdin(Stdio::inherit());
+        }
+        if !stdout {
+            self.sys.stdout(Stdio::inherit());
+        }
+        if !stderr {
+            self.sys.stderr(Stdio::inherit());
+        }
+        child?.wait_with_output()
+    }
+
+    /// Executes a command as a child process, waiting for it to finish and
+    /// collecting its exit status.
+    pub fn status(&mut self) -> io::Result<ExitStatus> {
+        self.spawn()?.wait()
+    }
+}
+
+/// Representation of a running or exited child process.
+///
+/// Unlike the std one, waiting for the child would not block the worker thread.
+#[derive(Debug)]
+pub struct Child {
+    sys: process::Child,
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pidfd: Option<pidfd::PidFd>,
+    kill_on_drop: bool,
+    /// The handle for writing to the child's standard input (stdin), if it
+    /// has been captured.
+    pub stdin: Option<ChildStdin>,
+    /// The handle for reading from the child's standard output (stdout), if it
+    /// has been captured.
+    pub stdout: Option<ChildStdout>,
+    /// The handle for reading from the child's standard error (stderr), if it
+    /// has been captured.
+    pub stderr: Option<ChildStderr>,
+}
+
+impl Child {
+    fn new(mut sys: process::Child, kill_on_drop: bool) -> io::Result<Child> {
+        let stdin = match sys.stdin.take() {
+            Some(s) => Some(CoIo::new(s)?),
+            None => None,
+        };
+        let stdout = match sys.stdout.take() {
+            Some(s) => Some(CoIo::new(s)?),
+            None => None,
+        };
+        let stderr = match sys.stderr.take() {
+            Some(s) => Some(CoIo::new(s)?),
+            None => None,
+        };
+
+        Ok(Child {
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            pidfd: pidfd::PidFd::open(sys.id()),
+            sys,
+            kill_on_drop,
+            stdin,
+            stdout,
+            stderr,
+        })
+    }
+
+    /// Returns the OS-assigned process identifier associated with this child.
+    pub fn id(&self) -> u32 {
+        self.sys.id()
+    }
+
+    /// Forces the child process to exit.
+    pub fn kill(&mut self) -> io::Result<()> {
+        self.sys.kill()
+    }
+
+    /// Attempts to collect the exit status of the child if it has already exited.
+    pub fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        self.sys.try_wait()
+    }
+
+    /// Waits for the child to exit completely, returning the status that it
+    /// exited with.
+    ///
+    /// The stdin handle to the child process, if any, will be closed before
+    /// waiting. In coroutine context only the coroutine is blocked.
+    pub fn wait(&mut self) -> io::Result<ExitStatus> {
+        drop(self.stdin.take());
+        match self.wait_impl(None)? {
+            Some(status) => Ok(status),
+            None => unreachable!("wait child without timeout"),
+        }
+    }
+
+    /// Waits for the child to exit with a timeout.
+    ///
+    /// Returns `Ok(None)` if the child is still running after the timeout.
+    pub fn wait_timeout(&mut self, dur: Duration) -> io::Result<Option<ExitStatus>> {
+        drop(self.stdin.take());
+        self.wait_impl(Some(dur))
+    }
+
+    /// Simultaneously waits for the child to exit and collect all remaining
+    /// output on the stdout/stderr handles, returning an `Output` instance.
+    pub fn wait_with_output(mut self) -> io::Result<Output> {
+        drop(self.stdin.take());
+
+        // read the stderr in another coroutine to avoid dead lock
+        let stderr = self.stderr.take().map(|mut err| {
+            go!(move || {
+                let mut buf = Vec::new();
+                err.read_to_end(&mut buf).map(|_| buf)
+            })
+        });
+
+        let mut stdout = Vec::new();
+        if let Some(mut out) = self.stdout.take() {
+            out.read_to_end(&mut stdout)?;
+        }
+
+        let stderr = match stderr {
+            Some(h) => h
+                .join()
+                .map_err(|_| io::Error::new(io::ErrorKind::Other, "read stderr panicked"))??,
+            None => Vec::new(),
+        };
+
+        let status = self.wait()?;
+        Ok(Output {
+            status,
+            stdout,
+            stderr,
+        })
+    }
+
+    fn wait_impl(&mut self, timeout: Option<Duration>) -> io::Result<Option<ExitStatus>> {
+        if !is_coroutine() {
+            return match timeout {
+                None => self.sys.wait().map(Some),
+                Some(dur) => self.thread_wait_timeout(dur),
+            };
+        }
+
+        let deadline = timeout.map(|dur| time::now() + dur);
+        loop {
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            {
+                if let Some(ref pidfd) = self.pidfd {
+                    pidfd.reset();
+                }
+            }
+
+            if let Some(status) = self.sys.try_wait()? {
+                return Ok(Some(status));
+            }
+
+            let timeout = match deadline {
+                None => None,
+                Some(d) => {
+                    let now = time::now();
+                    if now >= d {
+                        return Ok(None);
+                    }
+                    Some(d - now)
+                }
+            };
+
+            match self.wait_exit(timeout) {
+                Ok(_) => {}
+                Err(ref e) if e.kind() == io::ErrorKind::TimedOut => {}
+                // the child is already reaped, try_wait would give the status
+                Err(ref e) if e.raw_os_error() == Some(libc::ECHILD) => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    // wait the child exit in coroutine context, the child is not reaped
+    fn wait_exit(&self, timeout: Option<Duration>) -> io::Result<()> {
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        {
+            if let Some(ref pidfd) = self.pidfd {
+                return pidfd.wait(timeout);
+            }
+        }
+
+        let pid = self.sys.id();
+        blocking_pool::run(move || wait_no_reap(pid), timeout)
+    }
+
+    // there is no way to block the thread with timeout on a child, just poll it
+    fn thread_wait_timeout(&mut self, dur: Duration) -> io::Result<Option<ExitStatus>> {
+        let deadline = Instant::now() + dur;
+        let mut interval = Duration::from_millis(1);
+        loop {
+            if let Some(status) = self.sys.try_wait()? {
+                return Ok(Some(status));
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(None);
+            }
+            thread::sleep(::std::cmp::min(interval, deadline - now));
+            interval = ::std::cmp::min(interval * 2, Duration::from_millis(50));
+        }
+    }
+}
+
+impl Drop for Child {
+    fn drop(&mut self) {
+        if !self.kill_on_drop {
+            return;
+        }
+
+        if let Ok(None) = self.sys.try_wait() {
+            if self.sys.kill().is_ok() {
+                // reap the killed child in background to avoid zombie process
+                let pid = self.sys.id() as libc::pid_t;
+                blocking_pool::spawn(move || unsafe {
+                    let mut status = 0;
+                    libc::waitpid(pid, &mut status, 0);
+                });
+            }
+        }
+    }
+}
+
+// block the thread until the child exit, but leave it in a waitable state
+fn wait_no_reap(pid: u32) -> io::Result<()> {
+    loop {
+        let mut info: libc::siginfo_t = unsafe { ::std::mem::zeroed() };
+        let ret = unsafe {
+            libc::waitid(
+                libc::P_PID,
+                pid as libc::id_t,
+                &mut info,
+                libc::WEXITED | libc::WNOWAIT,
+            )
+        };
+        if ret == 0 {
+            return Ok(());
+        }
+        let err = io::Error::last_os_error();
+        if err.kind() != io::ErrorKind::Interrupted {
+            return Err(err);
+        }
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod pidfd {
+    use std::io;
+    use std::os::unix::io::{AsRawFd, RawFd};
+    use std::time::Duration;
+
+    use crate::io as io_impl;
+    use crate::io::sys::wait_io::wait_io_timeout;
+
+    #[derive(Debug)]
+    struct RawPidFd(RawFd);
+
+    impl AsRawFd for RawPidFd {
+        fn as_raw_fd(&self) -> RawFd {
+            self.0
+        }
+    }
+
+    impl Drop for RawPidFd {
+        fn drop(&mut self) {
+            unsafe { libc::close(self.0) };
+        }
+    }
+
+    // the pidfd would become readable when the process exits
+    #[derive(Debug)]
+    pub struct PidFd {
+        // must drop the io data before close the fd
+        io: io_impl::IoData,
+        _fd: RawPidFd,
+    }
+
+    impl PidFd {
+        // return None if the kernel doesn't support pidfd
+        pub fn open(pid: u32) -> Option<PidFd> {
+            let fd = unsafe { libc::syscall(libc::SYS_pidfd_open, pid as libc::pid_t, 0) };
+            if fd < 0 {
+                return None;
+            }
+            let fd = RawPidFd(fd as RawFd);
+            let io = io_impl::add_socket(&fd).ok()?;
+            Some(PidFd { io, _fd: fd })
+        }
+
+        pub fn reset(&self) {
+            self.io.reset();
+        }
+
+        pub fn wait(&self, timeout: Option<Duration>) -> io::Result<()> {
+            wait_io_timeout(&self.io, timeout)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn output() {
+        let h = go!(|| {
+            let output = Command::new("echo").arg("hello").output().unwrap();
+            assert!(output.status.success());
+            assert_eq!(output.stdout, b"hello\n");
+        });
+        h.join().unwrap();
+    }
+
+    #[test]
+    fn output_keeps_stdio() {
+        let h = go!(|| {
+            let mut cmd = Command::new("sh");
+            cmd.arg("-c").arg("echo out; echo err >&2");
+            cmd.stderr(Stdio::null());
+            let output = cmd.output().unwrap();
+            assert_eq!(output.stdout, b"out\n");
+            // the user configured stderr is not captured
+            assert!(output.stderr.is_empty());
+
+            // the defaults of output don't leak into spawn
+            let mut cmd = Command::new("true");
+            cmd.output().unwrap();
+            let mut child = cmd.spawn().unwrap();
+            assert!(child.stdin.is_none());
+            assert!(child.stdout.is_none());
+            assert!(child.stderr.is_none());
+            assert!(child.wait().unwrap().success());
+        });
+        h.join().unwrap();
+    }
+
+    #[test]
+    fn pipe_stdin() {
+        let h = go!(|| {
+            let mut child = Command::new("cat")
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .spawn()
+                .unwrap();
+            child.stdin.as_mut().unwrap().write_all(b"may").unwrap();
+            let output = child.wait_with_output().unwrap();
+            assert_eq!(output.stdout, b"may");
+        });
+        h.join().unwrap();
+    }
+
+    #[test]
+    fn wait_timeout() {
+        let h = go!(|| {
+            let mut child = Command::new("sleep").arg("10").spawn().unwrap();
+            let status = child.wait_timeout(Duration::from_millis(100)).unwrap();
+            assert!(status.is_none());
+            child.kill().unwrap();
+            let status = child.wait().unwrap();
+            assert!(!status.success());
+        });
+        h.join().unwrap();
+    }
+}
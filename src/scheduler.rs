@@ -5,7 +5,7 @@ use std::thread;
 use std::time::Duration;
 
 use crate::config::config;
-use crate::coroutine_impl::{run_coroutine, CoroutineImpl};
+use crate::coroutine_impl::{pinned_worker, run_coroutine, CoroutineImpl};
 use crate::io::{EventLoop, Selector};
 use crate::pool::CoroutinePool;
 use crate::sync::AtomicOption;
@@ -31,9 +31,26 @@ pub static WORKER_ID: AtomicUsize = AtomicUsize::new(!1);
 #[cfg(not(nightly))]
 thread_local! { pub static WORKER_ID: AtomicUsize = AtomicUsize::new(!1); }
 
-// here we use Arc<AtomicOption<>> for that in the select implementation
-// other event may try to consume the coroutine while timer thread consume it
-type TimerData = Arc<AtomicOption<CoroutineImpl>>;
+// get the id of the current worker thread, `!1` for the other threads
+#[inline]
+pub(crate) fn worker_id() -> usize {
+    #[cfg(nightly)]
+    let id = WORKER_ID.load(Ordering::Relaxed);
+    #[cfg(not(nightly))]
+    let id = WORKER_ID.with(|id| id.load(Ordering::Relaxed));
+    id
+}
+
+// the data of the timers that are driven by the timer thread
+pub enum TimerData {
+    // resume the coroutine with the timeout error
+    // here we use Arc<AtomicOption<>> for that in the select implementation
+    // other event may try to consume the coroutine while timer thread consume it
+    Resume(Arc<AtomicOption<CoroutineImpl>>),
+    // run the handler in the timer thread, it must not block
+    Handler(Box<dyn FnOnce() + Send>),
+}
+
 type TimerThread = timeout_list::TimerThread<TimerData>;
 
 // filter out the cancel panic, don't print anything for it
@@ -96,14 +113,17 @@ fn init_scheduler() {
         filter_cancel_panic();
         let s = unsafe { &*SCHED };
         // timer function
-        let timer_event_handler = |co: Arc<AtomicOption<CoroutineImpl>>| {
-            // just re-push the co to the visit list
-            if let Some(mut c) = co.take(Ordering::Relaxed) {
-                // set the timeout result for the coroutine
-                set_co_para(&mut c, io::Error::new(io::ErrorKind::TimedOut, "timeout"));
-                // s.schedule_global(c);
-                run_coroutine(c);
+        let timer_event_handler = |data: TimerData| match data {
+            TimerData::Resume(co) => {
+                // just re-push the co to the visit list
+                if let Some(mut c) = co.take(Ordering::Relaxed) {
+                    // set the timeout result for the coroutine
+                    set_co_para(&mut c, io::Error::new(io::ErrorKind::TimedOut, "timeout"));
+                    // s.schedule_global(c);
+                    run_coroutine(c);
+                }
             }
+            TimerData::Handler(f) => f(),
         };
 
         s.timer_thread.run(&timer_event_handler);
@@ -152,6 +172,18 @@ fn steal_global<T>(global: &deque::Injector<T>, local: &deque::Worker<T>) -> Opt
     ret
 }
 
+#[inline]
+fn pop_pinned<T>(pinned: &deque::Injector<T>) -> Option<T> {
+    let backoff = Backoff::new();
+    loop {
+        match pinned.steal() {
+            deque::Steal::Success(t) => return Some(t),
+            deque::Steal::Empty => return None,
+            deque::Steal::Retry => backoff.snooze(),
+        }
+    }
+}
+
 #[inline]
 fn steal_local<T>(stealer: &deque::Stealer<T>, local: &deque::Worker<T>) -> Option<T> {
     let backoff = Backoff::new();
@@ -170,6 +202,8 @@ pub struct Scheduler {
     event_loop: EventLoop,
     global_queue: deque::Injector<CoroutineImpl>,
     local_queues: Vec<deque::Worker<CoroutineImpl>>,
+    // the coroutines that can only run on the specified worker
+    pinned_queues: Vec<deque::Injector<CoroutineImpl>>,
     pub(crate) workers: ParkStatus,
     timer_thread: TimerThread,
     stealers: Vec<Vec<(usize, deque::Stealer<CoroutineImpl>)>>,
@@ -179,6 +213,7 @@ impl Scheduler {
     pub fn new(workers: usize) -> Box<Self> {
         let mut local_queues = Vec::with_capacity(workers);
         (0..workers).for_each(|_| local_queues.push(deque::Worker::new_fifo()));
+        let pinned_queues = (0..workers).map(|_| deque::Injector::new()).collect();
         let mut stealers = Vec::with_capacity(workers);
         for id in 0..workers {
             let mut stealers_l = Vec::with_capacity(workers);
@@ -195,7 +230,8 @@ impl Scheduler {
             event_loop: EventLoop::new(workers).expect("can't create event_loop"),
             global_queue: deque::Injector::new(),
             local_queues,
-            timer_thread: TimerThread::new(),
+            pinned_queues,
+            timer_thread: TimerThread::new(workers),
             workers: ParkStatus::new(workers),
             stealers,
         })
@@ -203,10 +239,11 @@ impl Scheduler {
 
     pub fn run_queued_tasks(&self, id: usize) {
         let local = unsafe { self.local_queues.get_unchecked(id) };
+        let pinned = unsafe { self.pinned_queues.get_unchecked(id) };
         let stealers = unsafe { self.stealers.get_unchecked(id) };
         loop {
             // Pop a task from the local queue
-            let co = local.pop().or_else(|| {
+            let co = local.pop().or_else(|| pop_pinned(pinned)).or_else(|| {
                 // Try stealing a of task from other local queues.
                 let parked_threads = self.workers.parked.load(Ordering::Relaxed);
                 stealers
@@ -226,7 +263,7 @@ impl Scheduler {
                 run_coroutine(co);
             } else {
                 // do a re-check
-                if self.global_queue.is_empty() {
+                if self.global_queue.is_empty() && pinned.is_empty() {
                     break;
                 }
             }
@@ -236,11 +273,11 @@ impl Scheduler {
     /// put the coroutine to correct queue so that next time it can be scheduled
     #[inline]
     pub fn schedule(&self, co: CoroutineImpl) {
-        #[cfg(nightly)]
-        let id = WORKER_ID.load(Ordering::Relaxed);
-        #[cfg(not(nightly))]
-        let id = WORKER_ID.with(|id| id.load(Ordering::Relaxed));
+        if let Some(id) = pinned_worker(&co) {
+            return self.schedule_pinned(id, co);
+        }
 
+        let id = worker_id();
         if id == !1 {
             self.schedule_global(co);
         } else {
@@ -251,18 +288,46 @@ impl Scheduler {
     /// put the coroutine to global queue so that next time it can be scheduled
     #[inline]
     pub fn schedule_global(&self, co: CoroutineImpl) {
+        if let Some(id) = pinned_worker(&co) {
+            return self.schedule_pinned(id, co);
+        }
+
         self.global_queue.push(co);
         // signal one waiting thread if any
         self.workers.wake_one(&self);
     }
 
+    /// put the coroutine to the queue that only the worker `id` would run
+    #[inline]
+    pub(crate) fn schedule_pinned(&self, id: usize, co: CoroutineImpl) {
+        unsafe { self.pinned_queues.get_unchecked(id) }.push(co);
+        if worker_id() != id {
+            // the worker may be blocked in the selector
+            self.get_selector().wakeup(id);
+        }
+    }
+
     #[inline]
     pub fn add_timer(
         &self,
         dur: Duration,
         co: Arc<AtomicOption<CoroutineImpl>>,
     ) -> timeout_list::TimeoutHandle<TimerData> {
-        self.timer_thread.add_timer(dur, co)
+        self.timer_thread.add_timer(dur, TimerData::Resume(co))
+    }
+
+    /// run the handler in the timer thread when the timer expires
+    #[inline]
+    pub(crate) fn add_timer_handler<F>(
+        &self,
+        dur: Duration,
+        f: F,
+    ) -> timeout_list::TimeoutHandle<TimerData>
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.timer_thread
+            .add_timer(dur, TimerData::Handler(Box::new(f)))
     }
 
     #[inline]
@@ -275,3 +340,35 @@ impl Scheduler {
         self.event_loop.get_selector()
     }
 }
+
+#[cfg(feature = "test-util")]
+impl Scheduler {
+    // wake up the timer thread and all the workers to process the timers
+    pub(crate) fn wake_timers(&self) {
+        self.timer_thread.wakeup();
+        let selector = self.get_selector();
+        for id in 0..self.workers.workers {
+            selector.wakeup(id);
+        }
+    }
+
+    // return the earliest timer deadline in ns when there is nothing to run,
+    // that is all the workers are parked and no timer handler is running
+    pub(crate) fn idle_deadline(&self) -> Option<u64> {
+        let all_parked = u64::MAX >> (64 - self.workers.workers);
+        if self.workers.parked.load(Ordering::Acquire) != all_parked
+            || !self.global_queue.is_empty()
+            || self.pinned_queues.iter().any(|q| !q.is_empty())
+            || self.timer_thread.is_busy()
+        {
+            return None;
+        }
+
+        let timer = self.timer_thread.next_deadline();
+        let io = self.get_selector().next_timer_deadline();
+        match (timer, io) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+}
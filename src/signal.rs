@@ -0,0 +1,302 @@
+//! Coroutine aware unix signal handling
+//!
+//! The signals are delivered through a self-pipe which is registered in the
+//! selector, so waiting for a signal only parks the calling coroutine.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use may::signal::{Signals, SIGHUP, SIGTERM};
+//!
+//! let mut signals = Signals::new(&[SIGTERM, SIGHUP]).unwrap();
+//! for sig in signals.forever() {
+//!     match sig {
+//!         SIGHUP => println!("reload config"),
+//!         _ => break,
+//!     }
+//! }
+//! ```
+
+use std::collections::VecDeque;
+use std::io::{self, ErrorKind, Read};
+use std::os::unix::io::AsRawFd;
+use std::os::unix::net::UnixStream;
+use std::sync::atomic::{AtomicI32, AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+use std::{fmt, mem, ptr};
+
+use crate::io::CoIo;
+
+pub use libc::{
+    SIGALRM, SIGCHLD, SIGCONT, SIGHUP, SIGINT, SIGIO, SIGPIPE, SIGQUIT, SIGTERM, SIGTSTP, SIGUSR1,
+    SIGUSR2, SIGWINCH,
+};
+
+// max number of `Signals` instance that could exist at the same time
+const MAX_SLOTS: usize = 128;
+
+// the write end fd of each registered `Signals`, -1 means the slot is free
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY_FD: AtomicI32 = AtomicI32::new(-1);
+static SLOT_FDS: [AtomicI32; MAX_SLOTS] = [EMPTY_FD; MAX_SLOTS];
+// the interested signal mask of each slot
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY_MASK: AtomicU64 = AtomicU64::new(0);
+static SLOT_MASKS: [AtomicU64; MAX_SLOTS] = [EMPTY_MASK; MAX_SLOTS];
+// number of signal handlers that are accessing the slots
+static IN_HANDLER: AtomicUsize = AtomicUsize::new(0);
+// the signals that already have our handler installed
+static INSTALLED: AtomicU64 = AtomicU64::new(0);
+
+// the signals that can't be handled
+const FORBIDDEN: &[libc::c_int] = &[
+    libc::SIGKILL,
+    libc::SIGSTOP,
+    libc::SIGILL,
+    libc::SIGFPE,
+    libc::SIGSEGV,
+    libc::SIGBUS,
+];
+
+// the signal handler, only async-signal-safe operations are allowed here
+extern "C" fn handler(sig: libc::c_int) {
+    let errno = unsafe { *errno_location() };
+    IN_HANDLER.fetch_add(1, Ordering::SeqCst);
+    let bit = 1u64 << sig;
+    for (fd, mask) in SLOT_FDS.iter().zip(SLOT_MASKS.iter()) {
+        if mask.load(Ordering::SeqCst) & bit == 0 {
+            continue;
+        }
+        let fd = fd.load(Ordering::SeqCst);
+        if fd < 0 {
+            continue;
+        }
+        // the fd is nonblocking, if the pipe is full the signal is just
+        // coalesced with the pending ones
+        let byte = sig as u8;
+        unsafe { libc::write(fd, &byte as *const u8 as *const libc::c_void, 1) };
+    }
+    IN_HANDLER.fetch_sub(1, Ordering::SeqCst);
+    // restore the errno for the interrupted code
+    unsafe { *errno_location() = errno };
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+unsafe fn errno_location() -> *mut libc::c_int {
+    libc::__errno_location()
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+unsafe fn errno_location() -> *mut libc::c_int {
+    libc::__error()
+}
+
+fn check_signal(sig: libc::c_int) -> io::Result<()> {
+    if sig <= 0 || sig >= 64 || FORBIDDEN.contains(&sig) {
+        return Err(io::Error::new(
+            ErrorKind::InvalidInput,
+            format!("can't register signal {}", sig),
+        ));
+    }
+    Ok(())
+}
+
+fn install(sig: libc::c_int) -> io::Result<()> {
+    check_signal(sig)?;
+
+    // install the same handler more than once is harmless
+    if INSTALLED.load(Ordering::Acquire) & (1 << sig) != 0 {
+        return Ok(());
+    }
+
+    unsafe {
+        let mut action: libc::sigaction = mem::zeroed();
+        action.sa_sigaction = handler as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        action.sa_flags = libc::SA_RESTART;
+        libc::sigemptyset(&mut action.sa_mask);
+        if libc::sigaction(sig, &action, ptr::null_mut()) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    INSTALLED.fetch_or(1 << sig, Ordering::AcqRel);
+    Ok(())
+}
+
+/// A set of signals that can be waited in coroutine context.
+///
+/// Once a signal is registered by any `Signals` its default action is
+/// replaced, e.g. `SIGINT` would no longer terminate the process, even
+/// after the `Signals` is dropped.
+///
+/// Multiple deliveries of the same signal may be coalesced into one when
+/// the pending signals are not consumed in time.
+pub struct Signals {
+    slot: usize,
+    rx: CoIo<UnixStream>,
+    // keep the write end open while the slot is registered
+    _tx: UnixStream,
+    pending: VecDeque<libc::c_int>,
+}
+
+impl fmt::Debug for Signals {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Signals")
+            .field("slot", &self.slot)
+            .field("mask", &SLOT_MASKS[self.slot].load(Ordering::Relaxed))
+            .finish()
+    }
+}
+
+impl Signals {
+    /// create a `Signals` that listens on the given signals
+    pub fn new(signals: &[libc::c_int]) -> io::Result<Signals> {
+        let mut mask = 0u64;
+        for &sig in signals {
+            check_signal(sig)?;
+            mask |= 1 << sig;
+        }
+
+        let (rx, tx) = UnixStream::pair()?;
+        tx.set_nonblocking(true)?;
+        let rx = CoIo::new(rx)?;
+
+        let slot = SLOT_FDS
+            .iter()
+            .position(|fd| {
+                fd.compare_exchange(-1, tx.as_raw_fd(), Ordering::SeqCst, Ordering::SeqCst)
+                    .is_ok()
+            })
+            .ok_or_else(|| io::Error::new(ErrorKind::Other, "too many signals instances"))?;
+        // the slot is released by drop if installing the handlers failed
+        let ret = Signals {
+            slot,
+            rx,
+            _tx: tx,
+            pending: VecDeque::new(),
+        };
+
+        // replace the default actions only after all the resources are ready
+        for &sig in signals {
+            install(sig)?;
+        }
+        SLOT_MASKS[slot].store(mask, Ordering::SeqCst);
+        Ok(ret)
+    }
+
+    /// add a new signal to the set
+    pub fn add_signal(&self, sig: libc::c_int) -> io::Result<()> {
+        install(sig)?;
+        SLOT_MASKS[self.slot].fetch_or(1 << sig, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// wait for the next signal
+    ///
+    /// this would park the coroutine until a signal arrives, in thread
+    /// context the thread is blocked
+    pub fn recv(&mut self) -> io::Result<libc::c_int> {
+        if let Some(sig) = self.pending.pop_front() {
+            return Ok(sig);
+        }
+
+        let mut buf = [0u8; 64];
+        loop {
+            match self.rx.read(&mut buf) {
+                Ok(0) => {
+                    return Err(io::Error::new(
+                        ErrorKind::UnexpectedEof,
+                        "signal pipe closed",
+                    ))
+                }
+                Ok(n) => {
+                    self.pending
+                        .extend(buf[1..n].iter().map(|&b| b as libc::c_int));
+                    return Ok(buf[0] as libc::c_int);
+                }
+                Err(ref e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// wait for the next signal with a timeout
+    ///
+    /// return `Ok(None)` if no signal arrives before the timeout, the timeout
+    /// only takes effect in coroutine context
+    pub fn recv_timeout(&mut self, dur: Duration) -> io::Result<Option<libc::c_int>> {
+        self.rx.set_read_timeout(Some(dur))?;
+        let ret = self.recv();
+        self.rx.set_read_timeout(None)?;
+        match ret {
+            Ok(sig) => Ok(Some(sig)),
+            Err(ref e) if e.kind() == ErrorKind::TimedOut || e.kind() == ErrorKind::WouldBlock => {
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// return an iterator that waits for the signals forever
+    pub fn forever(&mut self) -> Forever<'_> {
+        Forever { signals: self }
+    }
+}
+
+impl Drop for Signals {
+    fn drop(&mut self) {
+        SLOT_MASKS[self.slot].store(0, Ordering::SeqCst);
+        SLOT_FDS[self.slot].store(-1, Ordering::SeqCst);
+        // wait the running handlers finish before closing the write end
+        while IN_HANDLER.load(Ordering::SeqCst) != 0 {
+            ::std::hint::spin_loop();
+        }
+    }
+}
+
+/// An infinite iterator over the received signals
+///
+/// created by [`Signals::forever`](struct.Signals.html#method.forever)
+#[derive(Debug)]
+pub struct Forever<'a> {
+    signals: &'a mut Signals,
+}
+
+impl<'a> Iterator for Forever<'a> {
+    type Item = libc::c_int;
+
+    fn next(&mut self) -> Option<libc::c_int> {
+        self.signals.recv().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recv_signal() {
+        let h = go!(|| {
+            let mut signals = Signals::new(&[SIGUSR1, SIGUSR2]).unwrap();
+            unsafe { libc::raise(SIGUSR2) };
+            assert_eq!(signals.recv().unwrap(), SIGUSR2);
+            let ret = signals.recv_timeout(Duration::from_millis(50)).unwrap();
+            assert_eq!(ret, None);
+        });
+        h.join().unwrap();
+    }
+
+    #[test]
+    fn forbidden_signal() {
+        assert!(Signals::new(&[libc::SIGKILL]).is_err());
+
+        // the valid signal keeps its default action when creating failed
+        assert!(Signals::new(&[SIGWINCH, libc::SIGKILL]).is_err());
+        assert_eq!(INSTALLED.load(Ordering::Acquire) & (1 << SIGWINCH), 0);
+        let action = unsafe {
+            let mut action: libc::sigaction = mem::zeroed();
+            libc::sigaction(SIGWINCH, ptr::null(), &mut action);
+            action
+        };
+        assert_eq!(action.sa_sigaction, libc::SIG_DFL);
+    }
+}
@@ -5,6 +5,7 @@ use std::time::Duration;
 
 use crate::coroutine_impl::{co_cancel_data, is_coroutine, CoroutineImpl, EventSource};
 use crate::scheduler::get_scheduler;
+use crate::sync::{Blocker, CancellationToken, Cancelled};
 use crate::yield_now::{get_co_para, yield_with};
 
 struct Sleep {
@@ -39,3 +40,15 @@ pub fn sleep(dur: Duration) {
     // consume the timeout error
     get_co_para();
 }
+
+/// block the current coroutine until timeout or the token is cancelled
+///
+/// return `Err(Cancelled)` if the token is cancelled before the timeout
+pub fn sleep_or_cancel(dur: Duration, token: &CancellationToken) -> Result<(), Cancelled> {
+    let cur = Blocker::current();
+    let _waker = token.wake_blocker(&cur);
+    if token.is_cancelled() || cur.park(Some(dur)).is_ok() {
+        return Err(Cancelled(()));
+    }
+    Ok(())
+}
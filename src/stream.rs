@@ -0,0 +1,207 @@
+use std::fmt;
+use std::panic;
+use std::sync::Arc;
+
+use crate::coroutine_impl::spawn;
+use crate::join::JoinHandle;
+use crate::sync::mpsc::{channel, Receiver, Sender};
+use crate::sync::Semphore;
+
+/// The producer half of a [`stream`], used to yield items to the consumer
+///
+/// [`stream`]: fn.stream.html
+pub struct StreamSender<T> {
+    tx: Sender<T>,
+    // the free slots of the handoff buffer
+    slots: Arc<Semphore>,
+}
+
+impl<T> StreamSender<T> {
+    /// yield an item to the consumer
+    ///
+    /// it blocks the producer until the consumer takes the previous item.
+    /// if the consumer drops the stream, the producer is cancelled at its
+    /// next blocking point.
+    pub fn yield_(&self, item: T) {
+        self.slots.wait();
+        // the consumer is gone, the producer would be cancelled
+        self.tx.send(item).ok();
+    }
+}
+
+impl<T> fmt::Debug for StreamSender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad("StreamSender { .. }")
+    }
+}
+
+/// An iterator over the items yielded by the producer of a [`stream`]
+///
+/// dropping the stream before it's exhausted cancels the producer.
+///
+/// [`stream`]: fn.stream.html
+pub struct Stream<T> {
+    rx: Receiver<T>,
+    slots: Arc<Semphore>,
+    producer: Option<JoinHandle<()>>,
+}
+
+impl<T> Iterator for Stream<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        match self.rx.recv() {
+            Ok(item) => {
+                // let the producer continue
+                self.slots.post();
+                Some(item)
+            }
+            Err(_) => {
+                // the producer is done, propagate its panic
+                if let Some(Err(panic)) = self.producer.take().map(|h| h.join()) {
+                    panic::resume_unwind(panic);
+                }
+                None
+            }
+        }
+    }
+}
+
+impl<T> Drop for Stream<T> {
+    fn drop(&mut self) {
+        if let Some(h) = self.producer.take() {
+            if !h.is_done() {
+                unsafe { h.coroutine().cancel() };
+            }
+        }
+    }
+}
+
+impl<T> fmt::Debug for Stream<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad("Stream { .. }")
+    }
+}
+
+/// Run the producer in a new coroutine, return the yielded items as an iterator
+///
+/// the producer yields the items by [`StreamSender::yield_`], and it's
+/// blocked until the consumer takes the item from the iterator, so at most
+/// one item is buffered. the producer can block on any coroutine API between
+/// the yields, e.g. fetching a page from the network, which makes it easy to
+/// turn the paged results into a plain iterator.
+///
+/// the iterator ends when the producer returns. if the producer panics, the
+/// panic is propagated to the consumer after the yielded items.
+///
+/// # Safety
+///
+/// same as [`spawn`]
+///
+/// # Examples
+///
+/// ```rust
+/// use may::coroutine;
+/// use std::time::Duration;
+///
+/// let pages = unsafe {
+///     coroutine::stream(|tx| {
+///         for page in 0..3 {
+///             // simulate a network request
+///             coroutine::sleep(Duration::from_millis(10));
+///             tx.yield_(vec![page * 2, page * 2 + 1]);
+///         }
+///     })
+/// };
+///
+/// let items: Vec<_> = pages.flatten().collect();
+/// assert_eq!(items, [0, 1, 2, 3, 4, 5]);
+/// ```
+///
+/// [`StreamSender::yield_`]: struct.StreamSender.html#method.yield_
+/// [`spawn`]: fn.spawn.html
+pub unsafe fn stream<F, T>(f: F) -> Stream<T>
+where
+    F: FnOnce(StreamSender<T>) + Send + 'static,
+    T: Send + 'static,
+{
+    let (tx, rx) = channel();
+    let slots = Arc::new(Semphore::new(1));
+    let sender = StreamSender {
+        tx,
+        slots: slots.clone(),
+    };
+    let producer = spawn(move || f(sender));
+    Stream {
+        rx,
+        slots,
+        producer: Some(producer),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sleep::sleep;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[test]
+    fn stream_bounded() {
+        let produced = Arc::new(AtomicUsize::new(0));
+        let p = produced.clone();
+        let mut s = unsafe {
+            stream(move |tx| {
+                for i in 0..10 {
+                    p.fetch_add(1, Ordering::SeqCst);
+                    tx.yield_(i);
+                }
+            })
+        };
+
+        assert_eq!(s.next(), Some(0));
+        sleep(Duration::from_millis(50));
+        // the producer is blocked by the unconsumed item
+        assert_eq!(produced.load(Ordering::SeqCst), 3);
+        assert_eq!(s.collect::<Vec<_>>(), (1..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn stream_drop_cancel() {
+        struct Finished(Arc<AtomicUsize>);
+        impl Drop for Finished {
+            fn drop(&mut self) {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let finished = Arc::new(AtomicUsize::new(0));
+        let f = Finished(finished.clone());
+        let s = unsafe {
+            stream(move |tx| {
+                let _f = f;
+                let mut i = 0;
+                loop {
+                    tx.yield_(i);
+                    i += 1;
+                }
+            })
+        };
+
+        assert_eq!(s.take(5).sum::<u64>(), 10);
+        sleep(Duration::from_millis(50));
+        assert_eq!(finished.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "producer failed")]
+    fn stream_panic() {
+        let s = unsafe {
+            stream(|tx| {
+                tx.yield_(1);
+                panic!("producer failed");
+            })
+        };
+        assert_eq!(s.sum::<u32>(), 1);
+    }
+}
@@ -174,7 +174,16 @@ impl SyncBlocker {
 
     #[inline]
     pub fn unpark(&self) {
-        self.blocker.unpark();
+        // set the flag before waking up the waiter, or the waiter may
+        // come back without seeing it and take it as an interrupt
         self.unparked.store(true, Ordering::Release);
+        self.blocker.unpark();
+    }
+
+    // wake up the blocker without setting the unparked flag
+    // the waiter would deal with it like a timeout
+    #[inline]
+    pub fn interrupt(&self) {
+        self.blocker.unpark();
     }
 }
@@ -0,0 +1,543 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::RecvError;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+
+use super::blocking::Blocker;
+
+type Waker = Box<dyn Fn() + Send + Sync>;
+
+struct State {
+    // the wakers that are called when cancelled
+    wakers: Vec<(usize, Waker)>,
+    next_id: usize,
+    // the child tokens that are cancelled together
+    children: Vec<Weak<Inner>>,
+}
+
+struct Inner {
+    cancelled: AtomicBool,
+    state: Mutex<State>,
+}
+
+impl Inner {
+    fn new(cancelled: bool) -> Self {
+        Inner {
+            cancelled: AtomicBool::new(cancelled),
+            state: Mutex::new(State {
+                wakers: Vec::new(),
+                next_id: 0,
+                children: Vec::new(),
+            }),
+        }
+    }
+
+    // cancel the token, return the children that need to be cancelled
+    fn cancel(&self) -> Vec<Weak<Inner>> {
+        if self.cancelled.swap(true, Ordering::AcqRel) {
+            return Vec::new();
+        }
+
+        let (wakers, children) = {
+            let mut state = self.state.lock().unwrap();
+            let wakers = std::mem::take(&mut state.wakers);
+            let children = std::mem::take(&mut state.children);
+            (wakers, children)
+        };
+
+        // wake up the waiters outside the lock
+        for (_, waker) in wakers {
+            waker();
+        }
+        children
+    }
+}
+
+/// A token for cooperative cancellation
+///
+/// unlike `Coroutine::cancel` that unwinds the coroutine at the next blocking
+/// point, a cancellation token only sets a flag and wakes up the waiters. the
+/// code that holds the token decides when and how to stop, e.g. checking
+/// [`is_cancelled`] in a loop, or calling the `*_or_cancel` variants of the
+/// blocking APIs which return an error when the token is cancelled.
+///
+/// the tokens are hierarchical, cancelling a token cancels all the child
+/// tokens that are created by [`child_token`], but cancelling a child token
+/// would not affect its parent. the clones of a token share the same state.
+///
+/// the token works in both coroutine and thread context.
+///
+/// [`is_cancelled`]: struct.CancellationToken.html#method.is_cancelled
+/// [`child_token`]: struct.CancellationToken.html#method.child_token
+///
+/// # Examples
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate may;
+///
+/// use may::sync::mpsc::channel;
+/// use may::sync::{CancellationToken, RecvCancelError};
+///
+/// fn main() {
+///     let token = CancellationToken::new();
+///     let (tx, rx) = channel::<u32>();
+///
+///     let child = token.child_token();
+///     let h = go!(move || {
+///         let mut sum = 0;
+///         loop {
+///             match rx.recv_or_cancel(&child) {
+///                 Ok(v) => sum += v,
+///                 Err(RecvCancelError::Cancelled) => return sum,
+///                 Err(RecvCancelError::Disconnected) => unreachable!(),
+///             }
+///         }
+///     });
+///
+///     tx.send(1).unwrap();
+///     tx.send(2).unwrap();
+///     # may::coroutine::sleep(std::time::Duration::from_millis(10));
+///     token.cancel();
+///     assert_eq!(h.join().unwrap(), 3);
+/// }
+/// ```
+#[derive(Clone)]
+pub struct CancellationToken {
+    inner: Arc<Inner>,
+}
+
+impl Default for CancellationToken {
+    fn default() -> Self {
+        CancellationToken::new()
+    }
+}
+
+impl CancellationToken {
+    /// create a new token that is not cancelled
+    pub fn new() -> Self {
+        CancellationToken {
+            inner: Arc::new(Inner::new(false)),
+        }
+    }
+
+    /// create a child token that is cancelled when this token is cancelled
+    ///
+    /// the child token is already cancelled if this token is cancelled.
+    pub fn child_token(&self) -> CancellationToken {
+        let child = Arc::new(Inner::new(false));
+        {
+            let mut state = self.inner.state.lock().unwrap();
+            // check it in the lock, so that `cancel` would not miss the child
+            if self.is_cancelled() {
+                child.cancelled.store(true, Ordering::Release);
+            } else {
+                // drop the released children before the vec grows
+                if state.children.len() == state.children.capacity() {
+                    state.children.retain(|c| c.strong_count() > 0);
+                }
+                state.children.push(Arc::downgrade(&child));
+            }
+        }
+        CancellationToken { inner: child }
+    }
+
+    /// cancel the token and all its child tokens
+    ///
+    /// all the waiters on the tokens are woken up. it's fine to call it
+    /// more than once, only the first call takes effect.
+    pub fn cancel(&self) {
+        // cancel the children with a loop instead of recursion
+        let mut pending = self.inner.cancel();
+        while let Some(child) = pending.pop() {
+            if let Some(child) = child.upgrade() {
+                pending.extend(child.cancel());
+            }
+        }
+    }
+
+    /// return true if the token is cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::Acquire)
+    }
+
+    /// block the current coroutine or thread until the token is cancelled
+    pub fn cancelled(&self) {
+        self.cancelled_impl(None);
+    }
+
+    /// same as `cancelled` except that with an extra timeout value
+    ///
+    /// return false if timeout happened
+    pub fn cancelled_timeout(&self, dur: Duration) -> bool {
+        self.cancelled_impl(Some(dur))
+    }
+
+    fn cancelled_impl(&self, dur: Option<Duration>) -> bool {
+        if self.is_cancelled() {
+            return true;
+        }
+
+        let cur = Blocker::current();
+        let _waker = self.wake_blocker(&cur);
+        while !self.is_cancelled() {
+            if cur.park(dur).is_err() {
+                break;
+            }
+        }
+        self.is_cancelled()
+    }
+
+    // register the blocker that would be unparked when the token is cancelled
+    pub(crate) fn wake_blocker(&self, blocker: &Arc<Blocker>) -> WakerGuard<'_> {
+        let blocker = blocker.clone();
+        self.register(Box::new(move || blocker.unpark()))
+    }
+
+    // register the waker that would be called when the token is cancelled,
+    // it's called immediately if the token is already cancelled
+    //
+    // the waker is removed when the returned guard is dropped
+    pub(crate) fn register(&self, waker: Waker) -> WakerGuard<'_> {
+        let mut state = self.inner.state.lock().unwrap();
+        // check it in the lock, so that `cancel` would not miss the waker
+        if self.is_cancelled() {
+            drop(state);
+            waker();
+            return WakerGuard {
+                token: self,
+                id: None,
+            };
+        }
+
+        let id = state.next_id;
+        state.next_id = id.wrapping_add(1);
+        state.wakers.push((id, waker));
+        WakerGuard {
+            token: self,
+            id: Some(id),
+        }
+    }
+}
+
+impl fmt::Debug for CancellationToken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CancellationToken")
+            .field("is_cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
+
+// remove the registered waker from the token when dropped
+pub(crate) struct WakerGuard<'a> {
+    token: &'a CancellationToken,
+    id: Option<usize>,
+}
+
+impl<'a> Drop for WakerGuard<'a> {
+    fn drop(&mut self) {
+        if let Some(id) = self.id {
+            let mut state = self.token.inner.state.lock().unwrap();
+            if let Some(i) = state.wakers.iter().position(|w| w.0 == id) {
+                drop(state.wakers.swap_remove(i));
+            }
+        }
+    }
+}
+
+/// The error returned by the `*_or_cancel` APIs when the token is cancelled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled(pub(crate) ());
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        "operation cancelled".fmt(f)
+    }
+}
+
+impl Error for Cancelled {}
+
+/// the io error is of `ErrorKind::Other` with the `Cancelled` as the payload,
+/// rather than `ErrorKind::Interrupted` which is usually retried by the io
+/// helpers like `read_exact`
+impl From<Cancelled> for io::Error {
+    fn from(e: Cancelled) -> io::Error {
+        io::Error::new(io::ErrorKind::Other, e)
+    }
+}
+
+/// The error returned by the `recv_or_cancel` of the channels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecvCancelError {
+    /// the token is cancelled before any data is received
+    Cancelled,
+    /// the channel is empty and all the senders are dropped
+    Disconnected,
+}
+
+impl fmt::Display for RecvCancelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RecvCancelError::Cancelled => "receiving on a cancelled token".fmt(f),
+            RecvCancelError::Disconnected => "channel is empty and sending half is closed".fmt(f),
+        }
+    }
+}
+
+impl Error for RecvCancelError {}
+
+impl From<RecvError> for RecvCancelError {
+    fn from(_: RecvError) -> RecvCancelError {
+        RecvCancelError::Disconnected
+    }
+}
+
+impl From<Cancelled> for RecvCancelError {
+    fn from(_: Cancelled) -> RecvCancelError {
+        RecvCancelError::Cancelled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sleep::sleep;
+    use crate::sync::{mpmc, mpsc, Semphore};
+    use std::thread;
+
+    #[test]
+    fn token_hierarchy() {
+        let root = CancellationToken::new();
+        let child = root.child_token();
+        let grandchild = child.child_token();
+        let sibling = root.child_token();
+
+        // cancel a child would not affect the parent
+        sibling.cancel();
+        assert!(sibling.is_cancelled());
+        assert!(!root.is_cancelled());
+        assert!(!grandchild.is_cancelled());
+
+        root.cancel();
+        assert!(child.is_cancelled());
+        assert!(grandchild.is_cancelled());
+        // the clone shares the state
+        assert!(root.clone().is_cancelled());
+        // the child of a cancelled token is cancelled
+        assert!(root.child_token().is_cancelled());
+    }
+
+    #[test]
+    fn token_cancelled_wait() {
+        let token = CancellationToken::new();
+        let child = token.child_token();
+        let t = token.clone();
+        let h1 = go!(move || child.cancelled());
+        let h2 = thread::spawn(move || t.cancelled());
+
+        sleep(Duration::from_millis(50));
+        assert!(!token.cancelled_timeout(Duration::from_millis(10)));
+        token.cancel();
+        h1.join().unwrap();
+        h2.join().unwrap();
+        assert!(token.cancelled_timeout(Duration::from_millis(10)));
+    }
+
+    #[test]
+    fn sleep_or_cancel() {
+        use crate::sleep::sleep_or_cancel;
+
+        let token = CancellationToken::new();
+        let t = token.clone();
+        let h = go!(move || {
+            assert_eq!(sleep_or_cancel(Duration::from_millis(10), &t), Ok(()));
+            sleep_or_cancel(Duration::from_secs(10), &t)
+        });
+
+        sleep(Duration::from_millis(50));
+        token.cancel();
+        assert_eq!(h.join().unwrap(), Err(Cancelled(())));
+    }
+
+    #[test]
+    fn recv_or_cancel() {
+        let token = CancellationToken::new();
+        let (tx, rx) = mpsc::channel();
+        let (tx1, rx1) = mpmc::channel();
+        let (ack_tx, ack_rx) = mpsc::channel();
+        let (t, rx2) = (token.clone(), rx1.clone());
+        let h = go!(move || {
+            assert_eq!(rx.recv_or_cancel(&t), Ok(1));
+            assert_eq!(rx2.recv_or_cancel(&t), Ok(2));
+            ack_tx.send(()).unwrap();
+            let r1 = rx.recv_or_cancel(&t);
+            let r2 = rx2.recv_or_cancel(&t);
+            (r1, r2)
+        });
+
+        tx.send(1).unwrap();
+        tx1.send(2).unwrap();
+        // cancel only after the queued data is received
+        ack_rx.recv().unwrap();
+        sleep(Duration::from_millis(50));
+        token.cancel();
+        let (r1, r2) = h.join().unwrap();
+        assert_eq!(r1, Err(RecvCancelError::Cancelled));
+        assert_eq!(r2, Err(RecvCancelError::Cancelled));
+
+        // the channels are still usable after the cancel
+        tx1.send(3).unwrap();
+        assert_eq!(rx1.recv_timeout(Duration::from_millis(10)), Ok(3));
+    }
+
+    #[test]
+    fn semphore_wait_or_cancel() {
+        let sem = Arc::new(Semphore::new(0));
+        let token = CancellationToken::new();
+        let (s, t) = (sem.clone(), token.clone());
+        let h = go!(move || s.wait_or_cancel(&t));
+
+        sleep(Duration::from_millis(50));
+        token.cancel();
+        assert_eq!(h.join().unwrap(), Err(Cancelled(())));
+
+        // the cancelled waiter doesn't take the resource
+        sem.post();
+        assert_eq!(sem.get_value(), 1);
+        assert_eq!(sem.wait_or_cancel(&CancellationToken::new()), Ok(()));
+    }
+
+    #[test]
+    fn mutex_lock_or_cancel() {
+        let mutex = Arc::new(crate::sync::Mutex::new(0));
+        let token = CancellationToken::new();
+        let guard = mutex.lock().unwrap();
+        let (m, t) = (mutex.clone(), token.clone());
+        let h = go!(move || m.lock_or_cancel(&t).is_err());
+
+        sleep(Duration::from_millis(50));
+        token.cancel();
+        assert!(h.join().unwrap());
+
+        // the cancelled waiter doesn't take the lock
+        drop(guard);
+        *mutex.lock_or_cancel(&token).unwrap().unwrap() += 1;
+        assert_eq!(*mutex.lock().unwrap(), 1);
+    }
+
+    #[test]
+    fn condvar_wait_or_cancel() {
+        let pair = Arc::new((crate::sync::Mutex::new(false), crate::sync::Condvar::new()));
+        let token = CancellationToken::new();
+        let (p, t) = (pair.clone(), token.clone());
+        let h = go!(move || {
+            let (lock, cvar) = &*p;
+            let guard = lock.lock().unwrap();
+            let (guard, ret) = cvar.wait_or_cancel(guard, &t).unwrap();
+            // the mutex is locked again
+            assert!(!*guard);
+            ret
+        });
+
+        sleep(Duration::from_millis(50));
+        token.cancel();
+        assert_eq!(h.join().unwrap(), Err(Cancelled(())));
+        *pair.0.lock().unwrap() = true;
+    }
+
+    #[test]
+    fn join_or_cancel() {
+        let token = CancellationToken::new();
+        let h = go!(|| sleep(Duration::from_millis(200)));
+        let t = token.clone();
+        let j = go!(move || h.join_or_cancel(&t).err());
+
+        sleep(Duration::from_millis(50));
+        token.cancel();
+        // the handle is returned back
+        let h = j.join().unwrap().unwrap();
+        h.join().unwrap();
+    }
+
+    #[test]
+    fn park_or_cancel() {
+        use crate::coroutine::park_or_cancel;
+
+        let token = CancellationToken::new();
+        let t = token.clone();
+        let h = go!(move || park_or_cancel(&t));
+
+        sleep(Duration::from_millis(50));
+        token.cancel();
+        assert_eq!(h.join().unwrap(), Err(Cancelled(())));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn io_or_cancel() {
+        use crate::net::{TcpListener, TcpStream};
+
+        let listener = Arc::new(TcpListener::bind("127.0.0.1:0").unwrap());
+        let addr = listener.local_addr().unwrap();
+        let token = CancellationToken::new();
+        let (l, t) = (listener.clone(), token.clone());
+        let h = go!(move || {
+            let (s, _) = l.accept_or_cancel(&t).unwrap();
+            let mut buf = [0u8; 8];
+            assert_eq!(s.read_or_cancel(&mut buf, &t).unwrap(), 2);
+            let err = s.read_or_cancel(&mut buf, &t).unwrap_err();
+            // the token is already cancelled
+            let err1 = l.accept_or_cancel(&t).map(|_| ()).unwrap_err();
+            (err, err1)
+        });
+
+        let c = TcpStream::connect(addr).unwrap();
+        assert_eq!(c.write_or_cancel(b"hi", &token).unwrap(), 2);
+        sleep(Duration::from_millis(50));
+        token.cancel();
+        let (err, err1) = h.join().unwrap();
+        for e in [err, err1].iter() {
+            let inner = e.get_ref().and_then(|e| e.downcast_ref::<Cancelled>());
+            assert_eq!(inner, Some(&Cancelled(())));
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn io_cancel_from_thread() {
+        use crate::net::{TcpListener, TcpStream};
+        use crate::scheduler::worker_id;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let token = CancellationToken::new();
+        let t = token.clone();
+        let h = go!(move || {
+            let s = TcpStream::connect(addr).unwrap();
+            let mut buf = [0u8; 8];
+            let err = s.read_or_cancel(&mut buf, &t).unwrap_err();
+            // resumed on a worker instead of the cancelling thread
+            (err.kind(), worker_id())
+        });
+
+        let _peer = listener.accept().unwrap();
+        sleep(Duration::from_millis(50));
+        // cancel from a thread that is not a worker
+        thread::spawn(move || token.cancel()).join().unwrap();
+        let (kind, id) = h.join().unwrap();
+        assert_eq!(kind, io::ErrorKind::Other);
+        assert_ne!(id, !1);
+    }
+
+    #[test]
+    fn cancelled_io_error() {
+        let err = io::Error::from(Cancelled(()));
+        // not retried by the io helpers
+        assert_eq!(err.kind(), io::ErrorKind::Other);
+        let inner = err.get_ref().and_then(|e| e.downcast_ref::<Cancelled>());
+        assert_eq!(inner, Some(&Cancelled(())));
+    }
+}
@@ -10,6 +10,7 @@ use crate::park::ParkError;
 use may_queue::spsc;
 
 use super::blocking::SyncBlocker;
+use super::cancellation::{CancellationToken, Cancelled};
 use super::mutex::{self, Mutex, MutexGuard};
 
 /// A type indicating whether a timed wait on a condition variable returned
@@ -41,6 +42,16 @@ impl Condvar {
 
     // return false if timeout happened
     pub fn wait_impl<T>(&self, lock: &Mutex<T>, dur: Option<Duration>) -> Result<(), ParkError> {
+        self.wait_token_impl(lock, dur, None)
+    }
+
+    // the cancelled token is reported as `ParkError::Timeout`
+    fn wait_token_impl<T>(
+        &self,
+        lock: &Mutex<T>,
+        dur: Option<Duration>,
+        token: Option<&CancellationToken>,
+    ) -> Result<(), ParkError> {
         let cancel = if crate::coroutine_impl::is_coroutine() {
             Some(crate::coroutine_impl::current_cancel_data())
         } else {
@@ -64,8 +75,19 @@ impl Condvar {
             c.enable_cancel();
         }
 
+        let waker = token.map(|t| {
+            let blocker = cur.clone();
+            t.register(Box::new(move || blocker.interrupt()))
+        });
+
         // wait until coming back
-        let ret = cur.park(dur);
+        // the token would wake us up without setting the unparked flag
+        let ret = match cur.park(dur) {
+            Ok(_) if token.is_none() || cur.is_unparked() => Ok(()),
+            Ok(_) => Err(ParkError::Timeout),
+            Err(err) => Err(err),
+        };
+        drop(waker);
         // disable cancel panic
         if let Some(c) = cancel.as_ref() {
             c.disable_cancel();
@@ -153,6 +175,39 @@ impl Condvar {
         }
     }
 
+    /// same as `wait` except that it also returns when the token is cancelled
+    ///
+    /// the mutex is always re-acquired before returning, the second element
+    /// is `Err(Cancelled)` if the wait is ended by the token
+    pub fn wait_or_cancel<'a, T>(
+        &self,
+        guard: MutexGuard<'a, T>,
+        token: &CancellationToken,
+    ) -> LockResult<(MutexGuard<'a, T>, Result<(), Cancelled>)> {
+        let (poisoned, result) = {
+            let lock = mutex::guard_lock(&guard);
+            self.verify(lock as *const _ as usize);
+            let ret = self.wait_token_impl(lock, None, Some(token));
+            if ret == Err(ParkError::Canceled) {
+                // don't set the poison flag
+                ::std::mem::forget(guard);
+                // release the mutex to let other run
+                mutex::unlock_mutex(lock);
+                // now we can safely go with the cancel panic
+                trigger_cancel_panic();
+            }
+            (
+                mutex::guard_poison(&guard).get(),
+                ret.map_err(|_| Cancelled(())),
+            )
+        };
+        if poisoned {
+            Err(PoisonError::new((guard, result)))
+        } else {
+            Ok((guard, result))
+        }
+    }
+
     pub fn notify_one(&self) {
         // NOTICE: the following code would not drop the lock!
         // if let Some(w) = self.to_wake.lock().unwrap().pop() {
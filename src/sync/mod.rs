@@ -1,5 +1,6 @@
 mod atomic_option;
 mod blocking;
+mod cancellation;
 mod condvar;
 mod mutex;
 mod poison;
@@ -14,6 +15,7 @@ pub mod mpmc;
 pub mod mpsc;
 pub use self::atomic_option::AtomicOption;
 pub use self::blocking::{Blocker, FastBlocker};
+pub use self::cancellation::{CancellationToken, Cancelled, RecvCancelError};
 pub use self::condvar::{Condvar, WaitTimeoutResult};
 pub use self::mutex::{Mutex, MutexGuard};
 pub use self::rwlock::{RwLock, RwLockReadGuard, RwLockWriteGuard};
@@ -11,7 +11,7 @@ use std::sync::mpsc::{RecvError, RecvTimeoutError, SendError, TryRecvError};
 use std::sync::Arc;
 use std::time::Duration;
 
-use super::Semphore;
+use super::{CancellationToken, RecvCancelError, Semphore};
 use crossbeam::queue::SegQueue;
 
 /// /////////////////////////////////////////////////////////////////////////////
@@ -72,6 +72,24 @@ impl<T> InnerQueue<T> {
         }
     }
 
+    pub fn recv_or_cancel(&self, token: &CancellationToken) -> Result<T, RecvCancelError> {
+        match self.try_recv() {
+            Ok(data) => return Ok(data),
+            Err(TryRecvError::Empty) => {}
+            Err(TryRecvError::Disconnected) => return Err(RecvCancelError::Disconnected),
+        }
+
+        self.sem.wait_or_cancel(token)?;
+
+        match self.queue.pop() {
+            Some(data) => Ok(data),
+            None => match self.tx_ports.load(Ordering::Acquire) {
+                0 => Err(RecvCancelError::Disconnected),
+                _n => unreachable!("mpmc recv found no data"),
+            },
+        }
+    }
+
     pub fn try_recv(&self) -> Result<T, TryRecvError> {
         if !self.sem.try_wait() {
             return match self.tx_ports.load(Ordering::Acquire) {
@@ -223,6 +241,12 @@ impl<T> Receiver<T> {
         self.inner.recv(Some(timeout))
     }
 
+    /// same as `recv` except that it returns `Err(RecvCancelError::Cancelled)`
+    /// when the token is cancelled before any data is received
+    pub fn recv_or_cancel(&self, token: &CancellationToken) -> Result<T, RecvCancelError> {
+        self.inner.recv_or_cancel(token)
+    }
+
     pub fn iter(&self) -> Iter<T> {
         Iter { rx: self }
     }
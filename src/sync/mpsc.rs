@@ -5,9 +5,10 @@ use std::panic::{RefUnwindSafe, UnwindSafe};
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::mpsc::{RecvError, RecvTimeoutError, SendError, TryRecvError};
 use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
-use super::{AtomicOption, Blocker};
+use super::{AtomicOption, Blocker, CancellationToken, RecvCancelError};
+use crate::time;
 use may_queue::mpsc_list::Queue as WaitList;
 // TODO: SyncSender
 /// /////////////////////////////////////////////////////////////////////////////
@@ -44,7 +45,11 @@ impl<T> InnerQueue<T> {
         Ok(())
     }
 
-    pub fn recv(&self, dur: Option<Duration>) -> Result<T, TryRecvError> {
+    pub fn recv(
+        &self,
+        dur: Option<Duration>,
+        token: Option<&CancellationToken>,
+    ) -> Result<T, TryRecvError> {
         match self.try_recv() {
             Err(TryRecvError::Empty) => {}
             data => return data,
@@ -53,6 +58,7 @@ impl<T> InnerQueue<T> {
         let cur = Blocker::current();
         // register the waiter
         self.to_wake.swap(cur.clone(), Ordering::Release);
+        let _waker = token.map(|t| t.wake_blocker(&cur));
         // re-check the queue
         match self.try_recv() {
             Err(TryRecvError::Empty) => {
@@ -196,13 +202,28 @@ impl<T> Receiver<T> {
 
     pub fn recv(&self) -> Result<T, RecvError> {
         loop {
-            match self.inner.recv(None) {
+            match self.inner.recv(None, None) {
                 Err(TryRecvError::Empty) => {}
                 data => return data.map_err(|_| RecvError),
             }
         }
     }
 
+    /// same as `recv` except that it returns `Err(RecvCancelError::Cancelled)`
+    /// when the token is cancelled before any data is received
+    pub fn recv_or_cancel(&self, token: &CancellationToken) -> Result<T, RecvCancelError> {
+        loop {
+            match self.inner.recv(None, Some(token)) {
+                Err(TryRecvError::Empty) => {}
+                data => return data.map_err(|_| RecvCancelError::Disconnected),
+            }
+
+            if token.is_cancelled() {
+                return Err(RecvCancelError::Cancelled);
+            }
+        }
+    }
+
     pub fn recv_timeout(&self, timeout: Duration) -> Result<T, RecvTimeoutError> {
         // Do an optimistic try_recv to avoid the performance impact of
         // Instant::now() in the full-channel case.
@@ -214,9 +235,9 @@ impl<T> Receiver<T> {
     }
 
     fn recv_max_until(&self, timeout: Duration) -> Result<T, RecvTimeoutError> {
-        let deadline = Instant::now() + timeout;
+        let deadline = time::blocking_now() + timeout;
         loop {
-            match self.inner.recv(Some(timeout)) {
+            match self.inner.recv(Some(timeout), None) {
                 Ok(t) => return Ok(t),
                 Err(TryRecvError::Disconnected) => return Err(RecvTimeoutError::Disconnected),
                 Err(TryRecvError::Empty) => {}
@@ -224,7 +245,7 @@ impl<T> Receiver<T> {
 
             // If we're already passed the deadline, and we're here without
             // data, return a timeout, else try again.
-            if Instant::now() >= deadline {
+            if time::blocking_now() >= deadline {
                 return Err(RecvTimeoutError::Timeout);
             }
         }
@@ -9,6 +9,7 @@ use std::sync::Arc;
 use std::sync::{LockResult, TryLockError, TryLockResult};
 
 use super::blocking::SyncBlocker;
+use super::cancellation::{CancellationToken, Cancelled};
 use super::poison;
 use crate::cancel::trigger_cancel_panic;
 use crate::park::ParkError;
@@ -51,11 +52,30 @@ impl<T> Mutex<T> {
 
 impl<T: ?Sized> Mutex<T> {
     pub fn lock(&self) -> LockResult<MutexGuard<T>> {
+        match self.lock_impl(None) {
+            Ok(ret) => ret,
+            Err(_) => unreachable!("mutex lock cancelled"),
+        }
+    }
+
+    /// same as `lock` except that it returns `Err(Cancelled)` when
+    /// the token is cancelled before acquiring the mutex
+    pub fn lock_or_cancel(
+        &self,
+        token: &CancellationToken,
+    ) -> Result<LockResult<MutexGuard<T>>, Cancelled> {
+        self.lock_impl(Some(token))
+    }
+
+    fn lock_impl(
+        &self,
+        token: Option<&CancellationToken>,
+    ) -> Result<LockResult<MutexGuard<T>>, Cancelled> {
         // try lock first
         match self.try_lock() {
-            Ok(g) => return Ok(g),
+            Ok(g) => return Ok(Ok(g)),
             Err(TryLockError::WouldBlock) => {}
-            Err(TryLockError::Poisoned(e)) => return Err(e),
+            Err(TryLockError::Poisoned(e)) => return Ok(Err(e)),
         }
 
         let cur = SyncBlocker::current();
@@ -68,11 +88,27 @@ impl<T: ?Sized> Mutex<T> {
                 .map(|w| self.unpark_one(&w))
                 .expect("got null blocker!");
         }
+
+        let _waker = token.map(|t| {
+            let blocker = cur.clone();
+            t.register(Box::new(move || blocker.interrupt()))
+        });
+
         loop {
             match cur.park(None) {
-                Ok(_) => {
+                // the token would wake us up without setting the unparked flag
+                Ok(_) if token.is_none() || cur.is_unparked() => {
                     break;
                 }
+                Ok(_) => {
+                    // register, the unlocker would pass the lock on
+                    cur.set_release();
+                    // re-check unpark status
+                    if cur.is_unparked() && cur.take_release() {
+                        break;
+                    }
+                    return Err(Cancelled(()));
+                }
                 Err(ParkError::Timeout) => unreachable!("mutext timeout"),
                 Err(ParkError::Canceled) => {
                     let b_ignore = if crate::coroutine_impl::is_coroutine() {
@@ -109,7 +145,7 @@ impl<T: ?Sized> Mutex<T> {
             }
         }
 
-        MutexGuard::new(self)
+        Ok(MutexGuard::new(self))
     }
 
     pub fn try_lock(&self) -> TryLockResult<MutexGuard<T>> {
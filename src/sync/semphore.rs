@@ -4,6 +4,7 @@ use std::sync::Arc;
 use std::time::Duration;
 
 use super::blocking::SyncBlocker;
+use super::cancellation::{CancellationToken, Cancelled};
 use crate::cancel::trigger_cancel_panic;
 use crate::park::ParkError;
 use crossbeam::queue::SegQueue as WaitList;
@@ -70,8 +71,8 @@ impl Semphore {
             .expect("got null blocker!");
     }
 
-    // return false if timeout
-    fn wait_timeout_impl(&self, dur: Option<Duration>) -> bool {
+    // return false if timeout or the token is cancelled
+    fn wait_timeout_impl(&self, dur: Option<Duration>, token: Option<&CancellationToken>) -> bool {
         // try wait first
         if self.try_wait() {
             return true;
@@ -85,7 +86,19 @@ impl Semphore {
             self.wakeup_one();
         }
 
-        match cur.park(dur) {
+        let _waker = token.map(|t| {
+            let blocker = cur.clone();
+            t.register(Box::new(move || blocker.interrupt()))
+        });
+
+        // the token would wake us up without setting the unparked flag
+        let ret = match cur.park(dur) {
+            Ok(_) if token.is_none() || cur.is_unparked() => Ok(()),
+            Ok(_) => Err(ParkError::Timeout),
+            Err(err) => Err(err),
+        };
+
+        match ret {
             Ok(_) => true,
             Err(err) => {
                 // check the unpark status
@@ -113,13 +126,23 @@ impl Semphore {
     /// if the semphore value is bigger than zero the function returns immediately
     /// otherwise it would block the until a `post` is executed
     pub fn wait(&self) {
-        self.wait_timeout_impl(None);
+        self.wait_timeout_impl(None, None);
     }
 
     /// same as `wait` except that with an extra timeout value
     /// return false if timeout happened
     pub fn wait_timeout(&self, dur: Duration) -> bool {
-        self.wait_timeout_impl(Some(dur))
+        self.wait_timeout_impl(Some(dur), None)
+    }
+
+    /// same as `wait` except that it returns `Err(Cancelled)` when
+    /// the token is cancelled before acquiring the resource
+    pub fn wait_or_cancel(&self, token: &CancellationToken) -> Result<(), Cancelled> {
+        if self.wait_timeout_impl(None, Some(token)) {
+            Ok(())
+        } else {
+            Err(Cancelled(()))
+        }
     }
 
     /// return false if would block
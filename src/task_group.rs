@@ -0,0 +1,432 @@
+use std::any::Any;
+use std::error::Error;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::cancel::{trigger_cancel_panic, CancelGuard};
+use crate::coroutine_impl::{current, spawn, Coroutine};
+use crate::join::JoinHandle;
+use crate::sync::{CancellationToken, Semphore};
+
+// the state shared by the group and its children
+struct Shared {
+    // set when the first child failed
+    failed: AtomicBool,
+    // the completion sequence of the children
+    seq: AtomicUsize,
+    // the running children, indexed by the spawn order
+    running: Mutex<Vec<Option<Coroutine>>>,
+    // cancelled when the first child failed
+    token: CancellationToken,
+    // limit the number of running children
+    limit: Option<Semphore>,
+}
+
+impl Shared {
+    // cancel all the running children, only the first call takes effect
+    fn fail(&self) {
+        if self.failed.swap(true, Ordering::AcqRel) {
+            return;
+        }
+
+        self.token.cancel();
+        let running: Vec<Coroutine> = {
+            let mut running = self.running.lock().unwrap();
+            running.iter_mut().filter_map(Option::take).collect()
+        };
+        for co in running {
+            unsafe { co.cancel() };
+        }
+    }
+}
+
+// register the child when created, unregister it when dropped
+struct ChildGuard<'a> {
+    shared: &'a Shared,
+    id: usize,
+}
+
+impl<'a> ChildGuard<'a> {
+    fn new(shared: &'a Shared, id: usize) -> Self {
+        let guard = ChildGuard { shared, id };
+        {
+            let mut running = shared.running.lock().unwrap();
+            // check it in the lock, so that `fail` would not miss the child
+            if shared.failed.load(Ordering::Acquire) {
+                drop(running);
+                // the group is already failed, don't run the child at all
+                trigger_cancel_panic();
+            }
+            running[id] = Some(current());
+        }
+        guard
+    }
+}
+
+impl<'a> Drop for ChildGuard<'a> {
+    fn drop(&mut self) {
+        self.shared.running.lock().unwrap()[self.id] = None;
+        if let Some(limit) = self.shared.limit.as_ref() {
+            limit.post();
+        }
+        // a panic child fails the group
+        if thread::panicking() {
+            self.shared.fail();
+        }
+    }
+}
+
+/// A group of child coroutines with first-error semantics
+///
+/// the children are spawned into the group by [`spawn`], and the group is
+/// joined by [`join`] or [`join_unordered`]. when the first child returns an
+/// `Err` or panics, the group cancels all the remaining children, both
+/// cooperatively by cancelling the group [`token`], and forcibly by
+/// `Coroutine::cancel` at their next blocking point.
+///
+/// the group can limit the number of the running children, in which case
+/// [`spawn`] blocks until a running child is done.
+///
+/// dropping the group without joining waits for all the children, if it's
+/// dropped when panicking, the children are cancelled first.
+///
+/// [`spawn`]: struct.TaskGroup.html#method.spawn
+/// [`join`]: struct.TaskGroup.html#method.join
+/// [`join_unordered`]: struct.TaskGroup.html#method.join_unordered
+/// [`token`]: struct.TaskGroup.html#method.token
+///
+/// # Examples
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate may;
+///
+/// use may::coroutine::TaskGroup;
+/// use std::time::Duration;
+///
+/// fn main() {
+///     let mut group = TaskGroup::with_limit(2);
+///     for i in 0..4 {
+///         go!(group, move || {
+///             may::coroutine::sleep(Duration::from_millis(10));
+///             if i == 2 {
+///                 return Err(format!("child {} failed", i));
+///             }
+///             Ok(i)
+///         });
+///     }
+///
+///     let err = group.join().unwrap_err();
+///     assert_eq!(err.errors(), ["child 2 failed"]);
+/// }
+/// ```
+pub struct TaskGroup<T, E> {
+    shared: Arc<Shared>,
+    children: Vec<JoinHandle<(usize, Result<T, E>)>>,
+    // the children that are not spawned because the group is failed
+    skipped: usize,
+}
+
+impl<T: Send + 'static, E: Send + 'static> Default for TaskGroup<T, E> {
+    fn default() -> Self {
+        TaskGroup::new()
+    }
+}
+
+impl<T: Send + 'static, E: Send + 'static> TaskGroup<T, E> {
+    /// create a group without limiting the running children
+    pub fn new() -> Self {
+        Self::create(None)
+    }
+
+    /// create a group that runs at most `limit` children at the same time
+    pub fn with_limit(limit: usize) -> Self {
+        assert!(limit > 0, "the limit must be greater than zero");
+        Self::create(Some(Semphore::new(limit)))
+    }
+
+    fn create(limit: Option<Semphore>) -> Self {
+        TaskGroup {
+            shared: Arc::new(Shared {
+                failed: AtomicBool::new(false),
+                seq: AtomicUsize::new(0),
+                running: Mutex::new(Vec::new()),
+                token: CancellationToken::new(),
+                limit,
+            }),
+            children: Vec::new(),
+            skipped: 0,
+        }
+    }
+
+    /// return the token that is cancelled when the group is failed
+    ///
+    /// the children can use it to stop cooperatively, e.g. with the
+    /// `*_or_cancel` APIs inside a `CancelGuard` that defers the forced
+    /// cancel of the group
+    pub fn token(&self) -> CancellationToken {
+        self.shared.token.clone()
+    }
+
+    /// return true if any child is failed
+    pub fn is_failed(&self) -> bool {
+        self.shared.failed.load(Ordering::Acquire)
+    }
+
+    /// spawn a child coroutine into the group
+    ///
+    /// it blocks until a running child is done if the group limit is reached.
+    /// the child is not spawned and counted as cancelled if the group is
+    /// already failed.
+    ///
+    /// # Safety
+    ///
+    /// same as `coroutine::spawn`
+    pub unsafe fn spawn<F>(&mut self, f: F)
+    where
+        F: FnOnce() -> Result<T, E> + Send + 'static,
+    {
+        if let Some(limit) = self.shared.limit.as_ref() {
+            limit.wait();
+        }
+        if self.is_failed() {
+            if let Some(limit) = self.shared.limit.as_ref() {
+                limit.post();
+            }
+            self.skipped += 1;
+            return;
+        }
+
+        let id = self.children.len();
+        self.shared.running.lock().unwrap().push(None);
+        let shared = self.shared.clone();
+        let handle = spawn(move || {
+            let guard = ChildGuard::new(&shared, id);
+            let ret = f();
+            // unregister the child before failing the group
+            drop(guard);
+            let seq = shared.seq.fetch_add(1, Ordering::Relaxed);
+            if ret.is_err() {
+                shared.fail();
+            }
+            (seq, ret)
+        });
+        self.children.push(handle);
+    }
+
+    /// wait for all the children, return their values in the spawn order
+    ///
+    /// if any child failed, return the collected errors instead
+    pub fn join(mut self) -> Result<Vec<T>, GroupError<E>> {
+        self.join_impl(false)
+    }
+
+    /// wait for all the children, return their values in the completion order
+    ///
+    /// if any child failed, return the collected errors instead
+    pub fn join_unordered(mut self) -> Result<Vec<T>, GroupError<E>> {
+        self.join_impl(true)
+    }
+
+    fn join_impl(&mut self, unordered: bool) -> Result<Vec<T>, GroupError<E>> {
+        let mut values = Vec::with_capacity(self.children.len());
+        let mut errors = Vec::new();
+        let mut err = GroupError {
+            errors: Vec::new(),
+            panics: Vec::new(),
+            cancelled: self.skipped,
+        };
+
+        for child in self.children.drain(..) {
+            match child.join() {
+                Ok((seq, Ok(v))) => values.push((seq, v)),
+                Ok((seq, Err(e))) => errors.push((seq, e)),
+                Err(panic) => match panic.downcast_ref::<generator::Error>() {
+                    Some(&generator::Error::Cancel) => err.cancelled += 1,
+                    _ => err.panics.push(panic),
+                },
+            }
+        }
+
+        if errors.is_empty() && err.panics.is_empty() && err.cancelled == 0 {
+            if unordered {
+                values.sort_by_key(|v| v.0);
+            }
+            Ok(values.into_iter().map(|v| v.1).collect())
+        } else {
+            // the first error comes first
+            errors.sort_by_key(|e| e.0);
+            err.errors = errors.into_iter().map(|e| e.1).collect();
+            Err(err)
+        }
+    }
+}
+
+impl<T, E> Drop for TaskGroup<T, E> {
+    fn drop(&mut self) {
+        if self.children.is_empty() {
+            return;
+        }
+        if thread::panicking() {
+            self.shared.fail();
+        }
+        // the current coroutine may be unwinding by a cancel
+        let _guard = CancelGuard::new();
+        for child in self.children.drain(..) {
+            child.join().ok();
+        }
+    }
+}
+
+impl<T, E> fmt::Debug for TaskGroup<T, E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TaskGroup")
+            .field("children", &self.children.len())
+            .field("is_failed", &self.shared.failed.load(Ordering::Acquire))
+            .finish()
+    }
+}
+
+/// The error returned by `TaskGroup::join` when any child failed
+pub struct GroupError<E> {
+    errors: Vec<E>,
+    panics: Vec<Box<dyn Any + Send>>,
+    cancelled: usize,
+}
+
+impl<E> GroupError<E> {
+    /// the errors returned by the children, the first error comes first
+    pub fn errors(&self) -> &[E] {
+        &self.errors
+    }
+
+    /// consume the error, return the errors returned by the children
+    pub fn into_errors(self) -> Vec<E> {
+        self.errors
+    }
+
+    /// the panic payloads of the children, the cancelled ones not included
+    pub fn panics(&self) -> &[Box<dyn Any + Send>] {
+        &self.panics
+    }
+
+    /// the number of the children cancelled by the group
+    pub fn cancelled(&self) -> usize {
+        self.cancelled
+    }
+}
+
+impl<E: fmt::Debug> fmt::Debug for GroupError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("GroupError")
+            .field("errors", &self.errors)
+            .field("panics", &self.panics.len())
+            .field("cancelled", &self.cancelled)
+            .finish()
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for GroupError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.errors.first() {
+            Some(e) => write!(f, "task group failed: {}", e),
+            None if !self.panics.is_empty() => "task group failed: child panicked".fmt(f),
+            None => "task group cancelled".fmt(f),
+        }
+    }
+}
+
+impl<E: Error> Error for GroupError<E> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sleep::sleep;
+    use std::time::Duration;
+
+    #[test]
+    fn group_values_order() {
+        let mut group = TaskGroup::<_, ()>::new();
+        for i in 0..3u64 {
+            go!(group, move || {
+                sleep(Duration::from_millis(60 - i * 20));
+                Ok(i)
+            });
+        }
+        assert_eq!(group.join().unwrap(), [0, 1, 2]);
+
+        let mut group = TaskGroup::<_, ()>::new();
+        for i in 0..3u64 {
+            go!(group, move || {
+                sleep(Duration::from_millis(60 - i * 20));
+                Ok(i)
+            });
+        }
+        assert_eq!(group.join_unordered().unwrap(), [2, 1, 0]);
+    }
+
+    #[test]
+    fn group_first_error() {
+        let mut group = TaskGroup::new();
+        let token = group.token();
+        // cancelled by the group at the blocking point
+        go!(group, || {
+            sleep(Duration::from_secs(10));
+            Ok(0)
+        });
+        // stopped cooperatively by the group token
+        go!(group, move || {
+            crate::cancel::cancel_shield(|| token.cancelled());
+            Err("stopped")
+        });
+        go!(group, || {
+            sleep(Duration::from_millis(10));
+            Err("failed")
+        });
+
+        let err = group.join().unwrap_err();
+        assert_eq!(err.errors(), ["failed", "stopped"]);
+        assert_eq!(err.cancelled(), 1);
+        assert!(err.panics().is_empty());
+        assert_eq!(err.to_string(), "task group failed: failed");
+    }
+
+    #[test]
+    fn group_panic() {
+        let mut group = TaskGroup::<(), ()>::new();
+        go!(group, || {
+            sleep(Duration::from_secs(10));
+            Ok(())
+        });
+        go!(group, || panic!("boom"));
+        sleep(Duration::from_millis(50));
+
+        // the children spawned after the failure are not run
+        assert!(group.is_failed());
+        go!(group, || unreachable!());
+
+        let err = group.join().unwrap_err();
+        assert!(err.errors().is_empty());
+        assert_eq!(err.cancelled(), 2);
+        assert_eq!(err.panics()[0].downcast_ref::<&str>(), Some(&"boom"));
+    }
+
+    #[test]
+    fn group_limit() {
+        let running = Arc::new(AtomicUsize::new(0));
+        let mut group = TaskGroup::<_, ()>::with_limit(3);
+        for _ in 0..10 {
+            let running = running.clone();
+            go!(group, move || {
+                let n = running.fetch_add(1, Ordering::SeqCst) + 1;
+                sleep(Duration::from_millis(10));
+                running.fetch_sub(1, Ordering::SeqCst);
+                Ok(n)
+            });
+        }
+        let max = group.join().unwrap().into_iter().max().unwrap();
+        assert!(max <= 3);
+    }
+}
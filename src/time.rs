@@ -0,0 +1,400 @@
+//! Utilities for tracking time
+//!
+//! all the timers are driven by the scheduler timer thread, and the
+//! functions here can be used in both coroutine and thread context.
+//!
+//! * [`sleep`] and [`sleep_until`] block the current coroutine for a while.
+//! * [`interval`] creates an [`Interval`] that yields ticks at a fixed period.
+//! * [`timeout`] runs a closure with a time limit.
+//! * [`now`] returns the current time of the clock that drives the timers.
+//!
+//! with the `test-util` feature the clock can be paused, then it's a virtual
+//! clock that only moves forward by [`advance`] or automatically when the
+//! scheduler has nothing else to do, so that the long timeouts can be tested
+//! without waiting for the real time.
+//!
+//! [`sleep`]: fn.sleep.html
+//! [`sleep_until`]: fn.sleep_until.html
+//! [`interval`]: fn.interval.html
+//! [`Interval`]: struct.Interval.html
+//! [`timeout`]: fn.timeout.html
+//! [`now`]: fn.now.html
+//! [`advance`]: fn.advance.html
+
+use std::any::Any;
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::panic;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::coroutine_impl::{current, current_cancel_data, is_coroutine};
+use crate::scheduler::get_scheduler;
+use crate::timeout_list;
+
+pub use crate::sleep::{sleep, sleep_or_cancel};
+
+/// get the current time of the clock that drives all the timers
+///
+/// this is the same as `Instant::now()` unless the clock is paused
+/// with the `test-util` feature
+pub fn now() -> Instant {
+    timeout_list::ns_to_instant(timeout_list::now())
+}
+
+// the clock for checking the deadline of a blocking operation, the
+// coroutines are blocked by the timers that are driven by `now`, while
+// the threads are always blocked for the real time
+pub(crate) fn blocking_now() -> Instant {
+    if is_coroutine() {
+        now()
+    } else {
+        Instant::now()
+    }
+}
+
+/// block the current coroutine until the deadline is reached
+///
+/// return immediately if the deadline is already passed
+pub fn sleep_until(deadline: Instant) {
+    let now = now();
+    if deadline > now {
+        sleep(deadline - now);
+    }
+}
+
+/// Defines the behavior of an [`Interval`] when it misses a tick
+///
+/// a tick is missed when [`Interval::tick`] is called later than the time
+/// of the tick, e.g. the work between two ticks takes longer than the period.
+///
+/// [`Interval`]: struct.Interval.html
+/// [`Interval::tick`]: struct.Interval.html#method.tick
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MissedTickBehavior {
+    /// fire the missed ticks immediately until it catches up with the schedule
+    ///
+    /// this is the default behavior
+    #[default]
+    Burst,
+    /// fire the missed tick immediately, and the following ticks are delayed
+    /// to be one period after it
+    Delay,
+    /// fire the missed tick immediately, and skip the other missed ticks so
+    /// that the following ticks are still on the original schedule
+    Skip,
+}
+
+/// A timer that yields a tick at a fixed period
+///
+/// the ticks are scheduled from the start time rather than the time that
+/// `tick` returns, so the time spent between the ticks would not make the
+/// interval drift.
+///
+/// created by [`interval`] or [`interval_at`]
+///
+/// [`interval`]: fn.interval.html
+/// [`interval_at`]: fn.interval_at.html
+#[derive(Debug)]
+pub struct Interval {
+    // the time of the next tick
+    next: Instant,
+    period: Duration,
+    missed_tick_behavior: MissedTickBehavior,
+}
+
+/// create an `Interval` that the first tick completes immediately
+///
+/// # Panics
+///
+/// panics if `period` is zero
+///
+/// # Examples
+///
+/// ```
+/// use may::time;
+/// use std::time::Duration;
+///
+/// let mut interval = time::interval(Duration::from_millis(10));
+/// for _ in 0..3 {
+///     // the first tick returns immediately
+///     interval.tick();
+/// }
+/// ```
+pub fn interval(period: Duration) -> Interval {
+    interval_at(now(), period)
+}
+
+/// create an `Interval` that the first tick completes at `start`
+///
+/// # Panics
+///
+/// panics if `period` is zero
+pub fn interval_at(start: Instant, period: Duration) -> Interval {
+    assert!(period > Duration::from_secs(0), "`period` must be non-zero");
+    Interval {
+        next: start,
+        period,
+        missed_tick_behavior: MissedTickBehavior::default(),
+    }
+}
+
+impl Interval {
+    /// block until the next tick, return the scheduled time of the tick
+    pub fn tick(&mut self) -> Instant {
+        sleep_until(self.next);
+        let tick = self.next;
+        self.next = self.next_tick(tick, now());
+        tick
+    }
+
+    // calculate the next tick time after firing `tick` at `now`
+    fn next_tick(&self, tick: Instant, now: Instant) -> Instant {
+        let next = tick + self.period;
+        if next > now {
+            return next;
+        }
+
+        // we already missed the next tick
+        match self.missed_tick_behavior {
+            MissedTickBehavior::Burst => next,
+            MissedTickBehavior::Delay => now + self.period,
+            MissedTickBehavior::Skip => {
+                let period = self.period.as_nanos();
+                let ahead = period - (now - tick).as_nanos() % period;
+                now + Duration::from_nanos(ahead as u64)
+            }
+        }
+    }
+
+    /// reset the interval so that the next tick completes after one period
+    pub fn reset(&mut self) {
+        self.next = now() + self.period;
+    }
+
+    /// get the period of the interval
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    /// get the missed tick behavior
+    pub fn missed_tick_behavior(&self) -> MissedTickBehavior {
+        self.missed_tick_behavior
+    }
+
+    /// set the missed tick behavior
+    pub fn set_missed_tick_behavior(&mut self, behavior: MissedTickBehavior) {
+        self.missed_tick_behavior = behavior;
+    }
+}
+
+/// The error returned by [`timeout`] when the time limit is reached
+///
+/// [`timeout`]: fn.timeout.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Elapsed(());
+
+impl fmt::Display for Elapsed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        "deadline has elapsed".fmt(f)
+    }
+}
+
+impl Error for Elapsed {}
+
+impl From<Elapsed> for io::Error {
+    fn from(_: Elapsed) -> io::Error {
+        io::Error::new(io::ErrorKind::TimedOut, "deadline has elapsed")
+    }
+}
+
+fn is_cancel_panic(e: &(dyn Any + Send)) -> bool {
+    matches!(
+        e.downcast_ref::<generator::Error>(),
+        Some(generator::Error::Cancel)
+    )
+}
+
+/// run the closure with a time limit
+///
+/// when the time limit is reached, the blocking operation that the closure
+/// is waiting for is cancelled and `Err(Elapsed)` is returned. the closure
+/// is cancelled the same way as `Coroutine::cancel`, so the resources that
+/// it holds are dropped properly. if the closure completes before it gets
+/// cancelled the result is returned even if the time limit is reached.
+///
+/// the closure can only be cancelled in coroutine context, in thread context
+/// it always runs to completion.
+///
+/// # Examples
+///
+/// ```
+/// #[macro_use]
+/// extern crate may;
+///
+/// use may::time;
+/// use std::time::Duration;
+///
+/// fn main() {
+///     let h = go!(|| {
+///         let ret = time::timeout(Duration::from_millis(10), || {
+///             time::sleep(Duration::from_secs(10));
+///         });
+///         assert!(ret.is_err());
+///     });
+///     h.join().unwrap();
+/// }
+/// ```
+pub fn timeout<F, T>(dur: Duration, f: F) -> Result<T, Elapsed>
+where
+    F: FnOnce() -> T,
+{
+    if !is_coroutine() {
+        return Ok(f());
+    }
+
+    // the timer handler cancels the current coroutine when timeout, the
+    // cancel is done with the lock held so that it's finished once we
+    // find the target is taken
+    let target = Arc::new(Mutex::new(Some(current())));
+    let t = target.clone();
+    let handle = get_scheduler().add_timer_handler(dur, move || {
+        if let Some(co) = t.lock().unwrap().take() {
+            unsafe { co.cancel() };
+        }
+    });
+
+    let ret = panic::catch_unwind(panic::AssertUnwindSafe(f));
+
+    get_scheduler().del_timer(handle);
+    let done = target.lock().unwrap().take().is_some();
+    if !done {
+        // the timer has cancelled us, clear the flag
+        current_cancel_data().clear_cancel_bit();
+    }
+
+    match ret {
+        Ok(v) => Ok(v),
+        Err(e) if !done && is_cancel_panic(&*e) => Err(Elapsed(())),
+        Err(e) => panic::resume_unwind(e),
+    }
+}
+
+#[cfg(feature = "test-util")]
+static AUTO_ADVANCE: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+/// pause the clock that drives all the timers
+///
+/// once paused, the clock only moves forward by [`advance`], or when the auto
+/// advance is enabled by [`set_auto_advance`]. all the coroutine timers, like
+/// `sleep`, `park_timeout` and the io timeouts, are based on this clock, but
+/// blocking in thread context still waits for the real time.
+///
+/// the paused clock is aligned to the timer resolution, so advancing it by
+/// the duration of a timer would exactly expire the timer.
+///
+/// the clock is shared by the whole process, so it's better to use it in a
+/// dedicated test binary.
+///
+/// [`advance`]: fn.advance.html
+/// [`set_auto_advance`]: fn.set_auto_advance.html
+///
+/// # Examples
+///
+/// ```
+/// #[macro_use]
+/// extern crate may;
+///
+/// use may::time;
+/// use std::time::Duration;
+///
+/// fn main() {
+///     time::pause();
+///     time::set_auto_advance(true);
+///     let start = time::now();
+///     // this returns without waiting for an hour
+///     let h = go!(|| time::sleep(Duration::from_secs(3600)));
+///     h.join().unwrap();
+///     assert!(time::now() - start >= Duration::from_secs(3600));
+///     time::resume();
+/// }
+/// ```
+#[cfg(feature = "test-util")]
+pub fn pause() {
+    timeout_list::clock::pause();
+}
+
+/// resume the paused clock
+///
+/// the clock continues from the virtual time, the real time passed
+/// during the pause is skipped.
+#[cfg(feature = "test-util")]
+pub fn resume() {
+    timeout_list::clock::resume();
+}
+
+/// return true if the clock is paused
+#[cfg(feature = "test-util")]
+pub fn is_paused() -> bool {
+    timeout_list::clock::is_paused()
+}
+
+/// move the paused clock forward, and fire all the expired timers
+///
+/// only the timers that are already registered would expire, so make sure
+/// that the coroutines are blocked on the timers before advancing.
+///
+/// # Panics
+///
+/// panics if the clock is not paused
+#[cfg(feature = "test-util")]
+pub fn advance(dur: Duration) {
+    let advanced = timeout_list::clock::advance(timeout_list::dur_to_ns(dur));
+    assert!(advanced, "the clock is not paused");
+    crate::scheduler::get_scheduler().wake_timers();
+}
+
+/// enable or disable the auto advance of the paused clock
+///
+/// when enabled, the paused clock jumps to the next timer once all the
+/// workers are idle, that is all the coroutines are blocked on timers or
+/// something else that would not wake them up. the threads that are not
+/// coroutines are not tracked, so they should only wait for the coroutines.
+#[cfg(feature = "test-util")]
+pub fn set_auto_advance(enable: bool) {
+    static DRIVER: std::sync::Once = std::sync::Once::new();
+    AUTO_ADVANCE.store(enable, std::sync::atomic::Ordering::Release);
+    if enable {
+        DRIVER.call_once(|| {
+            std::thread::spawn(auto_advance);
+        });
+    }
+}
+
+// the thread that drives the paused clock when the scheduler is idle
+#[cfg(feature = "test-util")]
+fn auto_advance() {
+    let scheduler = crate::scheduler::get_scheduler();
+    let mut last = None;
+    loop {
+        std::thread::sleep(Duration::from_millis(1));
+        if !AUTO_ADVANCE.load(std::sync::atomic::Ordering::Acquire) || !is_paused() {
+            last = None;
+            continue;
+        }
+
+        // the scheduler must keep idle in two checks before advancing
+        let deadline = scheduler.idle_deadline();
+        if deadline.is_none() || deadline != last {
+            last = deadline;
+            continue;
+        }
+        last = None;
+
+        if let Some(deadline) = deadline {
+            timeout_list::clock::advance_to(deadline);
+            scheduler.wake_timers();
+        }
+    }
+}
@@ -1,23 +1,18 @@
-use std::cmp;
-use std::collections::{BinaryHeap, HashMap};
-use std::mem;
+#[cfg(feature = "test-util")]
+use std::sync::atomic::AtomicBool;
 use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::{Arc, Mutex, RwLock};
 use std::thread;
 use std::time::{Duration, Instant};
 
+use crate::scheduler::worker_id;
+use crate::timer_wheel::TimerWheel;
 use crossbeam::atomic::AtomicCell;
-use crossbeam::queue::SegQueue as mpsc;
-use may_queue::mpsc_list_v1::Entry;
-use may_queue::mpsc_list_v1::Queue as TimeoutQueue;
 
 const NANOS_PER_MILLI: u64 = 1_000_000;
 const NANOS_PER_SEC: u64 = 1_000_000_000;
 
-const HASH_CAP: usize = 1024;
-
 #[inline]
-fn dur_to_ns(dur: Duration) -> u64 {
+pub fn dur_to_ns(dur: Duration) -> u64 {
     // Note that a duration is a (u64, u32) (seconds, nanoseconds) pair
     dur.as_secs()
         .saturating_mul(NANOS_PER_SEC)
@@ -42,218 +37,330 @@ fn get_instant() -> &'static Instant {
     static START_TIME: MaybeUninit<Instant> = MaybeUninit::uninit();
     unsafe { &*START_TIME.as_ptr() }
 }
+
 // get the current wall clock in ns
 #[inline]
 pub fn now() -> u64 {
+    #[cfg(feature = "test-util")]
+    {
+        clock::now()
+    }
+    #[cfg(not(feature = "test-util"))]
+    {
+        real_now()
+    }
+}
+
+#[inline]
+fn real_now() -> u64 {
     // we need a Monotonic Clock here
     get_instant().elapsed().as_nanos() as u64
 }
 
-// timeout event data
-pub struct TimeoutData<T> {
-    time: u64,   // the wall clock in ns that the timer expires
-    pub data: T, // the data associate with the timeout event
+// convert the wall clock in ns to the `Instant`
+#[inline]
+pub fn ns_to_instant(ns: u64) -> Instant {
+    *get_instant() + ns_to_dur(ns)
 }
 
-// timeout handler which can be removed/cancelled
-pub type TimeoutHandle<T> = Entry<TimeoutData<T>>;
+// the virtual clock for tests, all the timers are driven by it
+#[cfg(feature = "test-util")]
+pub mod clock {
+    use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering};
+    use std::sync::Mutex;
+
+    use super::{dur_to_ns, real_now};
+    use crate::config::config;
+
+    static PAUSED: AtomicBool = AtomicBool::new(false);
+    // the frozen wall clock in ns when paused
+    static VIRTUAL_NOW: AtomicU64 = AtomicU64::new(0);
+    // the offset to the real clock after resumed
+    static OFFSET: AtomicI64 = AtomicI64::new(0);
+    // serialize the clock state changes
+    static LOCK: Mutex<()> = Mutex::new(());
+
+    #[inline]
+    pub fn now() -> u64 {
+        if PAUSED.load(Ordering::Acquire) {
+            return VIRTUAL_NOW.load(Ordering::Acquire);
+        }
+        (real_now() as i64 + OFFSET.load(Ordering::Acquire)) as u64
+    }
 
-struct TimeoutQueueWrapper<T> {
-    inner: TimeoutQueue<TimeoutData<T>>,
-    in_use: AtomicUsize,
-}
+    #[inline]
+    pub fn is_paused() -> bool {
+        PAUSED.load(Ordering::Acquire)
+    }
 
-impl<T> TimeoutQueueWrapper<T> {
-    fn new() -> Self {
-        TimeoutQueueWrapper {
-            inner: TimeoutQueue::new(),
-            in_use: AtomicUsize::new(0),
+    pub fn pause() {
+        let _lock = LOCK.lock().unwrap();
+        if !is_paused() {
+            // align to the timer tick, so that advancing the clock by the
+            // duration of a timer would exactly expire it
+            let resolution = dur_to_ns(config().get_timer_resolution()).max(1);
+            let now = now().div_ceil(resolution) * resolution;
+            VIRTUAL_NOW.store(now, Ordering::Release);
+            PAUSED.store(true, Ordering::Release);
         }
     }
-}
 
-type IntervalList<T> = Arc<TimeoutQueueWrapper<T>>;
+    pub fn resume() {
+        let _lock = LOCK.lock().unwrap();
+        if is_paused() {
+            // continue from the virtual time, the paused period is skipped
+            let offset = VIRTUAL_NOW.load(Ordering::Acquire) as i64 - real_now() as i64;
+            OFFSET.store(offset, Ordering::Release);
+            PAUSED.store(false, Ordering::Release);
+        }
+    }
 
-// this is the data type that used by the binary heap to get the latest timer
-struct IntervalEntry<T> {
-    time: u64,             // the head timeout value in the list, should be latest
-    list: IntervalList<T>, // point to the interval list
-    interval: u64,
-}
+    // move the paused clock forward by `ns`, return false if not paused
+    pub fn advance(ns: u64) -> bool {
+        let _lock = LOCK.lock().unwrap();
+        if !is_paused() {
+            return false;
+        }
+        let now = VIRTUAL_NOW.load(Ordering::Acquire);
+        VIRTUAL_NOW.store(now.saturating_add(ns), Ordering::Release);
+        true
+    }
 
-impl<T> IntervalEntry<T> {
-    // trigger the timeout event with the supplying function
-    // return next expire time
-    pub fn pop_timeout<F>(&self, now: u64, f: &F) -> Option<u64>
-    where
-        F: Fn(T),
-    {
-        let p = |v: &TimeoutData<T>| v.time <= now;
-        while let Some(timeout) = self.list.inner.pop_if(&p) {
-            f(timeout.data);
+    // move the paused clock forward to `time` if it's later than now
+    pub fn advance_to(time: u64) {
+        let _lock = LOCK.lock().unwrap();
+        if is_paused() {
+            VIRTUAL_NOW.fetch_max(time, Ordering::AcqRel);
         }
-        self.list.inner.peek().map(|t| t.time)
     }
 }
 
-impl<T> PartialEq for IntervalEntry<T> {
-    fn eq(&self, other: &Self) -> bool {
-        self.time == other.time
+// timeout handler which can be removed/cancelled
+pub use crate::timer_wheel::TimerHandle as TimeoutHandle;
+
+// the interval hashed timer list that is replaced by the timing wheel,
+// it's only kept as the baseline for the timer benches
+#[cfg(nightly)]
+pub mod interval_list {
+    use std::cmp;
+    use std::collections::{BinaryHeap, HashMap};
+    use std::mem;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex, RwLock};
+    use std::time::Duration;
+
+    use super::{dur_to_ns, now};
+    use may_queue::mpsc_list_v1::Entry;
+    use may_queue::mpsc_list_v1::Queue as TimeoutQueue;
+
+    const HASH_CAP: usize = 1024;
+
+    // timeout event data
+    pub struct TimeoutData<T> {
+        time: u64,   // the wall clock in ns that the timer expires
+        pub data: T, // the data associate with the timeout event
     }
-}
 
-impl<T> Eq for IntervalEntry<T> {}
+    // timeout handler which can be removed/cancelled
+    pub type TimeoutHandle<T> = Entry<TimeoutData<T>>;
 
-impl<T> PartialOrd for IntervalEntry<T> {
-    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
-        Some(self.cmp(other))
+    struct TimeoutQueueWrapper<T> {
+        inner: TimeoutQueue<TimeoutData<T>>,
+        in_use: AtomicUsize,
     }
-}
 
-impl<T> cmp::Ord for IntervalEntry<T> {
-    fn cmp(&self, other: &Self) -> cmp::Ordering {
-        other.time.cmp(&self.time)
+    impl<T> TimeoutQueueWrapper<T> {
+        fn new() -> Self {
+            TimeoutQueueWrapper {
+                inner: TimeoutQueue::new(),
+                in_use: AtomicUsize::new(0),
+            }
+        }
     }
-}
 
-// the timeout list data structure
-pub struct TimeOutList<T> {
-    // interval based hash map, protected by rw lock
-    interval_map: RwLock<HashMap<u64, IntervalList<T>>>,
-    // a priority queue, each element is the head of a mpsc queue
-    timer_bh: Mutex<BinaryHeap<IntervalEntry<T>>>,
-}
+    type IntervalList<T> = Arc<TimeoutQueueWrapper<T>>;
 
-impl<T> TimeOutList<T> {
-    pub fn new() -> Self {
-        TimeOutList {
-            interval_map: RwLock::new(HashMap::with_capacity(HASH_CAP)),
-            timer_bh: Mutex::new(BinaryHeap::new()),
+    // this is the data type that used by the binary heap to get the latest timer
+    struct IntervalEntry<T> {
+        time: u64,             // the head timeout value in the list, should be latest
+        list: IntervalList<T>, // point to the interval list
+        interval: u64,
+    }
+
+    impl<T> IntervalEntry<T> {
+        // trigger the timeout event with the supplying function
+        // return next expire time
+        pub fn pop_timeout<F>(&self, now: u64, f: &F) -> Option<u64>
+        where
+            F: Fn(T),
+        {
+            let p = |v: &TimeoutData<T>| v.time <= now;
+            while let Some(timeout) = self.list.inner.pop_if(&p) {
+                f(timeout.data);
+            }
+            self.list.inner.peek().map(|t| t.time)
         }
     }
 
-    fn install_timer_bh(&self, entry: IntervalEntry<T>) {
-        if entry.list.in_use.fetch_add(1, Ordering::AcqRel) == 0 {
-            self.timer_bh.lock().unwrap().push(entry);
+    impl<T> PartialEq for IntervalEntry<T> {
+        fn eq(&self, other: &Self) -> bool {
+            self.time == other.time
         }
     }
 
-    // add a timeout event to the list
-    // this can be called in any thread
-    // return true if we need to recall next expire
-    pub fn add_timer(&self, dur: Duration, data: T) -> (TimeoutHandle<T>, bool) {
-        let interval = dur_to_ns(dur);
-        let time = now() + interval; // TODO: deal with overflow?
-                                     //println!("add timer = {:?}", time);
-
-        let timeout = TimeoutData { time, data };
-
-        let interval_list = {
-            // use the read lock protect
-            let interval_map_r = self.interval_map.read().unwrap();
-            (*interval_map_r).get(&interval).cloned()
-            // drop the read lock here
-        };
+    impl<T> Eq for IntervalEntry<T> {}
 
-        if let Some(interval_list) = interval_list {
-            let (handle, is_head) = interval_list.inner.push(timeout);
-            if is_head {
-                // install the interval list to the binary heap
-                self.install_timer_bh(IntervalEntry {
-                    time,
-                    interval,
-                    list: interval_list,
-                });
+    impl<T> PartialOrd for IntervalEntry<T> {
+        fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl<T> cmp::Ord for IntervalEntry<T> {
+        fn cmp(&self, other: &Self) -> cmp::Ordering {
+            other.time.cmp(&self.time)
+        }
+    }
+
+    // the timeout list data structure
+    pub struct TimeOutList<T> {
+        // interval based hash map, protected by rw lock
+        interval_map: RwLock<HashMap<u64, IntervalList<T>>>,
+        // a priority queue, each element is the head of a mpsc queue
+        timer_bh: Mutex<BinaryHeap<IntervalEntry<T>>>,
+    }
+
+    impl<T> TimeOutList<T> {
+        pub fn new() -> Self {
+            TimeOutList {
+                interval_map: RwLock::new(HashMap::with_capacity(HASH_CAP)),
+                timer_bh: Mutex::new(BinaryHeap::new()),
             }
-            return (handle, is_head);
         }
 
-        // if the interval list is not there, get the write locker to install the list
-        // use the write lock protect
-        let mut interval_map_w = self.interval_map.write().unwrap();
-        // recheck the interval list in case other thread may install it
-        if let Some(interval_list) = (*interval_map_w).get(&interval) {
-            let (handle, is_head) = interval_list.inner.push(timeout);
-            if is_head {
-                // this rarely happens
-                self.install_timer_bh(IntervalEntry {
-                    time,
-                    interval,
-                    list: interval_list.clone(),
-                });
+        fn install_timer_bh(&self, entry: IntervalEntry<T>) {
+            if entry.list.in_use.fetch_add(1, Ordering::AcqRel) == 0 {
+                self.timer_bh.lock().unwrap().push(entry);
             }
-            return (handle, is_head);
         }
 
-        let interval_list = Arc::new(TimeoutQueueWrapper::<T>::new());
-        let ret = interval_list.inner.push(timeout).0;
-        (*interval_map_w).insert(interval, interval_list.clone());
-        // drop the write lock here
-        mem::drop(interval_map_w);
-
-        // install the new interval list to the binary heap
-        self.install_timer_bh(IntervalEntry {
-            time,
-            interval,
-            list: interval_list,
-        });
+        // add a timeout event to the list
+        // this can be called in any thread
+        // return true if we need to recall next expire
+        pub fn add_timer(&self, dur: Duration, data: T) -> (TimeoutHandle<T>, bool) {
+            let interval = dur_to_ns(dur);
+            let time = now() + interval; // TODO: deal with overflow?
+                                         //println!("add timer = {:?}", time);
+
+            let timeout = TimeoutData { time, data };
+
+            let interval_list = {
+                // use the read lock protect
+                let interval_map_r = self.interval_map.read().unwrap();
+                (*interval_map_r).get(&interval).cloned()
+                // drop the read lock here
+            };
 
-        (ret, true)
-    }
+            if let Some(interval_list) = interval_list {
+                let (handle, is_head) = interval_list.inner.push(timeout);
+                if is_head {
+                    // install the interval list to the binary heap
+                    self.install_timer_bh(IntervalEntry {
+                        time,
+                        interval,
+                        list: interval_list,
+                    });
+                }
+                return (handle, is_head);
+            }
 
-    // schedule in the timer thread
-    // this will remove all the expired timeout event
-    // and call the supplied function with registered data
-    // return the time in ns for the next expiration
-    pub fn schedule_timer<F: Fn(T)>(&self, now: u64, f: &F) -> Option<u64> {
-        loop {
-            // first peek the BH to see if there is any timeout event
-            let mut entry = {
-                let mut timer_bh = self.timer_bh.lock().unwrap();
-                match timer_bh.peek() {
-                    // the latest timeout event not happened yet
-                    Some(entry) => {
-                        if entry.time > now {
-                            return Some(entry.time - now);
-                        } else {
-                            // find out one entry
-                        }
-                    }
-                    None => return None,
+            // if the interval list is not there, get the write locker to install the list
+            // use the write lock protect
+            let mut interval_map_w = self.interval_map.write().unwrap();
+            // recheck the interval list in case other thread may install it
+            if let Some(interval_list) = (*interval_map_w).get(&interval) {
+                let (handle, is_head) = interval_list.inner.push(timeout);
+                if is_head {
+                    // this rarely happens
+                    self.install_timer_bh(IntervalEntry {
+                        time,
+                        interval,
+                        list: interval_list.clone(),
+                    });
                 }
-                let entry = timer_bh.pop().unwrap();
-                entry.list.in_use.store(0, Ordering::Release);
-                entry
-            };
+                return (handle, is_head);
+            }
 
-            // consume all the timeout event
-            // the binary heap can be modified here
-            // during running the timeout handler
-            match entry.pop_timeout(now, f) {
-                Some(time) => {
-                    if entry.list.in_use.fetch_add(1, Ordering::AcqRel) == 0 {
-                        // re-push the entry
-                        entry.time = time;
-                        self.timer_bh.lock().unwrap().push(entry);
+            let interval_list = Arc::new(TimeoutQueueWrapper::<T>::new());
+            let ret = interval_list.inner.push(timeout).0;
+            (*interval_map_w).insert(interval, interval_list.clone());
+            // drop the write lock here
+            mem::drop(interval_map_w);
+
+            // install the new interval list to the binary heap
+            self.install_timer_bh(IntervalEntry {
+                time,
+                interval,
+                list: interval_list,
+            });
+
+            (ret, true)
+        }
+
+        // schedule in the timer thread
+        // this will remove all the expired timeout event
+        // and call the supplied function with registered data
+        // return the time in ns for the next expiration
+        pub fn schedule_timer<F: Fn(T)>(&self, now: u64, f: &F) -> Option<u64> {
+            loop {
+                // first peek the BH to see if there is any timeout event
+                let mut entry = {
+                    let mut timer_bh = self.timer_bh.lock().unwrap();
+                    match timer_bh.peek() {
+                        // the latest timeout event not happened yet
+                        Some(entry) => {
+                            if entry.time > now {
+                                return Some(entry.time - now);
+                            } else {
+                                // find out one entry
+                            }
+                        }
+                        None => return None,
+                    }
+                    let entry = timer_bh.pop().unwrap();
+                    entry.list.in_use.store(0, Ordering::Release);
+                    entry
+                };
+
+                // consume all the timeout event
+                // the binary heap can be modified here
+                // during running the timeout handler
+                match entry.pop_timeout(now, f) {
+                    Some(time) => {
+                        if entry.list.in_use.fetch_add(1, Ordering::AcqRel) == 0 {
+                            // re-push the entry
+                            entry.time = time;
+                            self.timer_bh.lock().unwrap().push(entry);
+                        }
                     }
-                }
 
-                None => {
-                    // if the interval list is empty, need to delete it
-                    let mut interval_map_w = self.interval_map.write().unwrap();
-                    // recheck if the interval list is empty, other thread may append data to it
-                    if entry.list.inner.is_empty() {
-                        // if the len of the hash map is big enough just leave the queue there
-                        if (*interval_map_w).len() > HASH_CAP {
-                            // the list is really empty now, we can safely remove it
-                            (*interval_map_w).remove(&entry.interval);
+                    None => {
+                        // if the interval list is empty, need to delete it
+                        let mut interval_map_w = self.interval_map.write().unwrap();
+                        // recheck if the interval list is empty, other thread may append data to it
+                        if entry.list.inner.is_empty() {
+                            // if the len of the hash map is big enough just leave the queue there
+                            if (*interval_map_w).len() > HASH_CAP {
+                                // the list is really empty now, we can safely remove it
+                                (*interval_map_w).remove(&entry.interval);
+                            }
+                        } else if entry.list.in_use.fetch_add(1, Ordering::AcqRel) == 0 {
+                            // release the w lock first, we don't need it any more
+                            mem::drop(interval_map_w);
+                            // the list is push some data by other thread
+                            entry.time = entry.list.inner.peek().unwrap().time;
+                            self.timer_bh.lock().unwrap().push(entry);
                         }
-                    } else if entry.list.in_use.fetch_add(1, Ordering::AcqRel) == 0 {
-                        // release the w lock first, we don't need it any more
-                        mem::drop(interval_map_w);
-                        // the list is push some data by other thread
-                        entry.time = entry.list.inner.peek().unwrap().time;
-                        self.timer_bh.lock().unwrap().push(entry);
                     }
                 }
             }
@@ -262,24 +369,43 @@ impl<T> TimeOutList<T> {
 }
 
 pub struct TimerThread<T> {
-    timer_list: TimeOutList<T>,
-    // collect the remove request
-    remove_list: mpsc<TimeoutHandle<T>>,
+    // the timer wheels, each worker adds timers to its own shard
+    // so that they don't contend on the same lock
+    shards: Vec<TimerWheel<T>>,
     // the timer thread wakeup handler
     wakeup: AtomicCell<Option<thread::Thread>>,
+    // if the timer thread is running the timer handlers
+    #[cfg(feature = "test-util")]
+    busy: AtomicBool,
 }
 
 impl<T> TimerThread<T> {
-    pub fn new() -> Self {
+    pub fn new(shards: usize) -> Self {
         TimerThread {
-            timer_list: TimeOutList::new(),
-            remove_list: mpsc::new(),
+            shards: (0..shards.max(1)).map(|_| TimerWheel::new()).collect(),
             wakeup: AtomicCell::new(None),
+            #[cfg(feature = "test-util")]
+            busy: AtomicBool::new(false),
         }
     }
 
+    // the shard for the current thread, the workers use their own shards
+    // and the other threads are spread over the shards
+    fn shard(&self) -> &TimerWheel<T> {
+        static NEXT_SHARD: AtomicUsize = AtomicUsize::new(0);
+        thread_local! {
+            static SHARD: usize = NEXT_SHARD.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let id = match worker_id() {
+            id if id == !1 => SHARD.with(|id| *id),
+            id => id,
+        };
+        &self.shards[id % self.shards.len()]
+    }
+
     pub fn add_timer(&self, dur: Duration, data: T) -> TimeoutHandle<T> {
-        let (h, is_recal) = self.timer_list.add_timer(dur, data);
+        let (h, is_recal) = self.shard().add_timer(dur, data);
         // wake up the timer thread if it's a new queue
         if is_recal {
             if let Some(t) = self.wakeup.take() {
@@ -289,36 +415,56 @@ impl<T> TimerThread<T> {
         h
     }
 
+    // the timer can be removed in any thread
     pub fn del_timer(&self, handle: TimeoutHandle<T>) {
-        self.remove_list.push(handle);
-        if let Some(t) = self.wakeup.take() {
-            t.unpark();
-        }
+        handle.remove();
     }
 
     // the timer thread function
     pub fn run<F: Fn(T)>(&self, f: &F) {
         let current_thread = thread::current();
         loop {
-            while let Some(h) = self.remove_list.pop() {
-                h.remove();
-            }
             // we must register the thread handle first
             // or there will be no signal to wakeup the timer thread
             self.wakeup.swap(Some(current_thread.clone()));
 
-            if !self.remove_list.is_empty() {
-                if let Some(t) = self.wakeup.take() {
-                    t.unpark();
-                }
-            }
-
-            match self.timer_list.schedule_timer(now(), f) {
+            #[cfg(feature = "test-util")]
+            self.busy.store(true, Ordering::Release);
+            let now = now();
+            let next_expire = self
+                .shards
+                .iter()
+                .filter_map(|shard| shard.schedule_timer(now, f))
+                .min();
+            #[cfg(feature = "test-util")]
+            self.busy.store(false, Ordering::Release);
+
+            match next_expire {
                 Some(time) => thread::park_timeout(ns_to_dur(time)),
                 None => thread::park(),
             }
         }
     }
+
+    // wake up the timer thread to process the timers
+    #[cfg(feature = "test-util")]
+    pub fn wakeup(&self) {
+        if let Some(t) = self.wakeup.take() {
+            t.unpark();
+        }
+    }
+
+    // return true if the timer thread is running the timer handlers
+    #[cfg(feature = "test-util")]
+    pub fn is_busy(&self) -> bool {
+        self.busy.load(Ordering::Acquire)
+    }
+
+    // return the wall clock in ns of the next expiration
+    #[cfg(feature = "test-util")]
+    pub fn next_deadline(&self) -> Option<u64> {
+        self.shards.iter().filter_map(|s| s.next_deadline()).min()
+    }
 }
 
 #[cfg(test)]
@@ -330,7 +476,7 @@ mod tests {
 
     #[test]
     fn test_timeout_list() {
-        let timer = Arc::new(TimerThread::<usize>::new());
+        let timer = Arc::new(TimerThread::<usize>::new(2));
         let t = timer.clone();
         let f = |data: usize| {
             println!("timeout data:{:?}", data);
@@ -351,4 +497,34 @@ mod tests {
 
         thread::sleep(Duration::from_millis(1500));
     }
+
+    #[test]
+    fn test_timer_shards() {
+        use std::sync::Mutex;
+
+        let timer = Arc::new(TimerThread::<usize>::new(4));
+        let fired = Arc::new(Mutex::new(Vec::new()));
+        let (t, f) = (timer.clone(), fired.clone());
+        thread::spawn(move || t.run(&|data| f.lock().unwrap().push(data)));
+
+        // the threads add the timers to different shards
+        let handles: Vec<_> = (0..4)
+            .map(|i| {
+                let t = timer.clone();
+                thread::spawn(move || {
+                    let h = t.add_timer(Duration::from_millis(20 + i as u64), i);
+                    t.add_timer(Duration::from_millis(10), i + 10);
+                    h
+                })
+            })
+            .collect();
+        let mut handles: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        // the timer can be removed in another thread
+        timer.del_timer(handles.pop().unwrap());
+
+        thread::sleep(Duration::from_millis(200));
+        let mut fired = fired.lock().unwrap().clone();
+        fired.sort_unstable();
+        assert_eq!(fired, vec![0, 1, 2, 10, 11, 12, 13]);
+    }
 }
@@ -0,0 +1,453 @@
+//! hierarchical timing wheel
+//!
+//! the wheel has `LEVELS` levels and each level has `SLOTS` slots, a slot
+//! in level `n` covers `SLOTS^n` ticks. a timer is put into the level that
+//! its expiration tick first differs from the current tick, and cascades
+//! to the lower levels when the wheel reaches its slot. insert and cancel
+//! are O(1), the expiration is amortized O(1) for each timer.
+
+use std::mem;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::config::config;
+use crate::timeout_list::{dur_to_ns, now};
+
+const LEVEL_BITS: u32 = 6;
+const SLOTS: usize = 1 << LEVEL_BITS;
+const SLOT_MASK: u64 = SLOTS as u64 - 1;
+const LEVELS: usize = 6;
+
+// the empty link
+const NIL: usize = usize::MAX;
+// the list of timers that are expired when inserted
+const PENDING: usize = LEVELS * SLOTS;
+// the node is not in any list
+const UNLINKED: usize = PENDING + 1;
+
+struct Node<T> {
+    // the tick that the timer expires
+    when: u64,
+    // bumped when the node is freed, so that the stale handles are ignored
+    gen: u32,
+    // the list that the node belongs to
+    list: usize,
+    prev: usize,
+    next: usize,
+    data: Option<T>,
+}
+
+struct Wheel<T> {
+    // the tick that the wheel has processed
+    elapsed: u64,
+    // the earliest tick that the wheel needs to be polled
+    next_wake: u64,
+    nodes: Vec<Node<T>>,
+    // the head of the free nodes, linked by `next`
+    free: usize,
+    // the slot list heads of all the levels and the pending list
+    heads: Vec<usize>,
+    // the bit map of the non-empty slots for each level
+    occupied: [u64; LEVELS],
+}
+
+impl<T> Wheel<T> {
+    fn new(elapsed: u64) -> Self {
+        Wheel {
+            elapsed,
+            next_wake: u64::MAX,
+            nodes: Vec::new(),
+            free: NIL,
+            heads: vec![NIL; PENDING + 1],
+            occupied: [0; LEVELS],
+        }
+    }
+
+    fn alloc(&mut self, when: u64, data: T) -> usize {
+        if self.free == NIL {
+            self.nodes.push(Node {
+                when,
+                gen: 0,
+                list: UNLINKED,
+                prev: NIL,
+                next: NIL,
+                data: Some(data),
+            });
+            return self.nodes.len() - 1;
+        }
+
+        let key = self.free;
+        let node = &mut self.nodes[key];
+        self.free = node.next;
+        node.when = when;
+        node.data = Some(data);
+        key
+    }
+
+    fn free(&mut self, key: usize) -> Option<T> {
+        let node = &mut self.nodes[key];
+        node.gen = node.gen.wrapping_add(1);
+        node.list = UNLINKED;
+        node.prev = NIL;
+        node.next = self.free;
+        self.free = key;
+        node.data.take()
+    }
+
+    fn link(&mut self, key: usize, list: usize) {
+        let head = self.heads[list];
+        if head != NIL {
+            self.nodes[head].prev = key;
+        }
+        let node = &mut self.nodes[key];
+        node.list = list;
+        node.prev = NIL;
+        node.next = head;
+        self.heads[list] = key;
+        if list < PENDING {
+            self.occupied[list / SLOTS] |= 1 << (list % SLOTS);
+        }
+    }
+
+    fn unlink(&mut self, key: usize) {
+        let (list, prev, next) = {
+            let node = &self.nodes[key];
+            (node.list, node.prev, node.next)
+        };
+        if prev == NIL {
+            self.heads[list] = next;
+        } else {
+            self.nodes[prev].next = next;
+        }
+        if next != NIL {
+            self.nodes[next].prev = prev;
+        }
+        if list < PENDING && self.heads[list] == NIL {
+            self.occupied[list / SLOTS] &= !(1 << (list % SLOTS));
+        }
+        self.nodes[key].list = UNLINKED;
+    }
+
+    // put the node into the right slot according to the elapsed tick
+    fn insert(&mut self, key: usize) {
+        let when = self.nodes[key].when;
+        if when <= self.elapsed {
+            return self.link(key, PENDING);
+        }
+
+        // the highest bit that differs from the elapsed tick decides the level
+        let masked = (self.elapsed ^ when) | SLOT_MASK;
+        let level = ((63 - masked.leading_zeros()) / LEVEL_BITS) as usize;
+        // the timers that are too far away stay in the top level
+        let level = level.min(LEVELS - 1);
+        let slot = ((when >> (level as u32 * LEVEL_BITS)) & SLOT_MASK) as usize;
+        self.link(key, level * SLOTS + slot);
+    }
+
+    // find the next non-empty slot and the tick that it should be processed
+    fn next_expiration(&self) -> Option<(usize, u64)> {
+        if self.heads[PENDING] != NIL {
+            return Some((PENDING, self.elapsed));
+        }
+
+        // the lower level always expires earlier than the higher level
+        for level in 0..LEVELS {
+            let occupied = self.occupied[level];
+            if occupied == 0 {
+                continue;
+            }
+
+            let shift = level as u32 * LEVEL_BITS;
+            let slot_range = 1u64 << shift;
+            let level_range = slot_range << LEVEL_BITS;
+            let now_slot = (self.elapsed >> shift) & SLOT_MASK;
+            let zeros = u64::from(occupied.rotate_right(now_slot as u32).trailing_zeros());
+            let slot = (zeros + now_slot) & SLOT_MASK;
+
+            let level_start = self.elapsed & !(level_range - 1);
+            let mut deadline = level_start + slot * slot_range;
+            if deadline <= self.elapsed {
+                // only the far away timers in the top level could wrap
+                deadline += level_range;
+            }
+            return Some((level * SLOTS + slot as usize, deadline));
+        }
+        None
+    }
+
+    // process the wheel to the `now` tick, collect the expired timers
+    fn poll(&mut self, now: u64, expired: &mut Vec<T>) {
+        while let Some((list, deadline)) = self.next_expiration() {
+            if deadline > now {
+                break;
+            }
+            if deadline > self.elapsed {
+                self.elapsed = deadline;
+            }
+
+            // take the whole list, expire or cascade the timers
+            let mut key = mem::replace(&mut self.heads[list], NIL);
+            if list < PENDING {
+                self.occupied[list / SLOTS] &= !(1 << (list % SLOTS));
+            }
+            while key != NIL {
+                let next = self.nodes[key].next;
+                self.nodes[key].list = UNLINKED;
+                if self.nodes[key].when <= now {
+                    expired.extend(self.free(key));
+                } else {
+                    self.insert(key);
+                }
+                key = next;
+            }
+        }
+
+        if now > self.elapsed {
+            self.elapsed = now;
+        }
+        self.next_wake = self.next_expiration().map_or(u64::MAX, |(_, t)| t);
+    }
+}
+
+/// The handle of a timer in the wheel that can be used to cancel it
+pub struct TimerHandle<T> {
+    wheel: Arc<Mutex<Wheel<T>>>,
+    key: usize,
+    gen: u32,
+}
+
+unsafe impl<T: Send> Send for TimerHandle<T> {}
+unsafe impl<T: Send> Sync for TimerHandle<T> {}
+
+impl<T> TimerHandle<T> {
+    // run the function with the timer node if it's not expired or removed
+    fn with_node<R, F>(&self, f: F) -> Option<R>
+    where
+        F: FnOnce(&mut Wheel<T>) -> R,
+    {
+        let mut wheel = self.wheel.lock().unwrap();
+        let node = &wheel.nodes[self.key];
+        if node.gen != self.gen || node.list == UNLINKED {
+            return None;
+        }
+        Some(f(&mut wheel))
+    }
+
+    /// judge if the timer is still in the wheel
+    #[inline]
+    pub fn is_link(&self) -> bool {
+        self.with_node(|_| ()).is_some()
+    }
+
+    /// modify the timer data if the timer is still in the wheel
+    #[inline]
+    pub fn with_mut_data<F>(&self, f: F)
+    where
+        F: FnOnce(&mut T),
+    {
+        let key = self.key;
+        self.with_node(|wheel| wheel.nodes[key].data.as_mut().map(f));
+    }
+
+    /// cancel the timer, return the data if it's not expired yet
+    ///
+    /// this can be called in any thread
+    pub fn remove(self) -> Option<T> {
+        let key = self.key;
+        self.with_node(|wheel| {
+            wheel.unlink(key);
+            wheel.free(key)
+        })
+        .and_then(|data| data)
+    }
+
+    #[inline]
+    pub fn into_ptr(self) -> *mut Self {
+        Box::into_raw(Box::new(self))
+    }
+
+    /// # Safety
+    ///
+    /// Must use the ptr that from `TimerHandle::into_ptr`
+    #[inline]
+    pub unsafe fn from_ptr(ptr: *mut Self) -> Self {
+        *Box::from_raw(ptr)
+    }
+}
+
+/// A timer list based on the hierarchical timing wheel
+///
+/// the timers are rounded up to the tick resolution, so they never expire
+/// earlier than the requested duration.
+pub struct TimerWheel<T> {
+    wheel: Arc<Mutex<Wheel<T>>>,
+    // the tick resolution in ns
+    resolution: u64,
+}
+
+impl<T> Default for TimerWheel<T> {
+    fn default() -> Self {
+        TimerWheel::new()
+    }
+}
+
+impl<T> TimerWheel<T> {
+    /// create a wheel with the configured tick resolution
+    pub fn new() -> Self {
+        TimerWheel::with_resolution(config().get_timer_resolution())
+    }
+
+    /// create a wheel with the given tick resolution
+    pub fn with_resolution(resolution: Duration) -> Self {
+        let resolution = dur_to_ns(resolution).max(1);
+        TimerWheel {
+            wheel: Arc::new(Mutex::new(Wheel::new(now() / resolution))),
+            resolution,
+        }
+    }
+
+    /// add a timeout event to the wheel, this can be called in any thread
+    ///
+    /// return true if the timer expires earlier than all the others,
+    /// which means the caller needs to recall the next expiration
+    pub fn add_timer(&self, dur: Duration, data: T) -> (TimerHandle<T>, bool) {
+        self.add_timer_at(now().saturating_add(dur_to_ns(dur)), data)
+    }
+
+    // add a timer that expires at the wall clock `time` in ns
+    fn add_timer_at(&self, time: u64, data: T) -> (TimerHandle<T>, bool) {
+        // round up to the next tick
+        let when = time.div_ceil(self.resolution);
+
+        let mut wheel = self.wheel.lock().unwrap();
+        let key = wheel.alloc(when, data);
+        wheel.insert(key);
+        let gen = wheel.nodes[key].gen;
+        let when = when.max(wheel.elapsed);
+        let is_head = when < wheel.next_wake;
+        if is_head {
+            wheel.next_wake = when;
+        }
+        drop(wheel);
+
+        let handle = TimerHandle {
+            wheel: self.wheel.clone(),
+            key,
+            gen,
+        };
+        (handle, is_head)
+    }
+
+    /// remove all the expired timers and call the supplied function
+    /// with the registered data
+    ///
+    /// return the time in ns for the next expiration
+    pub fn schedule_timer<F: Fn(T)>(&self, now: u64, f: &F) -> Option<u64> {
+        let mut expired = Vec::new();
+        let next_wake = {
+            let mut wheel = self.wheel.lock().unwrap();
+            wheel.poll(now / self.resolution, &mut expired);
+            wheel.next_wake
+        };
+
+        // the handler may add new timers, so run it without the lock
+        for data in expired {
+            f(data);
+        }
+
+        if next_wake == u64::MAX {
+            return None;
+        }
+        let next = next_wake.saturating_mul(self.resolution);
+        Some(next.saturating_sub(now))
+    }
+
+    /// return the wall clock in ns of the next expiration
+    ///
+    /// this is a lower bound, the timer that expires at this time
+    /// may have been removed already
+    #[cfg(feature = "test-util")]
+    pub fn next_deadline(&self) -> Option<u64> {
+        let next_wake = self.wheel.lock().unwrap().next_wake;
+        if next_wake == u64::MAX {
+            return None;
+        }
+        Some(next_wake.saturating_mul(self.resolution))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    fn poll_at(wheel: &TimerWheel<u64>, now: u64) -> Vec<u64> {
+        let fired = RefCell::new(Vec::new());
+        wheel.schedule_timer(now, &|v| fired.borrow_mut().push(v));
+        let mut fired = fired.into_inner();
+        fired.sort_unstable();
+        fired
+    }
+
+    #[test]
+    fn test_wheel_expire() {
+        let wheel = TimerWheel::with_resolution(Duration::from_nanos(1));
+        let base = wheel.wheel.lock().unwrap().elapsed;
+        let ticks = [1, 63, 64, 65, 4095, 4096, 300_000, 1 << 37];
+        for &t in ticks.iter().rev() {
+            wheel.add_timer_at(base + t, t);
+        }
+
+        for &t in ticks.iter() {
+            // nothing expires before the deadline
+            assert!(poll_at(&wheel, base + t - 1).is_empty());
+            assert_eq!(wheel.schedule_timer(base + t - 1, &|_| ()), Some(1));
+            assert_eq!(poll_at(&wheel, base + t), vec![t]);
+        }
+        assert_eq!(wheel.schedule_timer(base + (1 << 38), &|_| ()), None);
+
+        // the expired timer fires in the next poll
+        let (_, is_head) = wheel.add_timer_at(base, 0);
+        assert!(is_head);
+        assert_eq!(poll_at(&wheel, base + (1 << 38)), vec![0]);
+    }
+
+    #[test]
+    fn test_wheel_cancel() {
+        let wheel = TimerWheel::with_resolution(Duration::from_millis(1));
+        let (h1, is_head) = wheel.add_timer(Duration::from_millis(10), 1);
+        assert!(is_head);
+        let (h2, is_head) = wheel.add_timer(Duration::from_millis(100), 2);
+        assert!(!is_head);
+        let (h3, is_head) = wheel.add_timer(Duration::from_millis(5), 3);
+        assert!(is_head);
+
+        assert!(h2.is_link());
+        assert_eq!(h2.remove(), Some(2));
+        h3.with_mut_data(|v| *v = 30);
+
+        assert_eq!(poll_at(&wheel, now() + 200_000_000), vec![1, 30]);
+        // the timer is already expired
+        assert!(!h1.is_link());
+        assert_eq!(h1.remove(), None);
+    }
+
+    #[test]
+    fn test_wheel_reuse_node() {
+        let wheel = TimerWheel::with_resolution(Duration::from_millis(1));
+        let (h1, _) = wheel.add_timer(Duration::from_millis(10), 1);
+        let h1 = unsafe { TimerHandle::from_ptr(h1.into_ptr()) };
+        assert_eq!(h1.remove(), Some(1));
+
+        // the stale handle would not remove the new timer in the same node
+        let (h2, _) = wheel.add_timer(Duration::from_millis(10), 2);
+        let stale = TimerHandle {
+            wheel: wheel.wheel.clone(),
+            key: h2.key,
+            gen: h2.gen.wrapping_sub(1),
+        };
+        assert_eq!(stale.remove(), None);
+        assert!(h2.is_link());
+        assert_eq!(poll_at(&wheel, now() + 20_000_000), vec![2]);
+    }
+}
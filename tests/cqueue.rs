@@ -228,3 +228,72 @@ fn cqueue_loop() {
 
     assert_eq!(result, 50);
 }
+
+#[test]
+fn cqueue_deadline() {
+    use std::time::Instant;
+
+    cqueue::scope(|cqueue| {
+        // the selectors finish one by one without sending any event
+        for token in 1..10 {
+            go!(cqueue, token, move |_es| {
+                coroutine::sleep(Duration::from_millis(token as u64 * 40))
+            });
+        }
+
+        let start = Instant::now();
+        match cqueue.poll(Some(Duration::from_millis(100))) {
+            Err(x) => assert_eq!(x, Timeout),
+            _ => unreachable!(),
+        }
+        let dur = start.elapsed();
+        assert!(dur >= Duration::from_millis(100));
+        assert!(dur < Duration::from_millis(300));
+    });
+}
+
+#[test]
+fn cqueue_remove() {
+    cqueue::scope(|cqueue| {
+        for token in &[0, 1, 1] {
+            cqueue_add!(cqueue, *token, _ = coroutine::sleep(Duration::from_secs(10)) => {});
+        }
+        assert_eq!(cqueue.len(), 3);
+
+        assert_eq!(cqueue.remove(2), 0);
+        assert_eq!(cqueue.remove(1), 2);
+        match cqueue.poll(Some(Duration::from_millis(50))) {
+            Err(x) => assert_eq!(x, Timeout),
+            _ => unreachable!(),
+        }
+        assert_eq!(cqueue.len(), 1);
+
+        assert_eq!(cqueue.remove(0), 1);
+        match cqueue.poll(None) {
+            Err(x) => assert_eq!(x, Finished),
+            _ => unreachable!(),
+        }
+        assert!(cqueue.is_empty());
+    });
+}
+
+#[test]
+fn cqueue_poll_many() {
+    cqueue::scope(|cqueue| {
+        for token in 0..5 {
+            cqueue_add_oneshot!(cqueue, token, _ = () => {});
+        }
+        // let the selectors run for some time
+        std::thread::sleep(Duration::from_millis(50));
+
+        let mut events = Vec::new();
+        assert_eq!(cqueue.poll_many(&mut events, 3, None), Ok(3));
+        assert_eq!(cqueue.poll_many(&mut events, 10, None), Ok(2));
+        let mut tokens: Vec<usize> = events.iter().map(|ev| ev.token).collect();
+        tokens.sort_unstable();
+        assert_eq!(tokens, [0, 1, 2, 3, 4]);
+
+        let ret = cqueue.poll_many(&mut events, 10, Some(Duration::from_millis(10)));
+        assert_eq!(ret, Err(Finished));
+    });
+}
@@ -81,6 +81,119 @@ fn cancel_io_coroutine() {
     }
 }
 
+#[test]
+fn cancel_shield_coroutine() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let steps = Arc::new(AtomicUsize::new(0));
+    let s = steps.clone();
+    let j = go!(move || {
+        coroutine::cancel_shield(|| {
+            {
+                let _guard = coroutine::CancelGuard::new();
+                // the cancel would not interrupt the shielded sleep
+                coroutine::sleep(Duration::from_millis(100));
+                s.fetch_add(1, Ordering::Relaxed);
+            }
+            // still shielded by the outer scope after the nested guard dropped
+            coroutine::sleep(Duration::from_millis(10));
+            s.fetch_add(1, Ordering::Relaxed);
+        });
+        // the deferred cancel is delivered at the next yield point
+        coroutine::sleep(Duration::from_secs(1000000));
+        s.fetch_add(1, Ordering::Relaxed);
+    });
+
+    // let the coroutine run
+    thread::sleep(Duration::from_millis(10));
+
+    unsafe { j.coroutine().cancel() };
+
+    match j.join() {
+        Ok(_) => panic!("test should return panic"),
+        Err(panic) => {
+            use generator::Error;
+            match panic.downcast_ref::<Error>() {
+                Some(&Error::Cancel) => assert_eq!(steps.load(Ordering::Relaxed), 2),
+                _ => panic!("panic type wrong"),
+            }
+        }
+    }
+}
+
+#[test]
+fn join_timeout_coroutine() {
+    let (tx, rx) = may::sync::mpsc::channel();
+    let j = go!(move || rx.recv().unwrap());
+
+    let j = j.try_join().unwrap_err();
+    let j = j.join_timeout(Duration::from_millis(10)).unwrap_err();
+    tx.send(42).unwrap();
+    let ret = j.join_timeout(Duration::from_secs(10)).unwrap();
+    assert_eq!(ret.unwrap(), 42);
+
+    let j = go!(|| 100);
+    j.wait();
+    assert_eq!(j.try_join().unwrap().unwrap(), 100);
+}
+
+#[test]
+fn abort_coroutine() {
+    let j = go!(coroutine::park);
+    // let the coroutine run
+    thread::sleep(Duration::from_millis(10));
+    let err = j.abort().unwrap_err();
+    assert!(matches!(
+        err.downcast_ref(),
+        Some(&generator::Error::Cancel)
+    ));
+
+    // the finished coroutine is not affected
+    let j = go!(|| 1);
+    j.wait();
+    assert_eq!(j.abort().unwrap(), 1);
+}
+
+#[test]
+fn detached_panic_hook() {
+    let (tx, rx) = std::sync::mpsc::channel();
+    let tx = std::sync::Mutex::new(tx);
+    coroutine::set_detached_panic_hook(move |name, panic| {
+        // only watch the coroutines in this test
+        if let Some(name) = name.filter(|n| n.starts_with("detached")) {
+            let msg = panic.downcast_ref::<&str>().unwrap();
+            tx.lock()
+                .unwrap()
+                .send(format!("{}: {}", name, msg))
+                .unwrap();
+        }
+    });
+
+    let spawn = |name: &str| {
+        let builder = coroutine::Builder::new().name(name.to_owned());
+        unsafe { builder.spawn(|| panic!("boom")) }.unwrap()
+    };
+
+    // dropped before the panic
+    let j = spawn("detached1");
+    drop(j);
+    // dropped after the panic
+    let j = spawn("detached2");
+    j.wait();
+    drop(j);
+    // the joined panic is not reported
+    spawn("detached3").join().unwrap_err();
+
+    let mut reports = vec![
+        rx.recv_timeout(Duration::from_secs(1)).unwrap(),
+        rx.recv_timeout(Duration::from_secs(1)).unwrap(),
+    ];
+    reports.sort();
+    assert_eq!(reports, ["detached1: boom", "detached2: boom"]);
+    assert!(rx.recv_timeout(Duration::from_millis(100)).is_err());
+}
+
 #[test]
 fn one_coroutine() {
     let j = go!(move || {
@@ -345,3 +458,278 @@ fn go_with_macro() {
         assert_eq!(stack_size, 10240);
     }
 }
+
+#[cfg(unix)]
+#[test]
+fn sharded_listener() {
+    use may::net::{TcpListenerBuilder, TcpStream};
+    use std::io::{Read, Write};
+
+    let listeners = TcpListenerBuilder::new()
+        .bind_sharded("127.0.0.1:0")
+        .unwrap();
+    let addr = listeners[0].local_addr().unwrap();
+    for listener in &listeners {
+        assert_eq!(listener.local_addr().unwrap(), addr);
+    }
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    for (id, listener) in listeners.into_iter().enumerate() {
+        let tx = tx.clone();
+        let builder = coroutine::Builder::new().worker(id);
+        unsafe {
+            builder.spawn(move || {
+                for stream in listener.incoming() {
+                    let mut s = stream.unwrap();
+                    tx.send((id, thread::current().id())).unwrap();
+                    let mut buf = [0; 4];
+                    s.read_exact(&mut buf).unwrap();
+                    // yield to give the other workers a chance to steal it
+                    coroutine::yield_now();
+                    s.write_all(&buf).unwrap();
+                    tx.send((id, thread::current().id())).unwrap();
+                }
+            })
+        }
+        .unwrap();
+    }
+
+    for _ in 0..32 {
+        let mut s = TcpStream::connect(addr).unwrap();
+        s.write_all(b"ping").unwrap();
+        let mut buf = [0; 4];
+        s.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"ping");
+    }
+
+    // each shard is always served by the same worker thread
+    let mut workers = std::collections::HashMap::new();
+    for (id, thread) in rx.try_iter() {
+        assert_eq!(*workers.entry(id).or_insert(thread), thread);
+    }
+    let threads: std::collections::HashSet<_> = workers.values().collect();
+    assert_eq!(threads.len(), workers.len());
+}
+
+#[test]
+fn pinned_coroutine() {
+    let ret = unsafe { coroutine::Builder::new().worker(usize::MAX).spawn(|| ()) };
+    assert_eq!(ret.unwrap_err().kind(), std::io::ErrorKind::InvalidInput);
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let j = unsafe {
+        coroutine::Builder::new().worker(0).spawn(move || {
+            for _ in 0..100 {
+                tx.send(thread::current().id()).unwrap();
+                coroutine::sleep(Duration::from_millis(1));
+                coroutine::yield_now();
+            }
+        })
+    }
+    .unwrap();
+    j.join().unwrap();
+
+    // the timer thread wakes it up, but it's always resumed on worker 0
+    let first = rx.recv().unwrap();
+    assert!(rx.try_iter().all(|id| id == first));
+}
+
+#[cfg(unix)]
+#[test]
+fn tcp_socket_options() {
+    use may::net::{TcpListener, TcpSocket};
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let j = go!(move || {
+        let socket = TcpSocket::new_v4().unwrap();
+        socket.set_keepalive(true).unwrap();
+        socket.set_recv_buffer_size(64 * 1024).unwrap();
+        let s = socket.connect(addr).unwrap();
+        assert!(s.keepalive().unwrap());
+
+        s.set_keepalive_idle(Duration::from_secs(30)).unwrap();
+        assert_eq!(s.keepalive_idle().unwrap(), Duration::from_secs(30));
+        s.set_keepalive_interval(Duration::from_secs(5)).unwrap();
+        assert_eq!(s.keepalive_interval().unwrap(), Duration::from_secs(5));
+        s.set_keepalive_count(3).unwrap();
+        assert_eq!(s.keepalive_count().unwrap(), 3);
+        s.set_linger(Some(Duration::from_secs(0))).unwrap();
+        assert_eq!(s.linger().unwrap(), Some(Duration::from_secs(0)));
+        s.set_tos(0x10).unwrap();
+        assert_eq!(s.tos().unwrap(), 0x10);
+    });
+
+    let (_s, _) = listener.accept().unwrap();
+    j.join().unwrap();
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn udp_batch() {
+    use may::net::{RecvMsg, SendMsg, UdpSocket};
+
+    let rx = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let tx = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let rx_addr = rx.local_addr().unwrap();
+    let tx_addr = tx.local_addr().unwrap();
+
+    let j = go!(move || {
+        let mut bufs = [[0u8; 4]; 8];
+        let mut msgs: Vec<RecvMsg> = bufs.iter_mut().map(|b| RecvMsg::new(b)).collect();
+        let mut got = 0;
+        while got < 3 {
+            let n = rx.recv_batch(&mut msgs[got..]).unwrap();
+            assert!(n > 0);
+            got += n;
+        }
+        assert_eq!(msgs[0].data(), b"a");
+        assert_eq!(msgs[1].data(), b"bc");
+        assert_eq!(msgs[2].data(), b"defg");
+        assert!(msgs[2].truncated);
+        assert!(!msgs[0].truncated);
+        assert_eq!(msgs[0].addr, Some(tx_addr));
+    });
+
+    thread::sleep(Duration::from_millis(50));
+    let msgs = [
+        SendMsg::new(b"a", rx_addr),
+        SendMsg::new(b"bc", rx_addr),
+        SendMsg::new(b"defgh", rx_addr),
+    ];
+    assert_eq!(tx.send_batch(&msgs).unwrap(), 3);
+    j.join().unwrap();
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn udp_batch_chunks() {
+    use may::net::{RecvMsg, SendMsg, UdpSocket};
+
+    let rx = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let tx = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let rx_addr = rx.local_addr().unwrap();
+
+    // more than one syscall chunk
+    let data: Vec<[u8; 1]> = (0..40u8).map(|i| [i]).collect();
+    let msgs: Vec<SendMsg> = data.iter().map(|d| SendMsg::new(d, rx_addr)).collect();
+    assert_eq!(tx.send_batch(&msgs).unwrap(), 40);
+
+    let j = go!(move || {
+        let mut bufs = [[0u8; 4]; 64];
+        let mut msgs: Vec<RecvMsg> = bufs.iter_mut().map(|b| RecvMsg::new(b)).collect();
+        let mut got = 0;
+        while got < 40 {
+            let n = rx.recv_batch(&mut msgs[got..]).unwrap();
+            assert!(n > 0);
+            got += n;
+        }
+        assert_eq!(got, 40);
+        for (i, m) in msgs[..40].iter().enumerate() {
+            assert_eq!(m.data(), &[i as u8]);
+        }
+    });
+    j.join().unwrap();
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn udp_msg() {
+    use may::net::{SendMeta, UdpSocket};
+    use std::io::{IoSlice, IoSliceMut};
+    use std::net::{IpAddr, Ipv4Addr};
+
+    let localhost = IpAddr::V4(Ipv4Addr::LOCALHOST);
+    let rx = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let tx = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let rx_addr = rx.local_addr().unwrap();
+    let tx_addr = tx.local_addr().unwrap();
+    rx.set_recv_pktinfo(true).unwrap();
+    rx.set_recv_timestampns(true).unwrap();
+    rx.set_recv_ecn(true).unwrap();
+
+    let j = go!(move || {
+        let (mut head, mut body) = ([0u8; 2], [0u8; 8]);
+        let mut bufs = [IoSliceMut::new(&mut head), IoSliceMut::new(&mut body)];
+        let meta = rx.recv_msg(&mut bufs).unwrap();
+        assert_eq!(meta.len, 5);
+        assert_eq!(meta.addr, Some(tx_addr));
+        assert_eq!(meta.dst_addr, Some(localhost));
+        assert_eq!(meta.ecn, Some(2));
+        assert!(meta.timestamp.is_some());
+        assert!(!meta.truncated);
+        assert_eq!(&head, b"he");
+        assert_eq!(&body[..3], b"llo");
+    });
+
+    thread::sleep(Duration::from_millis(50));
+    let meta = SendMeta {
+        src_addr: Some(localhost),
+        ecn: Some(2),
+        ..SendMeta::default()
+    };
+    let bufs = [IoSlice::new(b"hel"), IoSlice::new(b"lo")];
+    assert_eq!(tx.send_msg(&bufs, Some(rx_addr), &meta).unwrap(), 5);
+    j.join().unwrap();
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn raw_socket() {
+    use may::net::raw::{Domain, RawSocket, SockAddr, Type};
+    use std::io::{IoSlice, IoSliceMut};
+    use std::mem;
+    use std::os::unix::io::AsRawFd;
+
+    // a udp socket is enough to drive the raw socket apis
+    let any: SockAddr = "127.0.0.1:0"
+        .parse::<std::net::SocketAddr>()
+        .unwrap()
+        .into();
+    let rx = RawSocket::new(Domain::ipv4(), Type::dgram(), None).unwrap();
+    rx.bind(&any).unwrap();
+    let tx = RawSocket::new(Domain::ipv4(), Type::dgram(), None).unwrap();
+    tx.bind(&any).unwrap();
+    let rx_addr = rx.local_addr().unwrap();
+    let tx_addr = tx.local_addr().unwrap().as_std();
+    let on: libc::c_int = 1;
+    let ret = unsafe {
+        libc::setsockopt(
+            rx.as_raw_fd(),
+            libc::IPPROTO_IP,
+            libc::IP_PKTINFO,
+            &on as *const _ as *const libc::c_void,
+            mem::size_of_val(&on) as libc::socklen_t,
+        )
+    };
+    assert_eq!(ret, 0);
+
+    let j = go!(move || {
+        let mut buf = [0u8; 16];
+        let (n, addr) = rx.recv_from(&mut buf).unwrap();
+        assert_eq!(&buf[..n], b"ping");
+        assert_eq!(addr.as_std(), tx_addr);
+
+        let mut control = [0u64; 16];
+        let control =
+            unsafe { std::slice::from_raw_parts_mut(control.as_mut_ptr() as *mut u8, 128) };
+        let meta = rx
+            .recv_msg(&mut [IoSliceMut::new(&mut buf)], control)
+            .unwrap();
+        assert_eq!(&buf[..meta.len], b"pong");
+        assert!(meta.control_len > 0);
+        assert!(!meta.truncated && !meta.ctrl_truncated);
+        let cmsg = control.as_ptr() as *const libc::cmsghdr;
+        unsafe {
+            assert_eq!((*cmsg).cmsg_level, libc::IPPROTO_IP);
+            assert_eq!((*cmsg).cmsg_type, libc::IP_PKTINFO);
+        }
+    });
+
+    thread::sleep(Duration::from_millis(50));
+    assert_eq!(tx.send_to(b"ping", &rx_addr).unwrap(), 4);
+    let bufs = [IoSlice::new(b"po"), IoSlice::new(b"ng")];
+    assert_eq!(tx.send_msg(&bufs, Some(&rx_addr), &[]).unwrap(), 4);
+    j.join().unwrap();
+}
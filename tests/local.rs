@@ -50,3 +50,68 @@ fn coroutine_local_many() {
         assert_eq!(f.load(Ordering::Relaxed), 0);
     });
 }
+
+#[test]
+fn coroutine_local_set_take() {
+    coroutine_local!(static FOO: Vec<u32> = vec![1]);
+
+    go!(|| {
+        // set without initialization
+        FOO.set(vec![2]);
+        FOO.with(|f| assert_eq!(*f, [2]));
+        assert_eq!(FOO.replace(vec![3]), [2]);
+        assert_eq!(FOO.take(), [3]);
+        FOO.with(|f| assert!(f.is_empty()));
+    })
+    .join()
+    .unwrap();
+
+    // replace would initialize the value first
+    let old = go!(|| FOO.replace(vec![4])).join().unwrap();
+    assert_eq!(old, [1]);
+}
+
+#[test]
+fn coroutine_local_set_in_with() {
+    coroutine_local!(static FOO: u32 = 0);
+
+    let ret = go!(|| FOO.with(|_| FOO.set(1))).join();
+    assert!(ret.is_err());
+}
+
+#[test]
+fn coroutine_local_inherit() {
+    coroutine_local!(inherit static ID: String = String::new());
+    coroutine_local!(static OTHER: u32 = 0);
+
+    let ret = go!(|| {
+        ID.set("parent".to_owned());
+        OTHER.set(1);
+        let child = go!(|| {
+            let id = ID.with(|id| id.clone());
+            // the grandchild inherits the value of the child
+            ID.set("child".to_owned());
+            let grandchild = go!(|| ID.with(|id| id.clone())).join().unwrap();
+            (id, grandchild, OTHER.with(|o| *o))
+        })
+        .join()
+        .unwrap();
+        // the child has its own copy
+        (child, ID.take())
+    })
+    .join()
+    .unwrap();
+
+    assert_eq!(
+        ret,
+        (
+            ("parent".to_owned(), "child".to_owned(), 0),
+            "parent".to_owned()
+        )
+    );
+
+    // inherited from the thread context
+    ID.set("thread".to_owned());
+    let id = go!(|| ID.with(|id| id.clone())).join().unwrap();
+    assert_eq!(id, "thread");
+}
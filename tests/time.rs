@@ -0,0 +1,133 @@
+#[macro_use]
+extern crate may;
+
+use may::sync::mpsc::channel;
+use may::time::{self, MissedTickBehavior};
+use std::time::{Duration, Instant};
+
+#[test]
+fn sleep_until() {
+    let h = go!(|| {
+        let deadline = Instant::now() + Duration::from_millis(50);
+        time::sleep_until(deadline);
+        assert!(Instant::now() >= deadline);
+        // a passed deadline returns immediately
+        let now = Instant::now();
+        time::sleep_until(now - Duration::from_millis(10));
+        assert!(now.elapsed() < Duration::from_millis(10));
+    });
+    h.join().unwrap();
+}
+
+#[test]
+fn interval_tick() {
+    let h = go!(|| {
+        let period = Duration::from_millis(20);
+        let start = Instant::now();
+        let mut interval = time::interval(period);
+        let first = interval.tick();
+        assert!(first.elapsed() < period);
+        for i in 1..5 {
+            let tick = interval.tick();
+            // the ticks are on the schedule without drift
+            assert_eq!(tick, first + period * i);
+            assert!(Instant::now() >= start + period * i);
+        }
+    });
+    h.join().unwrap();
+}
+
+#[test]
+fn interval_missed_tick() {
+    let period = Duration::from_millis(20);
+    let lag = Duration::from_millis(50);
+
+    // burst fires all the missed ticks immediately
+    let mut interval = time::interval(period);
+    let first = interval.tick();
+    std::thread::sleep(lag);
+    assert_eq!(interval.tick(), first + period);
+    assert_eq!(interval.tick(), first + period * 2);
+
+    // delay reschedules from the time that the tick fired
+    let mut interval = time::interval(period);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    interval.tick();
+    std::thread::sleep(lag);
+    let before = Instant::now();
+    let tick = interval.tick();
+    assert!(tick < before);
+    assert!(interval.tick() >= before + period);
+
+    // skip keeps the ticks on the original schedule
+    let mut interval = time::interval(period);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    let first = interval.tick();
+    std::thread::sleep(lag);
+    assert_eq!(interval.tick(), first + period);
+    assert_eq!(interval.tick(), first + period * 3);
+}
+
+#[test]
+fn timeout_elapsed() {
+    let h = go!(|| {
+        let start = Instant::now();
+        let ret = time::timeout(Duration::from_millis(50), || {
+            time::sleep(Duration::from_secs(10));
+        });
+        assert!(ret.is_err());
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        // the coroutine can still block after the timeout
+        time::sleep(Duration::from_millis(10));
+    });
+    h.join().unwrap();
+}
+
+#[test]
+fn timeout_complete() {
+    let h = go!(|| {
+        let ret = time::timeout(Duration::from_millis(100), || {
+            time::sleep(Duration::from_millis(10));
+            42
+        });
+        assert_eq!(ret, Ok(42));
+
+        // the watcher is stopped, the coroutine is not cancelled later
+        time::sleep(Duration::from_millis(200));
+    });
+    h.join().unwrap();
+}
+
+#[test]
+fn timeout_recv() {
+    let (tx, rx) = channel::<u32>();
+    let h = go!(move || {
+        let ret = time::timeout(Duration::from_millis(50), || rx.recv().unwrap());
+        let err: std::io::Error = ret.unwrap_err().into();
+        assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+
+        // the channel is still usable after the timeout
+        rx.recv().unwrap()
+    });
+    time::sleep(Duration::from_millis(100));
+    tx.send(5).unwrap();
+    assert_eq!(h.join().unwrap(), 5);
+}
+
+#[test]
+fn timeout_panic() {
+    let h = go!(|| {
+        let _ = time::timeout(Duration::from_millis(100), || panic!("panic in timeout"));
+    });
+    assert!(h.join().is_err());
+}
+
+#[test]
+fn timeout_in_thread() {
+    let ret = time::timeout(Duration::from_millis(10), || {
+        time::sleep(Duration::from_millis(20));
+        1
+    });
+    assert_eq!(ret, Ok(1));
+}
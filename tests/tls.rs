@@ -0,0 +1,185 @@
+#![cfg(feature = "tls")]
+#[macro_use]
+extern crate may;
+
+use may::net::tls::rustls::pki_types::pem::PemObject;
+use may::net::tls::rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+use may::net::tls::rustls::{ClientConfig, RootCertStore, ServerConfig};
+use may::net::tls::{TlsAcceptor, TlsConnector, TlsStream};
+use may::net::{TcpListener, TcpStream};
+
+use std::convert::TryFrom;
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+const CERT_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/cert");
+
+// the handshake needs much more stack than the default coroutine stack
+fn init() {
+    may::config().set_stack_size(0x8000);
+}
+
+fn acceptor() -> TlsAcceptor {
+    let certs = CertificateDer::pem_file_iter(format!("{}/server.pem", CERT_DIR))
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    let key = PrivateKeyDer::from_pem_file(format!("{}/server.key", CERT_DIR)).unwrap();
+    let config = ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .unwrap();
+    TlsAcceptor::new(Arc::new(config))
+}
+
+fn connector() -> TlsConnector {
+    let mut roots = RootCertStore::empty();
+    for cert in CertificateDer::pem_file_iter(format!("{}/ca.pem", CERT_DIR)).unwrap() {
+        roots.add(cert.unwrap()).unwrap();
+    }
+    let config = ClientConfig::builder()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    TlsConnector::new(Arc::new(config))
+}
+
+fn connect(addr: SocketAddr) -> io::Result<TlsStream> {
+    let tcp = TcpStream::connect(addr)?;
+    let name = ServerName::try_from("localhost").unwrap();
+    connector().connect(name, tcp)
+}
+
+#[test]
+fn tls_echo() {
+    init();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let acceptor = acceptor();
+
+    let server = go!(move || {
+        let (tcp, _) = listener.accept().unwrap();
+        let mut stream = acceptor.accept(tcp).unwrap();
+        let mut buf = [0; 1024];
+        loop {
+            let n = stream.read(&mut buf).unwrap();
+            if n == 0 {
+                break;
+            }
+            stream.write_all(&buf[..n]).unwrap();
+        }
+    });
+
+    let client = go!(move || {
+        let mut stream = connect(addr).unwrap();
+        let msg = vec![b'x'; 64 * 1024];
+        stream.write_all(&msg).unwrap();
+        let mut rsp = vec![0; msg.len()];
+        stream.read_exact(&mut rsp).unwrap();
+        assert_eq!(rsp, msg);
+        stream.shutdown(Shutdown::Write).unwrap();
+    });
+
+    client.join().unwrap();
+    server.join().unwrap();
+}
+
+#[test]
+fn tls_bad_name() {
+    init();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let acceptor = acceptor();
+
+    let server = go!(move || {
+        let (tcp, _) = listener.accept().unwrap();
+        assert!(acceptor.accept(tcp).is_err());
+    });
+
+    let tcp = TcpStream::connect(addr).unwrap();
+    let name = ServerName::try_from("example.com").unwrap();
+    let err = connector().connect(name, tcp).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    server.join().unwrap();
+}
+
+#[test]
+fn tls_read_timeout() {
+    init();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let acceptor = acceptor();
+
+    let server = go!(move || {
+        let (tcp, _) = listener.accept().unwrap();
+        let mut stream = acceptor.accept(tcp).unwrap();
+        // wait for the client to close
+        let mut buf = [0; 16];
+        assert_eq!(stream.read(&mut buf).unwrap(), 0);
+    });
+
+    let client = go!(move || {
+        let mut stream = connect(addr).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_millis(100)))
+            .unwrap();
+        assert_eq!(
+            stream.read_timeout().unwrap(),
+            Some(Duration::from_millis(100))
+        );
+        let mut buf = [0; 16];
+        let err = stream.read(&mut buf).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        stream.shutdown(Shutdown::Both).unwrap();
+    });
+
+    client.join().unwrap();
+    server.join().unwrap();
+}
+
+#[test]
+fn tls_split() {
+    init();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let acceptor = acceptor();
+
+    let server = go!(move || {
+        let (tcp, _) = listener.accept().unwrap();
+        let mut stream = acceptor.accept(tcp).unwrap();
+        let mut buf = [0; 1024];
+        loop {
+            let n = stream.read(&mut buf).unwrap();
+            if n == 0 {
+                break;
+            }
+            stream.write_all(&buf[..n]).unwrap();
+        }
+        stream.shutdown(Shutdown::Write).unwrap();
+    });
+
+    let mut stream = connect(addr).unwrap();
+    let mut reader = stream.try_clone().unwrap();
+    // the reader is blocked in another coroutine while writing
+    let read = go!(move || {
+        let mut rsp = Vec::new();
+        reader.read_to_end(&mut rsp).unwrap();
+        rsp
+    });
+
+    for i in 0..100u8 {
+        stream.write_all(&[i; 100]).unwrap();
+        coroutine_sleep();
+    }
+    stream.shutdown(Shutdown::Write).unwrap();
+
+    let rsp = read.join().unwrap();
+    let expect = (0..100u8).flat_map(|i| vec![i; 100]).collect::<Vec<_>>();
+    assert_eq!(rsp, expect);
+    server.join().unwrap();
+}
+
+fn coroutine_sleep() {
+    may::coroutine::sleep(Duration::from_millis(1));
+}
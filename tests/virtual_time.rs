@@ -0,0 +1,108 @@
+#![cfg(feature = "test-util")]
+
+#[macro_use]
+extern crate may;
+
+use std::io::ErrorKind;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use may::sync::mpsc::channel;
+use may::time;
+
+// the clock is global, the tests can't run in parallel
+static CLOCK: Mutex<()> = Mutex::new(());
+
+fn paused_clock() -> std::sync::MutexGuard<'static, ()> {
+    let guard = CLOCK.lock().unwrap_or_else(|e| e.into_inner());
+    time::pause();
+    guard
+}
+
+#[test]
+fn manual_advance() {
+    let _clock = paused_clock();
+    let real = Instant::now();
+    let start = time::now();
+
+    let done = Arc::new(AtomicBool::new(false));
+    let d = done.clone();
+    let (tx, rx) = channel();
+    let h = go!(move || {
+        tx.send(()).unwrap();
+        time::sleep(Duration::from_secs(3600));
+        d.store(true, Ordering::Release);
+    });
+    // make sure the timer is registered before advancing the clock
+    rx.recv().unwrap();
+
+    // the paused clock doesn't move with the real time
+    thread::sleep(Duration::from_millis(50));
+    assert_eq!(time::now(), start);
+    assert!(!done.load(Ordering::Acquire));
+
+    time::advance(Duration::from_secs(1800));
+    thread::sleep(Duration::from_millis(50));
+    assert!(!done.load(Ordering::Acquire));
+
+    time::advance(Duration::from_secs(1800));
+    h.join().unwrap();
+    assert!(done.load(Ordering::Acquire));
+    assert_eq!(time::now() - start, Duration::from_secs(3600));
+    assert!(real.elapsed() < Duration::from_secs(5));
+
+    time::resume();
+    assert!(!time::is_paused());
+    assert!(time::now() - start >= Duration::from_secs(3600));
+}
+
+#[test]
+fn auto_advance() {
+    let _clock = paused_clock();
+    time::set_auto_advance(true);
+    let real = Instant::now();
+    let start = time::now();
+
+    // a retry schedule that takes more than a day
+    let h = go!(|| {
+        let (_tx, rx) = channel::<u32>();
+        let mut backoff = Duration::from_secs(60);
+        for _ in 0..10 {
+            let err = rx.recv_timeout(backoff).unwrap_err();
+            assert_eq!(err, std::sync::mpsc::RecvTimeoutError::Timeout);
+            backoff *= 2;
+        }
+        time::now()
+    });
+    let end = h.join().unwrap();
+    assert!(end - start >= Duration::from_secs(60 * 1023));
+
+    // the io timeouts are driven by the same clock
+    let listener = may::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let h = go!(move || {
+        use std::io::Read;
+        let mut s = may::net::TcpStream::connect(addr).unwrap();
+        s.set_read_timeout(Some(Duration::from_secs(7200))).unwrap();
+        let start = time::now();
+        let err = s.read(&mut [0; 8]).unwrap_err();
+        assert!(err.kind() == ErrorKind::TimedOut || err.kind() == ErrorKind::WouldBlock);
+        time::now() - start
+    });
+    let _conn = listener.accept().unwrap();
+    assert!(h.join().unwrap() >= Duration::from_secs(7200));
+
+    assert!(real.elapsed() < Duration::from_secs(10));
+    time::set_auto_advance(false);
+    time::resume();
+}
+
+#[test]
+#[should_panic(expected = "the clock is not paused")]
+fn advance_not_paused() {
+    let _clock = CLOCK.lock().unwrap_or_else(|e| e.into_inner());
+    time::resume();
+    time::advance(Duration::from_secs(1));
+}